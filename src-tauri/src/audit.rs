@@ -0,0 +1,382 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::settings::{self, Settings};
+
+// Hash the first entry links to
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+// Sequence number and hash of the newest entry, loaded from disk on first use.
+// The lock also serializes appends so the chain never forks.
+static CHAIN_HEAD: once_cell::sync::Lazy<std::sync::Mutex<Option<(u64, String)>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub seq: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub action: String,
+    pub target: String,
+    pub success: bool,
+    pub details: serde_json::Value,
+    pub prev_hash: String,
+    pub hash: String,
+}
+
+// Everything covered by an entry's hash, in a fixed field order
+#[derive(Serialize)]
+struct HashedFields<'a> {
+    seq: u64,
+    timestamp: &'a chrono::DateTime<chrono::Utc>,
+    action: &'a str,
+    target: &'a str,
+    success: bool,
+    details: &'a serde_json::Value,
+    prev_hash: &'a str,
+}
+
+impl AuditEntry {
+    fn compute_hash(&self) -> String {
+        let fields = HashedFields {
+            seq: self.seq,
+            timestamp: &self.timestamp,
+            action: &self.action,
+            target: &self.target,
+            success: self.success,
+            details: &self.details,
+            prev_hash: &self.prev_hash,
+        };
+        let bytes = serde_json::to_vec(&fields).unwrap_or_default();
+        format!("{:x}", Sha256::digest(bytes))
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub valid: bool,
+    pub entries: u64,
+    // Line of the first entry that breaks the chain
+    pub first_invalid_line: Option<u64>,
+    pub reason: Option<String>,
+}
+
+fn audit_path() -> PathBuf {
+    settings::data_dir().join("audit.log")
+}
+
+// Append an entry linked to the previous one by its hash
+pub fn record(action: &str, target: &str, success: bool, details: serde_json::Value) {
+    if let Err(e) = append(action, target, success, details) {
+        log::error!("Failed to write audit entry: {}", e);
+    }
+}
+
+fn append(action: &str, target: &str, success: bool, details: serde_json::Value) -> Result<(), String> {
+    let mut head = CHAIN_HEAD.lock().unwrap();
+    append_to(&audit_path(), &mut head, action, target, success, details)
+}
+
+fn append_to(
+    path: &Path,
+    head: &mut Option<(u64, String)>,
+    action: &str,
+    target: &str,
+    success: bool,
+    details: serde_json::Value,
+) -> Result<(), String> {
+    if head.is_none() {
+        *head = Some(load_head(path)?);
+    }
+    let (last_seq, last_hash) = head.clone().expect("chain head loaded above");
+
+    let mut entry = AuditEntry {
+        seq: last_seq + 1,
+        timestamp: chrono::Utc::now(),
+        action: action.to_string(),
+        target: target.to_string(),
+        success,
+        details,
+        prev_hash: last_hash,
+        hash: String::new(),
+    };
+    entry.hash = entry.compute_hash();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let line = serde_json::to_string(&entry)
+        .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)
+        .map_err(|e| format!("Failed to open audit log: {}", e))?;
+    writeln!(file, "{}", line)
+        .and_then(|_| file.sync_data())
+        .map_err(|e| format!("Failed to append to audit log: {}", e))?;
+
+    *head = Some((entry.seq, entry.hash));
+    Ok(())
+}
+
+// Read the newest entry to continue the chain. A log whose last line is not a
+// chained entry is left as evidence and nothing more is appended until it is repaired;
+// `verify` reports where the chain breaks.
+fn load_head(path: &Path) -> Result<(u64, String), String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((0, GENESIS_HASH.to_string())),
+        Err(e) => return Err(format!("Failed to read audit log: {}", e)),
+    };
+
+    let last_line = BufReader::new(file).lines()
+        .map_while(Result::ok)
+        .filter(|line| !line.trim().is_empty())
+        .last();
+
+    match last_line.map(|line| serde_json::from_str::<AuditEntry>(&line)) {
+        None => Ok((0, GENESIS_HASH.to_string())),
+        Some(Ok(entry)) => Ok((entry.seq, entry.hash)),
+        Some(Err(e)) => Err(format!("Audit chain is broken, refusing to append: last entry is unreadable: {}", e)),
+    }
+}
+
+pub fn entries() -> Result<Vec<AuditEntry>, String> {
+    let content = match fs::read_to_string(audit_path()) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read audit log: {}", e)),
+    };
+
+    Ok(content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+// Walk the whole log checking sequence numbers, hashes and links
+pub fn verify() -> Result<VerifyReport, String> {
+    let _head = CHAIN_HEAD.lock().unwrap();
+    verify_file(&audit_path())
+}
+
+fn verify_file(path: &Path) -> Result<VerifyReport, String> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Ok(VerifyReport { valid: true, entries: 0, first_invalid_line: None, reason: None });
+        }
+        Err(e) => return Err(format!("Failed to read audit log: {}", e)),
+    };
+
+    let mut expected_prev = GENESIS_HASH.to_string();
+    let mut expected_seq = 1;
+    let mut count = 0;
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read audit log: {}", e))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index as u64 + 1;
+        let invalid = |reason: String| VerifyReport {
+            valid: false,
+            entries: count,
+            first_invalid_line: Some(line_number),
+            reason: Some(reason),
+        };
+
+        let entry: AuditEntry = match serde_json::from_str(&line) {
+            Ok(entry) => entry,
+            Err(e) => return Ok(invalid(format!("Unreadable entry: {}", e))),
+        };
+        if entry.seq != expected_seq {
+            return Ok(invalid(format!("Expected sequence {} but found {}", expected_seq, entry.seq)));
+        }
+        if entry.prev_hash != expected_prev {
+            return Ok(invalid("Entry does not link to the previous entry".to_string()));
+        }
+        if entry.compute_hash() != entry.hash {
+            return Ok(invalid("Entry contents do not match its hash".to_string()));
+        }
+
+        expected_prev = entry.hash;
+        expected_seq += 1;
+        count += 1;
+    }
+
+    Ok(VerifyReport { valid: true, entries: count, first_invalid_line: None, reason: None })
+}
+
+// Write the entries within a date range to a JSON-lines file. The range stays
+// verifiable on its own from the prev_hash of its first entry.
+pub fn export_range(
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    destination: &Path,
+) -> Result<usize, String> {
+    let selected: Vec<AuditEntry> = entries()?.into_iter()
+        .filter(|entry| from.is_none_or(|from| entry.timestamp >= from))
+        .filter(|entry| to.is_none_or(|to| entry.timestamp <= to))
+        .collect();
+
+    let mut content = String::new();
+    for entry in &selected {
+        let line = serde_json::to_string(entry)
+            .map_err(|e| format!("Failed to serialize audit entry: {}", e))?;
+        content.push_str(&line);
+        content.push('\n');
+    }
+    fs::write(destination, content)
+        .map_err(|e| format!("Failed to write audit export: {}", e))?;
+
+    Ok(selected.len())
+}
+
+// Record which settings changed, with the API key redacted
+pub fn record_settings_change(previous: &Settings, updated: &Settings) {
+    let (Ok(serde_json::Value::Object(before)), Ok(serde_json::Value::Object(after))) =
+        (serde_json::to_value(previous), serde_json::to_value(updated))
+    else {
+        return;
+    };
+
+    let changed: Vec<&String> = after.iter()
+        .filter(|(key, value)| before.get(*key) != Some(value))
+        .map(|(key, _)| key)
+        .collect();
+    if changed.is_empty() {
+        return;
+    }
+
+    record("settings_changed", "settings", true, serde_json::json!({ "fields": changed }));
+    if previous.api_key != updated.api_key {
+        record_api_key_change(&updated.api_key);
+    }
+}
+
+pub fn record_api_key_change(new_key: &str) {
+    // Only enough of the key to tell keys apart
+    let suffix: String = new_key.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    record("api_key_changed", "api_key", true, serde_json::json!({ "key_suffix": suffix }));
+}
+
+// Tauri commands for the audit log
+#[tauri::command]
+pub async fn get_audit_log() -> Result<Vec<AuditEntry>, String> {
+    entries()
+}
+
+#[tauri::command]
+pub async fn verify_audit_log() -> Result<VerifyReport, String> {
+    verify()
+}
+
+#[tauri::command]
+pub async fn export_audit_log(
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    destination: Option<String>,
+) -> Result<String, String> {
+    let destination = match destination {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = Settings::load()?.export_path
+                .map(PathBuf::from)
+                .or_else(dirs::download_dir)
+                .unwrap_or_else(|| PathBuf::from("."));
+            dir.join(format!("audit-{}.jsonl", chrono::Local::now().format("%Y%m%d-%H%M%S")))
+        }
+    };
+
+    export_range(from, to, &destination)?;
+    Ok(destination.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    // A log of `count` chained entries, as written by the app
+    fn chained_log(count: usize) -> (TempDir, PathBuf) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("audit.log");
+        let mut head = None;
+        for index in 0..count {
+            append_to(&path, &mut head, "scan_verdict", &format!("/tmp/file-{}", index), true, serde_json::json!({ "index": index })).unwrap();
+        }
+        (dir, path)
+    }
+
+    fn lines(path: &Path) -> Vec<String> {
+        fs::read_to_string(path).unwrap().lines().map(String::from).collect()
+    }
+
+    fn write_lines(path: &Path, lines: &[String]) {
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+    }
+
+    #[test]
+    fn an_untouched_chain_verifies() {
+        let (_dir, path) = chained_log(4);
+        let report = verify_file(&path).unwrap();
+        assert!(report.valid);
+        assert_eq!(report.entries, 4);
+    }
+
+    #[test]
+    fn an_edited_record_is_detected() {
+        let (_dir, path) = chained_log(4);
+        let mut lines = lines(&path);
+        lines[1] = lines[1].replace("/tmp/file-1", "/tmp/innocent");
+        write_lines(&path, &lines);
+
+        let report = verify_file(&path).unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.first_invalid_line, Some(2));
+        assert_eq!(report.reason.as_deref(), Some("Entry contents do not match its hash"));
+    }
+
+    #[test]
+    fn a_deleted_line_is_detected() {
+        let (_dir, path) = chained_log(4);
+        let mut lines = lines(&path);
+        lines.remove(2);
+        write_lines(&path, &lines);
+
+        let report = verify_file(&path).unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.first_invalid_line, Some(3));
+        assert_eq!(report.reason.as_deref(), Some("Expected sequence 3 but found 4"));
+    }
+
+    #[test]
+    fn a_reordered_line_is_detected() {
+        let (_dir, path) = chained_log(4);
+        let mut lines = lines(&path);
+        lines.swap(1, 2);
+        write_lines(&path, &lines);
+
+        let report = verify_file(&path).unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.first_invalid_line, Some(2));
+        assert_eq!(report.reason.as_deref(), Some("Expected sequence 2 but found 3"));
+    }
+
+    #[test]
+    fn appending_after_an_unreadable_last_line_is_refused() {
+        let (_dir, path) = chained_log(2);
+        let mut written = lines(&path);
+        written.push("{\"seq\": 3, truncated".to_string());
+        write_lines(&path, &written);
+
+        let error = load_head(&path).unwrap_err();
+        assert!(error.starts_with("Audit chain is broken, refusing to append"), "{}", error);
+
+        let mut head = None;
+        assert!(append_to(&path, &mut head, "quarantine", "/tmp/file", true, serde_json::Value::Null).is_err());
+        assert_eq!(lines(&path), written);
+    }
+}
+
@@ -1,129 +1,528 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tokio::sync::{Mutex, Semaphore};
-use tokio::time::{Duration, sleep};
-use futures::stream::{self, StreamExt};
-use crate::settings::Settings;
-use crate::virus_total::scan_file_internal;
-use crate::{AppState, ScanResult, ScanStatus};
-use crate::scanner_config::ScannerConfig;
+use serde::Serialize;
+use serde_json::json;
+use tauri::async_runtime::JoinHandle;
+use tauri::AppHandle;
+use tokio::sync::{broadcast, watch, Mutex, Notify, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::time::{sleep, Duration};
+use crate::config::ScannerConfig;
+use crate::host::Host;
+use crate::scan_queue::{QueuedScan, ScanPriority, ScanQueue};
+use crate::settings::{self, Settings};
+use crate::throttle::{ResourceMonitor, ResourceSample, ThrottleReason, ThrottleState};
+use crate::virus_total::{self, scan_file_internal, ScanError, ScanResult};
+use crate::AppState;
+use crate::{ErrorCategory, Subsystem};
 
+// Completions buffered for each subscriber before the oldest are dropped
+const COMPLETION_BACKLOG: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BackgroundScannerStatus {
+    pub running: bool,
+    pub queued: usize,
+    pub active: usize,
+    pub max_concurrent_scans: usize,
+    pub throttle: ThrottleStatus,
+}
+
+// Sent when a queued scan finishes, for whoever is waiting on particular files
+#[derive(Debug, Clone)]
+pub struct ScanCompletion {
+    pub path: PathBuf,
+    pub file_hash: Option<String>,
+    pub result: Result<ScanResult, String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ThrottleStatus {
+    #[serde(flatten)]
+    pub state: ThrottleState,
+    pub sample: ResourceSample,
+}
+
+// Permits in existence, free or held, against the configured limit. Surplus permits are
+// retired as soon as they are free.
+#[derive(Debug)]
+struct PermitBudget {
+    issued: usize,
+    target: usize,
+}
+
+#[derive(Clone)]
 pub struct BackgroundScanner {
-    state: Arc<AppState>,
-    config: Arc<Mutex<ScannerConfig>>,
+    config: Arc<RwLock<ScannerConfig>>,
+    scanning: Arc<AtomicBool>,
+    active_scans: Arc<AtomicUsize>,
+    scan_queue: Arc<Mutex<ScanQueue>>,
     scan_semaphore: Arc<Semaphore>,
+    permits: Arc<std::sync::Mutex<PermitBudget>>,
+    wake: Arc<Notify>,
+    paused: Arc<AtomicBool>,
+    throttle: Arc<std::sync::Mutex<(Vec<ThrottleReason>, ResourceSample)>>,
+    gate: Arc<watch::Sender<ThrottleState>>,
+    // Loops of the current run, aborted if a new run starts before they noticed a stop
+    tasks: Arc<std::sync::Mutex<Vec<JoinHandle<()>>>>,
+    completions: broadcast::Sender<ScanCompletion>,
+}
+
+impl Default for BackgroundScanner {
+    fn default() -> Self {
+        Self::new(ScannerConfig::default())
+    }
 }
 
 impl BackgroundScanner {
-    pub fn new(state: Arc<AppState>) -> Self {
-        let config = Arc::new(Mutex::new(ScannerConfig::default()));
-        let scan_semaphore = Arc::new(Semaphore::new(config.blocking_lock().max_concurrent_scans));
-        Self { 
-            state,
-            config,
+    pub fn new(config: ScannerConfig) -> Self {
+        let limit = config.max_concurrent_scans;
+        let scan_semaphore = Arc::new(Semaphore::new(limit));
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            scanning: Arc::new(AtomicBool::new(false)),
+            active_scans: Arc::new(AtomicUsize::new(0)),
+            scan_queue: Arc::new(Mutex::new(ScanQueue::default())),
             scan_semaphore,
+            permits: Arc::new(std::sync::Mutex::new(PermitBudget { issued: limit, target: limit })),
+            wake: Arc::new(Notify::new()),
+            paused: Arc::new(AtomicBool::new(false)),
+            throttle: Arc::new(std::sync::Mutex::new((Vec::new(), ResourceSample::default()))),
+            gate: Arc::new(watch::channel(ThrottleState::Running).0),
+            tasks: Arc::new(std::sync::Mutex::new(Vec::new())),
+            completions: broadcast::channel(COMPLETION_BACKLOG).0,
+        }
+    }
+
+    // Swap in a new config without waiting for running scans, resizing the semaphore to match
+    pub async fn set_config(&self, config: ScannerConfig) {
+        let limit = config.max_concurrent_scans;
+        *self.config.write().await = config;
+
+        let mut budget = self.permits.lock().unwrap();
+        budget.target = limit;
+        if budget.issued < limit {
+            self.scan_semaphore.add_permits(limit - budget.issued);
+            budget.issued = limit;
+        } else {
+            // Free permits go now, the rest when running scans hand them back
+            budget.issued -= self.scan_semaphore.forget_permits(budget.issued - limit);
+        }
+        drop(budget);
+
+        self.wake.notify_one();
+    }
+
+    // Hand a scan's permit back, or retire it while more are issued than configured
+    fn release(&self, permit: OwnedSemaphorePermit) {
+        let mut budget = self.permits.lock().unwrap();
+        if budget.issued > budget.target {
+            permit.forget();
+            budget.issued -= 1;
+        } else {
+            drop(permit);
+        }
+    }
+
+    pub fn start_scanning(&self, host: Host) -> bool {
+        // Held while spawning so a concurrent start or stop cannot interleave
+        let mut tasks = self.tasks.lock().unwrap();
+        if self.scanning.compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst).is_err() {
+            return false;
+        }
+
+        // Loops from a run stopped moments ago may not have seen the flag yet.
+        // Scans they already started are separate tasks and still finish.
+        for task in tasks.drain(..) {
+            task.abort();
+        }
+
+        let scanner = self.clone();
+        let resource_host = host.clone();
+        tasks.push(tauri::async_runtime::spawn(async move {
+            scanner.scanning_loop(host).await;
+        }));
+
+        let scanner = self.clone();
+        tasks.push(tauri::async_runtime::spawn(async move {
+            scanner.resource_loop(resource_host).await;
+        }));
+
+        true
+    }
+
+    pub fn stop_scanning(&self) {
+        let _tasks = self.tasks.lock().unwrap();
+        self.scanning.store(false, Ordering::SeqCst);
+        self.wake.notify_one();
+        // Release anything parked on the gate so it sees the scanner has stopped
+        self.gate.send_modify(|_| {});
+    }
+
+    pub fn pause(&self, host: &Host) {
+        self.paused.store(true, Ordering::SeqCst);
+        self.refresh_gate(host);
+    }
+
+    pub fn resume(&self, host: &Host) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.refresh_gate(host);
+    }
+
+    pub fn throttle_status(&self) -> ThrottleStatus {
+        ThrottleStatus {
+            state: self.gate.borrow().clone(),
+            sample: self.throttle.lock().unwrap().1.clone(),
         }
     }
 
-    async fn scan_single_file(&self, file_path: String, retry_count: u32) -> Result<ScanResult, String> {
-        let _permit = self.scan_semaphore.acquire().await.map_err(|e| e.to_string())?;
-        
-        let result = scan_file_internal(&file_path, &self.state).await;
-        
-        if let Err(ref e) = result {
-            let config = self.config.lock().await;
-            if retry_count < config.retry_attempts {
-                sleep(Duration::from_secs(config.retry_delay_seconds)).await;
-                return self.scan_single_file(file_path, retry_count + 1).await;
+    // Recompute whether scans may run and tell the UI when that changes
+    fn refresh_gate(&self, host: &Host) {
+        let reasons = self.throttle.lock().unwrap().0.clone();
+        let state = if self.paused.load(Ordering::SeqCst) {
+            ThrottleState::Paused
+        } else if !reasons.is_empty() {
+            ThrottleState::Throttled { reasons }
+        } else {
+            ThrottleState::Running
+        };
+
+        let changed = self.gate.send_if_modified(|current| {
+            if *current == state {
+                return false;
             }
+            *current = state;
+            true
+        });
+
+        if changed {
+            if let Err(e) = host.emit("throttle-state", self.throttle_status()) {
+                log::error!("Failed to emit throttle-state event: {}", e);
+            }
+            host.state().status_indicator.refresh();
         }
-        
-        result
     }
 
-    pub async fn start(&self) {
-        let state = self.state.clone();
-        let config = self.config.clone();
-        
-        tokio::spawn(async move {
-            loop {
-                // Load current settings
-                let settings = match Settings::load() {
-                    Ok(s) => s,
-                    Err(_) => {
-                        sleep(Duration::from_secs(60)).await;
-                        continue;
-                    }
-                };
-
-                // Check if auto-rescan is enabled and get interval
-                if let Some(interval_hours) = settings.auto_rescan_interval {
-                    let config = config.lock().await;
-                    let batch_size = config.scan_batch_size;
-                    
-                    // Get files to scan
-                    let files_to_scan = {
-                        let mut files = Vec::new();
-                        let quarantined_files = state.quarantined_files.lock().await;
-                        let scan_history = state.scan_history.lock().await;
-                        
-                        for file in quarantined_files.iter() {
-                            if let Ok(metadata) = tokio::fs::metadata(&file.path).await {
-                                if let Ok(modified) = metadata.modified() {
-                                    if let Ok(elapsed) = modified.elapsed() {
-                                        // Check if file needs rescanning
-                                        let last_scan = scan_history.iter()
-                                            .filter(|scan| scan.file_path == file.path)
-                                            .max_by_key(|scan| scan.scan_date);
-                                            
-                                        let needs_rescan = match last_scan {
-                                            Some(scan) => {
-                                                let scan_age = scan.scan_date.elapsed().unwrap_or_default();
-                                                scan_age.as_secs() > interval_hours * 3600
-                                            }
-                                            None => true
-                                        };
-                                        
-                                        if needs_rescan {
-                                            files.push(file.path.clone());
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        files
-                    };
-
-                    // Process files in batches
-                    for batch in files_to_scan.chunks(batch_size) {
-                        let futures = stream::iter(batch)
-                            .map(|file_path| {
-                                let scanner = self.clone();
-                                async move {
-                                    let result = scanner.scan_single_file(file_path.to_string(), 0).await;
-                                    (file_path, result)
-                                }
-                            })
-                            .buffer_unordered(config.max_concurrent_scans);
-
-                        // Process results
-                        futures.for_each(|(file_path, result)| async {
-                            match result {
-                                Ok(scan_result) => {
-                                    let mut history = state.scan_history.lock().await;
-                                    if history.len() >= settings.scan_history_limit as usize {
-                                        history.remove(0);
-                                    }
-                                    history.push(scan_result);
-                                },
-                                Err(e) => eprintln!("Failed to scan file {}: {}", file_path, e),
-                            }
-                        }).await;
-                    }
+    // Wait until the scanner is neither paused nor throttled, or has been stopped
+    async fn wait_until_runnable(&self) {
+        let mut gate = self.gate.subscribe();
+        loop {
+            if *gate.borrow_and_update() == ThrottleState::Running || !self.is_scanning() {
+                return;
+            }
+            if gate.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
+    async fn resource_loop(&self, host: Host) {
+        let mut monitor = ResourceMonitor::default();
+
+        while self.is_scanning() {
+            let config = self.config.read().await.throttle.clone();
+
+            let sample = monitor.sample(&config);
+            let reasons = if config.enabled {
+                sample.throttle_reasons(&config)
+            } else {
+                Vec::new()
+            };
+            *self.throttle.lock().unwrap() = (reasons, sample);
+            self.refresh_gate(&host);
+
+            sleep(Duration::from_secs(config.check_interval_secs)).await;
+        }
+    }
+
+    pub fn is_scanning(&self) -> bool {
+        self.scanning.load(Ordering::SeqCst)
+    }
+
+    // Restore the queue persisted by a previous run
+    pub async fn load_queue(&self) {
+        let queue = ScanQueue::load(settings::data_dir().join("scan_queue.json"));
+        let restored = !queue.is_empty();
+        *self.scan_queue.lock().await = queue;
+
+        if restored {
+            self.wake.notify_one();
+        }
+    }
+
+    pub async fn add_to_queue(&self, path: PathBuf, priority: ScanPriority) -> bool {
+        let file_hash = virus_total::calculate_file_hash(&path).await.ok();
+        self.add_hashed_to_queue(path, file_hash, priority).await
+    }
+
+    // For callers that already hashed the file
+    pub async fn add_hashed_to_queue(&self, path: PathBuf, file_hash: Option<String>, priority: ScanPriority) -> bool {
+        let added = self.scan_queue.lock().await.push(path, file_hash, priority);
+
+        if added {
+            self.wake.notify_one();
+        }
+        added
+    }
+
+    pub async fn queued_scans(&self) -> Vec<QueuedScan> {
+        self.scan_queue.lock().await.list()
+    }
+
+    pub async fn move_queued_scan(&self, id: &str, position: usize) -> Result<(), String> {
+        self.scan_queue.lock().await.move_to(id, position)
+    }
+
+    pub async fn remove_queued_scan(&self, id: &str) -> bool {
+        self.scan_queue.lock().await.remove(id).is_some()
+    }
+
+    pub async fn remove_queued_path(&self, path: &Path) -> bool {
+        self.scan_queue.lock().await.remove_path(path).is_some()
+    }
+
+    pub async fn is_queued(&self, path: &Path, file_hash: Option<&str>) -> bool {
+        self.scan_queue.lock().await.contains(path, file_hash)
+    }
+
+    // Subscribe before queueing, so no completion can be missed
+    pub fn subscribe_completions(&self) -> broadcast::Receiver<ScanCompletion> {
+        self.completions.subscribe()
+    }
+
+    pub async fn status(&self) -> BackgroundScannerStatus {
+        BackgroundScannerStatus {
+            running: self.is_scanning(),
+            queued: self.scan_queue.lock().await.len(),
+            active: self.active_scans.load(Ordering::SeqCst),
+            max_concurrent_scans: self.config.read().await.max_concurrent_scans,
+            throttle: self.throttle_status(),
+        }
+    }
+
+    async fn scanning_loop(&self, host: Host) {
+        log::info!("Background scanner started");
+
+        while self.is_scanning() {
+            self.wait_until_runnable().await;
+            if !self.is_scanning() {
+                break;
+            }
+
+            // Take a free slot first and only then the queue's head, so a scan queued while
+            // every slot is busy still goes ahead of anything with a lower priority
+            let permit = tokio::select! {
+                permit = self.scan_semaphore.clone().acquire_owned() => match permit {
+                    Ok(permit) => permit,
+                    Err(_) => break,
+                },
+                // A stop request, or new work that finds every slot still taken
+                _ = self.wake.notified() => continue,
+            };
+            if !self.is_scanning() {
+                self.release(permit);
+                break;
+            }
+
+            let entry = self.scan_queue.lock().await.pop();
+            let Some(entry) = entry else {
+                self.release(permit);
+                // Idle until new work or the next interval
+                let interval = self.config.read().await.scan_interval_secs;
+                tokio::select! {
+                    _ = sleep(Duration::from_secs(interval)) => {}
+                    _ = self.wake.notified() => {}
                 }
+                continue;
+            };
+
+            // Work from a snapshot so config updates never wait on a scan
+            let config = self.config.read().await.clone();
+            self.spawn_scan(&host, entry, config, permit);
+        }
+
+        log::info!("Background scanner stopped");
+    }
+
+    fn spawn_scan(&self, host: &Host, entry: QueuedScan, config: ScannerConfig, permit: OwnedSemaphorePermit) {
+        let scanner = self.clone();
+        let host = host.clone();
+        tokio::spawn(async move {
+            // A pause or throttle that kicked in since the slot was taken holds the scan back
+            scanner.wait_until_runnable().await;
 
-                // Sleep before next scan cycle
-                sleep(Duration::from_secs(3600)).await;
+            let path = entry.path;
+            scanner.active_scans.fetch_add(1, Ordering::SeqCst);
+            let result = scanner.scan_file(&host, &path, &config).await.map_err(String::from);
+            scanner.active_scans.fetch_sub(1, Ordering::SeqCst);
+            scanner.release(permit);
+
+            // Announced before the entry leaves the queue, so a waiter that finds it gone
+            // already has the completion to read. Having no subscribers is normal.
+            let _ = scanner.completions.send(ScanCompletion {
+                path: path.clone(),
+                file_hash: entry.file_hash.clone(),
+                result: result.clone(),
+            });
+            scanner.scan_queue.lock().await.complete(&entry.id);
+
+            match result {
+                Ok(scan_result) => {
+                    virus_total::respond(&host, &scan_result).await;
+                    host.emit("scan-complete", json!({
+                        "path": path.to_string_lossy(),
+                        "result": scan_result
+                    })).ok();
+                }
+                Err(e) => {
+                    log::error!("Failed to scan file {}: {}", path.display(), e);
+                    host.emit("scan-error", json!({
+                        "path": path.to_string_lossy(),
+                        "error": e
+                    })).ok();
+                }
             }
         });
     }
+
+    async fn scan_file(&self, host: &Host, path: &Path, config: &ScannerConfig) -> Result<ScanResult, ScanError> {
+        let state = host.state();
+        let file_path = path.to_string_lossy().to_string();
+
+        // A file that disappeared in the meantime, or a rejected API key, will not come
+        // right by waiting
+        retry_with_backoff!(
+            state.error_handler,
+            Subsystem::Scanner,
+            "background_scan",
+            config.retry,
+            scan_file_internal(&file_path, state),
+            |e: &ScanError| e.category,
+            |e: &ScanError| path.exists() && e.category != ErrorCategory::ConfigurationError
+        )
+    }
+}
+
+// Tauri commands for the background scanner
+#[tauri::command]
+pub async fn start_background_scanner(
+    state: tauri::State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<bool, String> {
+    let settings = Settings::load()?;
+    state.background_scanner.set_config(settings.scanner).await;
+    Ok(state.background_scanner.start_scanning(Host::App(app_handle)))
+}
+
+#[tauri::command]
+pub async fn stop_background_scanner(state: tauri::State<'_, AppState>) -> Result<(), String> {
+    state.background_scanner.stop_scanning();
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn pause_background_scanner(
+    state: tauri::State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<ThrottleStatus, String> {
+    state.background_scanner.pause(&Host::App(app_handle));
+    Ok(state.background_scanner.throttle_status())
+}
+
+#[tauri::command]
+pub async fn resume_background_scanner(
+    state: tauri::State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<ThrottleStatus, String> {
+    state.background_scanner.resume(&Host::App(app_handle));
+    Ok(state.background_scanner.throttle_status())
+}
+
+#[tauri::command]
+pub async fn get_throttle_state(state: tauri::State<'_, AppState>) -> Result<ThrottleStatus, String> {
+    Ok(state.background_scanner.throttle_status())
+}
+
+#[tauri::command]
+pub async fn enqueue_scan(
+    paths: Vec<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<usize, String> {
+    let mut queued = 0;
+    for path in paths {
+        let path = PathBuf::from(path);
+        if !path.is_file() {
+            return Err(format!("Not a file: {}", path.display()));
+        }
+        if state.background_scanner.add_to_queue(path, ScanPriority::UserInitiated).await {
+            queued += 1;
+        }
+    }
+    Ok(queued)
+}
+
+#[tauri::command]
+pub async fn get_scan_queue(state: tauri::State<'_, AppState>) -> Result<Vec<QueuedScan>, String> {
+    Ok(state.background_scanner.queued_scans().await)
+}
+
+#[tauri::command]
+pub async fn move_queued_scan(
+    id: String,
+    position: usize,
+    state: tauri::State<'_, AppState>,
+) -> Result<Vec<QueuedScan>, String> {
+    state.background_scanner.move_queued_scan(&id, position).await?;
+    Ok(state.background_scanner.queued_scans().await)
+}
+
+#[tauri::command]
+pub async fn remove_queued_scan(
+    id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, String> {
+    Ok(state.background_scanner.remove_queued_scan(&id).await)
+}
+
+#[tauri::command]
+pub async fn get_background_scanner_status(
+    state: tauri::State<'_, AppState>,
+) -> Result<BackgroundScannerStatus, String> {
+    Ok(state.background_scanner.status().await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(max_concurrent_scans: usize) -> ScannerConfig {
+        ScannerConfig { max_concurrent_scans, ..ScannerConfig::default() }
+    }
+
+    #[tokio::test]
+    async fn raising_the_limit_after_lowering_it_restores_every_permit() {
+        let scanner = BackgroundScanner::new(config(4));
+        let held = scanner.scan_semaphore.clone().acquire_many_owned(3).await.unwrap();
+
+        // Only one permit is free, the other two are retired when the scans finish
+        scanner.set_config(config(1)).await;
+        assert_eq!(scanner.scan_semaphore.available_permits(), 0);
+
+        scanner.set_config(config(6)).await;
+        drop(held);
+        assert_eq!(scanner.scan_semaphore.available_permits(), 6);
+    }
+
+    #[tokio::test]
+    async fn permits_held_while_lowering_are_retired_on_release() {
+        let scanner = BackgroundScanner::new(config(3));
+        let first = scanner.scan_semaphore.clone().acquire_owned().await.unwrap();
+        let second = scanner.scan_semaphore.clone().acquire_owned().await.unwrap();
+
+        scanner.set_config(config(1)).await;
+        scanner.release(first);
+        assert_eq!(scanner.scan_semaphore.available_permits(), 0);
+        scanner.release(second);
+        assert_eq!(scanner.scan_semaphore.available_permits(), 1);
+    }
 }
@@ -0,0 +1,492 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use serde_json::Value;
+use virus_scanner_app_lib::audit;
+use virus_scanner_app_lib::folder_scan::collect_files;
+use virus_scanner_app_lib::quarantine::{Quarantine, QuarantineItem};
+use virus_scanner_app_lib::settings::{Settings, SettingsError};
+use virus_scanner_app_lib::virus_total::{self, ScanResult, ScanStatus, VirusTotal};
+use virus_scanner_app_lib::AppState;
+
+// Scan files and manage the scanner from scripts, CI jobs and git hooks
+#[derive(Parser)]
+#[command(
+    name = "vscan",
+    version,
+    about = "Scan files with VirusTotal without opening the app",
+    after_help = "Exit codes: 0 clean, 1 suspicious, 2 malicious, 3 error. \
+                  When several files are scanned the most severe outcome wins."
+)]
+struct Cli {
+    /// How results are printed
+    #[arg(long, value_enum, default_value_t = Format::Table, global = true)]
+    format: Format,
+    /// Overrides the API key saved in the app's settings
+    #[arg(long, env = "VT_API_KEY", hide_env_values = true, global = true)]
+    api_key: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Table,
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Scan files, and the files inside any folders given
+    Scan {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+        /// Only scan the top level of folders
+        #[arg(long)]
+        no_recursive: bool,
+    },
+    /// Show the VirusTotal verdict for a SHA-256, SHA-1 or MD5 hash
+    Lookup {
+        hash: String,
+    },
+    /// Show recent scan results, newest first
+    History {
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// List or restore quarantined files
+    Quarantine {
+        #[command(subcommand)]
+        command: QuarantineCommand,
+    },
+    /// Read or change settings by their dotted path, e.g. scanner.max_concurrent_scans
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Run the scanner as a background service, or control a running one
+    #[cfg(unix)]
+    Daemon {
+        /// Control socket, by default daemon.sock in the app's data folder
+        #[arg(long, global = true)]
+        socket: Option<PathBuf>,
+        #[command(subcommand)]
+        command: DaemonCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum QuarantineCommand {
+    /// List quarantined files
+    List,
+    /// Restore a quarantined file to where it was found, or to --to
+    Restore {
+        id: String,
+        #[arg(long)]
+        to: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Print one setting, or all of them
+    Get {
+        key: Option<String>,
+    },
+    /// Change a setting; the value is parsed as JSON, falling back to a plain string
+    Set {
+        key: String,
+        value: String,
+    },
+}
+
+#[cfg(unix)]
+#[derive(Subcommand)]
+enum DaemonCommand {
+    /// Run in the foreground until SIGTERM; SIGHUP reloads the settings
+    Run {
+        /// By default daemon.pid in the app's data folder
+        #[arg(long)]
+        pid_file: Option<PathBuf>,
+    },
+    /// Show what the daemon is doing
+    Status,
+    /// Reload the settings file
+    Reload,
+    /// Stop the daemon
+    Stop,
+    /// Pause the background scanner
+    Pause,
+    /// Resume the background scanner
+    Resume,
+    /// Queue files for the daemon's background scanner
+    Scan {
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+    /// Print the daemon's events as they happen, one JSON object per line
+    Events,
+}
+
+// Ordered by severity so the worst outcome of a run decides the exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Outcome {
+    Clean = 0,
+    Suspicious = 1,
+    Malicious = 2,
+    Error = 3,
+}
+
+impl From<&ScanStatus> for Outcome {
+    fn from(status: &ScanStatus) -> Self {
+        match status {
+            ScanStatus::Clean | ScanStatus::Completed => Outcome::Clean,
+            ScanStatus::Suspicious => Outcome::Suspicious,
+            ScanStatus::Malicious => Outcome::Malicious,
+            // A scan that never finished has no verdict to rely on
+            ScanStatus::Pending | ScanStatus::InProgress | ScanStatus::Failed => Outcome::Error,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct FileReport {
+    path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<ScanResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    // The daemon logs through the app's own logger
+    #[cfg(unix)]
+    let runs_daemon = matches!(cli.command, Command::Daemon { command: DaemonCommand::Run { .. }, .. });
+    #[cfg(not(unix))]
+    let runs_daemon = false;
+    if !runs_daemon {
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn")).init();
+    }
+
+    let outcome = match run(&cli).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            Outcome::Error
+        }
+    };
+    ExitCode::from(outcome as u8)
+}
+
+async fn run(cli: &Cli) -> Result<Outcome, String> {
+    match &cli.command {
+        Command::Scan { paths, no_recursive } => scan(cli, paths, !no_recursive).await,
+        Command::Lookup { hash } => lookup(cli, hash).await,
+        Command::History { limit } => history(cli, *limit),
+        Command::Quarantine { command } => quarantine(cli, command),
+        Command::Config { command } => config(cli, command),
+        #[cfg(unix)]
+        Command::Daemon { socket, command } => daemon(cli, socket.as_deref(), command).await,
+    }
+}
+
+fn api_key(cli: &Cli) -> Result<String, String> {
+    cli.api_key.clone()
+        .or_else(|| Settings::load().ok().map(|settings| settings.api_key))
+        .filter(|key| !key.is_empty())
+        .ok_or_else(|| "No API key configured; finish setup in the app or pass --api-key".to_string())
+}
+
+// Scan through the same path as the app so results land in its history
+async fn scan(cli: &Cli, paths: &[PathBuf], recursive: bool) -> Result<Outcome, String> {
+    let state = AppState::headless(api_key(cli)?);
+
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            files.extend(collect_files(path, recursive));
+        } else {
+            files.push(path.clone());
+        }
+    }
+
+    let mut outcome = Outcome::Clean;
+    let mut reports = Vec::new();
+    for file in files {
+        let path = file.to_string_lossy().to_string();
+        let report = match virus_total::scan_file_internal(&path, &state).await {
+            Ok(result) => {
+                outcome = outcome.max(Outcome::from(&result.status));
+                FileReport { path, result: Some(result), error: None }
+            }
+            Err(e) => {
+                outcome = Outcome::Error;
+                FileReport { path, result: None, error: Some(e.into()) }
+            }
+        };
+        reports.push(report);
+    }
+
+    match cli.format {
+        Format::Json => print_json(&reports)?,
+        Format::Table => print_table(
+            &["STATUS", "DETECTIONS", "FILE"],
+            reports.iter().map(|report| match (&report.result, &report.error) {
+                (Some(result), _) => vec![status_label(&result.status), detections(result), report.path.clone()],
+                (None, error) => vec!["error".to_string(), error.clone().unwrap_or_default(), report.path.clone()],
+            }).collect(),
+        ),
+    }
+    Ok(outcome)
+}
+
+// The local history first, so known files cost no API quota
+async fn lookup(cli: &Cli, hash: &str) -> Result<Outcome, String> {
+    if !matches!(hash.len(), 32 | 40 | 64) || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Not an MD5, SHA-1 or SHA-256 hash: {}", hash));
+    }
+    let hash = hash.to_lowercase();
+
+    let known = virus_total::load_history().into_iter()
+        .filter(|result| result.file_hash == hash)
+        .max_by_key(|result| result.scan_date);
+    let result = match known {
+        Some(result) => result,
+        None => VirusTotal::new(api_key(cli)?).lookup_report(&hash).await?
+            .ok_or_else(|| format!("VirusTotal has no report for {}", hash))?,
+    };
+
+    match cli.format {
+        Format::Json => print_json(&result)?,
+        Format::Table => print_table(
+            &["STATUS", "DETECTIONS", "SHA256", "NAME"],
+            vec![vec![status_label(&result.status), detections(&result), result.file_hash.clone(), result.file_name.clone()]],
+        ),
+    }
+    Ok(Outcome::from(&result.status))
+}
+
+fn history(cli: &Cli, limit: usize) -> Result<Outcome, String> {
+    let mut history = virus_total::load_history();
+    history.sort_by_key(|result| std::cmp::Reverse(result.scan_date));
+    history.truncate(limit);
+
+    match cli.format {
+        Format::Json => print_json(&history)?,
+        Format::Table => print_table(
+            &["DATE", "STATUS", "DETECTIONS", "FILE"],
+            history.iter().map(|result| vec![
+                result.scan_date.format("%Y-%m-%d %H:%M").to_string(),
+                status_label(&result.status),
+                detections(result),
+                result.file_path.clone(),
+            ]).collect(),
+        ),
+    }
+    Ok(Outcome::Clean)
+}
+
+fn quarantine(cli: &Cli, command: &QuarantineCommand) -> Result<Outcome, String> {
+    let quarantine = Quarantine::open(&Settings::load()?.quarantine_settings)?;
+
+    match command {
+        QuarantineCommand::List => {
+            let items: Vec<QuarantineItem> = quarantine.list()?;
+            match cli.format {
+                Format::Json => print_json(&items)?,
+                Format::Table => print_table(
+                    &["ID", "QUARANTINED", "VERDICT", "ORIGINAL PATH"],
+                    items.iter().map(|item| vec![
+                        item.id.clone(),
+                        item.quarantined_at.format("%Y-%m-%d %H:%M").to_string(),
+                        item.verdict.as_ref().map(status_label).unwrap_or_else(|| "-".to_string()),
+                        item.original_path.clone(),
+                    ]).collect(),
+                ),
+            }
+        }
+        QuarantineCommand::Restore { id, to } => {
+            let restored = quarantine.restore(id, to.as_deref())?;
+            match cli.format {
+                Format::Json => print_json(&serde_json::json!({ "id": id, "restored_to": restored }))?,
+                Format::Table => println!("Restored {} to {}", id, restored.display()),
+            }
+        }
+    }
+    Ok(Outcome::Clean)
+}
+
+fn config(cli: &Cli, command: &ConfigCommand) -> Result<Outcome, String> {
+    let previous = Settings::load()?;
+    let mut root = serde_json::to_value(&previous)
+        .map_err(|e| format!("Failed to serialize settings: {}", e))?;
+
+    match command {
+        ConfigCommand::Get { key } => {
+            // A secret asked for by name is printed, anything wider has its secrets masked
+            let value = match key {
+                Some(key) => match root.pointer(&pointer(key)) {
+                    Some(Value::Object(_) | Value::Array(_)) => {
+                        redact_secrets(&mut root);
+                        root.pointer(&pointer(key)).cloned().unwrap_or_default()
+                    }
+                    Some(value) => value.clone(),
+                    None => return Err(format!("Unknown setting: {}", key)),
+                },
+                None => {
+                    redact_secrets(&mut root);
+                    root
+                }
+            };
+            match (cli.format, &value) {
+                (Format::Table, Value::String(text)) => println!("{}", text),
+                (Format::Table, Value::Bool(_) | Value::Number(_) | Value::Null) => println!("{}", value),
+                _ => print_json(&value)?,
+            }
+        }
+        ConfigCommand::Set { key, value } => {
+            let slot = root.pointer_mut(&pointer(key))
+                .ok_or_else(|| format!("Unknown setting: {}", key))?;
+            *slot = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.clone()));
+
+            let updated: Settings = serde_json::from_value(root)
+                .map_err(|e| format!("Invalid value for {}: {}", key, e))?;
+            updated.save_validated().map_err(|e| match e {
+                SettingsError::Invalid { errors } => errors.iter()
+                    .map(|error| format!("{}: {}", error.field, error.message))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                SettingsError::Storage { message } => message,
+            })?;
+            audit::record_settings_change(&previous, &updated);
+
+            if cli.format == Format::Table {
+                println!("Set {}", key);
+            }
+        }
+    }
+    Ok(Outcome::Clean)
+}
+
+#[cfg(unix)]
+async fn daemon(cli: &Cli, socket: Option<&std::path::Path>, command: &DaemonCommand) -> Result<Outcome, String> {
+    use virus_scanner_app_lib::daemon::{self, ControlRequest};
+
+    let socket = socket.map(PathBuf::from).unwrap_or_else(daemon::default_socket_path);
+    let request = match command {
+        DaemonCommand::Run { pid_file } => {
+            let pid_file = pid_file.clone().unwrap_or_else(daemon::default_pid_path);
+            daemon::run(&pid_file, &socket).await?;
+            return Ok(Outcome::Clean);
+        }
+        DaemonCommand::Events => {
+            daemon::follow_events(&socket, |event| println!("{}", event)).await?;
+            return Ok(Outcome::Clean);
+        }
+        DaemonCommand::Status => ControlRequest::Status,
+        DaemonCommand::Reload => ControlRequest::Reload,
+        DaemonCommand::Stop => ControlRequest::Stop,
+        DaemonCommand::Pause => ControlRequest::Pause,
+        DaemonCommand::Resume => ControlRequest::Resume,
+        DaemonCommand::Scan { paths } => ControlRequest::Scan {
+            // The daemon may run in another working directory
+            paths: paths.iter()
+                .map(|path| std::fs::canonicalize(path)
+                    .map(|path| path.to_string_lossy().to_string())
+                    .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e)))
+                .collect::<Result<_, _>>()?,
+        },
+    };
+
+    let data = daemon::request(&socket, &request).await?;
+    match (cli.format, command) {
+        (Format::Json, _) => print_json(&data)?,
+        (Format::Table, DaemonCommand::Status) => print_table(
+            &["PID", "STARTED", "MONITORING", "QUEUED", "STATUS"],
+            vec![vec![
+                data["pid"].to_string(),
+                data["started_at"].as_str().unwrap_or("-").to_string(),
+                data["monitoring"].to_string(),
+                data["scanner"]["queued"].to_string(),
+                data["summary"].as_str().unwrap_or("-").to_string(),
+            ]],
+        ),
+        (Format::Table, DaemonCommand::Scan { .. }) => println!("Queued {} file(s)", data["queued"]),
+        (Format::Table, _) => println!("OK"),
+    }
+    Ok(Outcome::Clean)
+}
+
+// scanner.retry.max_attempts -> /scanner/retry/max_attempts
+fn pointer(key: &str) -> String {
+    key.split('.').map(|part| format!("/{}", part)).collect()
+}
+
+// Mask the API key, the REST API token and webhook secrets and headers
+fn redact_secrets(root: &mut Value) {
+    fn mask(value: &mut Value) {
+        if value.as_str().is_some_and(|text| !text.is_empty()) {
+            *value = Value::String("********".to_string());
+        }
+    }
+
+    for pointer in ["/api_key", "/rest_api/token"] {
+        if let Some(value) = root.pointer_mut(pointer) {
+            mask(value);
+        }
+    }
+    if let Some(Value::Array(webhooks)) = root.get_mut("webhooks") {
+        for webhook in webhooks {
+            if let Some(secret) = webhook.get_mut("secret") {
+                mask(secret);
+            }
+            if let Some(Value::Object(headers)) = webhook.get_mut("headers") {
+                headers.values_mut().for_each(mask);
+            }
+        }
+    }
+}
+
+fn status_label(status: &ScanStatus) -> String {
+    format!("{:?}", status).to_lowercase()
+}
+
+fn detections(result: &ScanResult) -> String {
+    match (result.detection_count, result.total_engines) {
+        (Some(detections), Some(total)) => format!("{}/{}", detections, total),
+        _ => "-".to_string(),
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value)
+        .map_err(|e| format!("Failed to serialize output: {}", e))?;
+    println!("{}", json);
+    Ok(())
+}
+
+// Left-aligned columns sized to their widest cell
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| -> String {
+        cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    println!("{}", format_row(headers.to_vec()));
+    for row in &rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
@@ -0,0 +1,495 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_util::sync::CancellationToken;
+use crate::folder_scan::collect_files;
+use crate::host::Host;
+use crate::rest_api::{self, Listen, RunningServer};
+use crate::scanner::ScanPipeline;
+use crate::settings::{self, FieldError};
+use crate::virus_total::{self, ScanResult, ScanStatus, VirusTotal};
+
+// Longest command line accepted, e.g. SCAN with a long path
+const MAX_COMMAND_LEN: u64 = 4096;
+// Idle time allowed between a client's command and stream chunks
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ClamdConfig {
+    pub enabled: bool,
+    // A loopback address such as 127.0.0.1:3310, or unix:/path/to/clamd.sock
+    pub listen: String,
+    // Like clamd's StreamMaxLength, the largest INSTREAM upload accepted
+    pub max_stream_bytes: u64,
+    // Answer FOUND for suspicious files too, as clamd does for heuristic matches
+    pub report_suspicious: bool,
+    // Files are only looked up, never uploaded. Answer ERROR rather than OK for files
+    // VirusTotal has no report on.
+    pub report_unknown: bool,
+    // Folders SCAN and CONTSCAN may read over TCP. On a unix socket, which only this
+    // user can reach, any path is allowed.
+    pub scan_roots: Vec<PathBuf>,
+}
+
+impl Default for ClamdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:3310".to_string(),
+            max_stream_bytes: 25 * 1024 * 1024,
+            report_suspicious: true,
+            report_unknown: false,
+            scan_roots: Vec::new(),
+        }
+    }
+}
+
+impl ClamdConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if let Err(e) = rest_api::parse_listen(&self.listen) {
+            errors.push(FieldError::new("clamd.listen", e));
+        }
+        if self.max_stream_bytes == 0 {
+            errors.push(FieldError::new("clamd.max_stream_bytes", "Streams must be allowed at least one byte"));
+        }
+        for root in &self.scan_roots {
+            if !root.is_absolute() {
+                errors.push(FieldError::new("clamd.scan_roots", format!("Scan root must be absolute: {}", root.display())));
+            }
+        }
+    }
+}
+
+struct Context {
+    host: Host,
+    max_stream_bytes: u64,
+    report_suspicious: bool,
+    report_unknown: bool,
+    // None when any path may be scanned
+    scan_roots: Option<Vec<PathBuf>>,
+}
+
+// Runs the clamd protocol server while it is enabled in the settings
+#[derive(Clone, Default)]
+pub struct ClamdServer {
+    server: Arc<tokio::sync::Mutex<Option<RunningServer>>>,
+}
+
+impl ClamdServer {
+    // Stop any running server and start a new one for the given config
+    pub async fn apply(&self, host: &Host, config: &ClamdConfig) {
+        let mut server = self.server.lock().await;
+        if let Some(previous) = server.take() {
+            previous.stop().await;
+        }
+        if !config.enabled {
+            return;
+        }
+
+        let listen = match rest_api::parse_listen(&config.listen) {
+            Ok(listen) => listen,
+            Err(e) => {
+                log::error!("Not starting clamd server: {}", e);
+                return;
+            }
+        };
+
+        let scan_roots = match listen {
+            #[cfg(unix)]
+            Listen::Unix(_) => None,
+            Listen::Tcp(_) => Some(config.scan_roots.iter()
+                .filter_map(|root| std::fs::canonicalize(root).ok())
+                .collect()),
+        };
+
+        let shutdown = CancellationToken::new();
+        let context = Arc::new(Context {
+            host: host.clone(),
+            max_stream_bytes: config.max_stream_bytes,
+            report_suspicious: config.report_suspicious,
+            report_unknown: config.report_unknown,
+            scan_roots,
+        });
+
+        *server = Some(RunningServer::spawn(shutdown.clone(), async move {
+            if let Err(e) = serve(listen, context, shutdown).await {
+                log::error!("clamd server stopped: {}", e);
+            }
+        }));
+    }
+}
+
+async fn serve(listen: Listen, context: Arc<Context>, shutdown: CancellationToken) -> Result<(), String> {
+    match listen {
+        Listen::Tcp(address) => {
+            let listener = tokio::net::TcpListener::bind(address).await
+                .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+            log::info!("clamd server listening on {}", address);
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => serve_connection(stream, context.clone()),
+                        Err(e) => log::warn!("Failed to accept clamd connection: {}", e),
+                    },
+                }
+            }
+        }
+        #[cfg(unix)]
+        Listen::Unix(path) => {
+            let (listener, socket) = rest_api::bind_unix(&path)?;
+            log::info!("clamd server listening on {}", path.display());
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => serve_connection(stream, context.clone()),
+                        Err(e) => log::warn!("Failed to accept clamd connection: {}", e),
+                    },
+                }
+            }
+            socket.remove();
+        }
+    }
+
+    log::info!("clamd server stopped");
+    Ok(())
+}
+
+fn serve_connection<S>(stream: S, context: Arc<Context>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = handle(&context, stream).await {
+            log::debug!("clamd connection closed: {}", e);
+        }
+    });
+}
+
+// One command per connection; sessions (IDSESSION) are not supported
+async fn handle<S>(context: &Context, stream: S) -> Result<(), String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stream = BufReader::new(stream);
+    let (command, delimiter) = tokio::time::timeout(CLIENT_TIMEOUT, read_command(&mut stream)).await
+        .map_err(|_| "Timed out waiting for a command".to_string())??;
+    let (name, argument) = command.split_once(' ').unwrap_or((command.as_str(), ""));
+    log::debug!("clamd command: {}", name);
+
+    let replies = match name {
+        "PING" => vec!["PONG".to_string()],
+        "VERSION" => vec![format!("ClamAV-compatible virus-scanner-app {}/VirusTotal", env!("CARGO_PKG_VERSION"))],
+        "SCAN" => scan_path(context, argument, true).await,
+        "CONTSCAN" => scan_path(context, argument, false).await,
+        "INSTREAM" => vec![instream(context, &mut stream).await],
+        _ => vec!["UNKNOWN COMMAND".to_string()],
+    };
+
+    let stream = stream.get_mut();
+    for reply in replies {
+        // A stray delimiter inside an error message would split the reply
+        let mut line = reply.replace(['\n', '\0'], " ").into_bytes();
+        line.push(delimiter);
+        stream.write_all(&line).await
+            .map_err(|e| format!("Failed to write reply: {}", e))?;
+    }
+    stream.flush().await
+        .map_err(|e| format!("Failed to write reply: {}", e))
+}
+
+// "zCOMMAND\0" and "nCOMMAND\n" choose the reply delimiter; unprefixed commands end with a newline
+async fn read_command<R>(reader: &mut R) -> Result<(String, u8), String>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut limited = (&mut *reader).take(MAX_COMMAND_LEN);
+    let first = limited.read_u8().await
+        .map_err(|e| format!("Failed to read command: {}", e))?;
+    let delimiter = if first == b'z' { b'\0' } else { b'\n' };
+
+    let mut line = Vec::new();
+    if first != b'z' && first != b'n' {
+        line.push(first);
+    }
+    limited.read_until(delimiter, &mut line).await
+        .map_err(|e| format!("Failed to read command: {}", e))?;
+    if line.pop() != Some(delimiter) {
+        return Err("Command too long or not terminated".to_string());
+    }
+
+    let command = String::from_utf8(line)
+        .map_err(|_| "Command is not valid UTF-8".to_string())?;
+    Ok((command.trim_end_matches('\r').to_string(), delimiter))
+}
+
+// SCAN stops at the first infected file; CONTSCAN reports every one
+async fn scan_path(context: &Context, path: &str, stop_on_found: bool) -> Vec<String> {
+    let root = Path::new(path);
+    if !root.is_absolute() {
+        return vec![format!("{}: Path must be absolute. ERROR", path)];
+    }
+    if let Some(roots) = &context.scan_roots {
+        let allowed = std::fs::canonicalize(root)
+            .is_ok_and(|canonical| roots.iter().any(|allowed| canonical.starts_with(allowed)));
+        if !allowed {
+            return vec![format!("{}: Path is outside the configured scan roots. ERROR", path)];
+        }
+    }
+    let files = if root.is_dir() {
+        collect_files(root, true)
+    } else if root.is_file() {
+        vec![root.to_path_buf()]
+    } else {
+        return vec![format!("{}: No such file or directory. ERROR", path)];
+    };
+
+    let mut replies = Vec::new();
+    for file in files {
+        let file_path = file.to_string_lossy().to_string();
+        match scan_file(context, &file).await {
+            Ok(None) => {}
+            Ok(Some(name)) => {
+                replies.push(format!("{}: {} FOUND", file_path, name));
+                if stop_on_found {
+                    break;
+                }
+            }
+            Err(e) => replies.push(format!("{}: {} ERROR", file_path, e)),
+        }
+    }
+    // Like clamd, a clean folder is answered with a single line for the folder itself
+    if replies.is_empty() {
+        replies.push(format!("{}: OK", path));
+    }
+    replies
+}
+
+// Stream chunks are a big-endian u32 length followed by that many bytes, ending with a zero length
+async fn instream<R>(context: &Context, reader: &mut R) -> String
+where
+    R: AsyncRead + Unpin,
+{
+    let dir = settings::data_dir().join("clamd_streams").join(uuid::Uuid::new_v4().to_string());
+    let path = dir.join("stream");
+
+    let reply = match receive_stream(context, reader, &dir, &path).await {
+        Ok(()) => match scan_stream(context, &path).await {
+            Ok(None) => "stream: OK".to_string(),
+            Ok(Some(name)) => format!("stream: {} FOUND", name),
+            Err(e) => format!("stream: {} ERROR", e),
+        },
+        Err(reply) => reply,
+    };
+
+    if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            log::warn!("Failed to remove streamed file {}: {}", dir.display(), e);
+        }
+    }
+    reply
+}
+
+// Errors are returned as the complete reply line
+async fn receive_stream<R>(context: &Context, reader: &mut R, dir: &Path, path: &Path) -> Result<(), String>
+where
+    R: AsyncRead + Unpin,
+{
+    tokio::fs::create_dir_all(dir).await
+        .map_err(|e| format!("Failed to create stream directory: {}. ERROR", e))?;
+    let mut file = tokio::fs::File::create(path).await
+        .map_err(|e| format!("Failed to create stream file: {}. ERROR", e))?;
+
+    let mut received = 0u64;
+    loop {
+        let len = tokio::time::timeout(CLIENT_TIMEOUT, reader.read_u32()).await
+            .map_err(|_| "Timed out waiting for stream data. ERROR".to_string())?
+            .map_err(|e| format!("Failed to read stream: {}. ERROR", e))? as u64;
+        if len == 0 {
+            break;
+        }
+        received += len;
+        if received > context.max_stream_bytes {
+            return Err("INSTREAM size limit exceeded. ERROR".to_string());
+        }
+
+        let mut chunk = (&mut *reader).take(len);
+        let copied = tokio::time::timeout(CLIENT_TIMEOUT, tokio::io::copy(&mut chunk, &mut file)).await
+            .map_err(|_| "Timed out waiting for stream data. ERROR".to_string())?
+            .map_err(|e| format!("Failed to write stream: {}. ERROR", e))?;
+        if copied != len {
+            return Err("Stream ended in the middle of a chunk. ERROR".to_string());
+        }
+    }
+
+    file.flush().await
+        .map_err(|e| format!("Failed to write stream: {}. ERROR", e))
+}
+
+// Cache and hash lookup only: clients hand over mail attachments and other content
+// that must not be uploaded
+async fn lookup(context: &Context, path: &Path) -> Result<Option<ScanResult>, String> {
+    let api_key = context.host.state().api_key.lock().await.clone()
+        .ok_or_else(|| "API key not initialized".to_string())?;
+    let outcome = ScanPipeline::lookup_only(VirusTotal::new(api_key)).try_scan(path).await;
+    virus_total::report_api_outcome(context.host.state(), &outcome).await;
+    Ok(outcome?)
+}
+
+// A file on this machine is recorded and goes through the quarantine policy and webhooks
+// like any other scan. Returns the name to report when the file counts as infected.
+async fn scan_file(context: &Context, path: &Path) -> Result<Option<String>, String> {
+    let Some(result) = lookup(context, path).await? else {
+        return unknown(context);
+    };
+    virus_total::record_result(context.host.state(), result.clone()).await;
+    virus_total::respond(&context.host, &result).await;
+    verdict(context, &result)
+}
+
+// A streamed file only lives in a temporary folder, so it is neither recorded nor quarantined
+async fn scan_stream(context: &Context, path: &Path) -> Result<Option<String>, String> {
+    let Some(result) = lookup(context, path).await? else {
+        return unknown(context);
+    };
+    log::info!("clamd stream {}: {:?}", result.file_hash, result.status);
+    verdict(context, &result)
+}
+
+fn unknown(context: &Context) -> Result<Option<String>, String> {
+    if context.report_unknown {
+        Err("Not known to VirusTotal".to_string())
+    } else {
+        Ok(None)
+    }
+}
+
+fn verdict(context: &Context, result: &ScanResult) -> Result<Option<String>, String> {
+    match result.status {
+        ScanStatus::Malicious => Ok(Some(virus_name(result, "VirusTotal.Malicious"))),
+        ScanStatus::Suspicious if context.report_suspicious => {
+            Ok(Some(virus_name(result, "Heuristics.VirusTotal.Suspicious")))
+        }
+        ScanStatus::Clean | ScanStatus::Completed | ScanStatus::Suspicious => Ok(None),
+        ScanStatus::Pending | ScanStatus::InProgress | ScanStatus::Failed => {
+            Err("Scan did not finish".to_string())
+        }
+    }
+}
+
+// The detection name most engines agree on, so mail filters log something recognisable
+fn virus_name(result: &ScanResult, fallback: &str) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for entry in result.vendor_results.iter().flat_map(|results| results.values()) {
+        if let (true, Some(name)) = (entry.detected, entry.result.as_deref()) {
+            *counts.entry(name).or_default() += 1;
+        }
+    }
+    counts.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| fallback.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host::Headless;
+    use crate::AppState;
+    use tempfile::TempDir;
+    use tokio::io::{duplex, DuplexStream};
+
+    fn context(max_stream_bytes: u64) -> Context {
+        Context {
+            host: Host::Headless(Arc::new(Headless::new(AppState::default()))),
+            max_stream_bytes,
+            report_suspicious: true,
+            report_unknown: false,
+            scan_roots: None,
+        }
+    }
+
+    // Send `request` as a client and return everything the server answered
+    async fn exchange(request: &[u8]) -> (Result<(), String>, Vec<u8>) {
+        let (mut client, server) = duplex(64 * 1024);
+        client.write_all(request).await.unwrap();
+        client.shutdown().await.unwrap();
+        let result = handle(&context(1024), server).await;
+
+        let mut reply = Vec::new();
+        client.read_to_end(&mut reply).await.unwrap();
+        (result, reply)
+    }
+
+    async fn send_chunks(client: &mut DuplexStream, chunks: &[&[u8]]) {
+        for chunk in chunks {
+            client.write_u32(chunk.len() as u32).await.unwrap();
+            client.write_all(chunk).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn replies_with_the_delimiter_the_command_used() {
+        assert_eq!(exchange(b"zPING\0").await.1, b"PONG\0");
+        assert_eq!(exchange(b"nPING\n").await.1, b"PONG\n");
+        assert_eq!(exchange(b"PING\r\n").await.1, b"PONG\n");
+        assert_eq!(exchange(b"zFROBNICATE\0").await.1, b"UNKNOWN COMMAND\0");
+    }
+
+    #[tokio::test]
+    async fn rejects_an_over_long_command() {
+        let mut request = b"nSCAN /".to_vec();
+        request.extend(std::iter::repeat_n(b'a', MAX_COMMAND_LEN as usize));
+        request.push(b'\n');
+
+        let (result, reply) = exchange(&request).await;
+        assert_eq!(result, Err("Command too long or not terminated".to_string()));
+        assert!(reply.is_empty());
+    }
+
+    #[tokio::test]
+    async fn rejects_an_unterminated_command() {
+        let (result, _) = exchange(b"zPING").await;
+        assert_eq!(result, Err("Command too long or not terminated".to_string()));
+    }
+
+    #[tokio::test]
+    async fn joins_instream_chunks_into_one_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("stream");
+        let (mut client, mut server) = duplex(64 * 1024);
+        send_chunks(&mut client, &[b"first ", b"second", b""]).await;
+
+        receive_stream(&context(1024), &mut server, dir.path(), &path).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), b"first second");
+    }
+
+    #[tokio::test]
+    async fn stops_an_instream_over_the_size_limit() {
+        let dir = TempDir::new().unwrap();
+        let (mut client, mut server) = duplex(64 * 1024);
+        send_chunks(&mut client, &[b"1234", b"5678", b""]).await;
+
+        let result = receive_stream(&context(6), &mut server, dir.path(), &dir.path().join("stream")).await;
+        assert_eq!(result, Err("INSTREAM size limit exceeded. ERROR".to_string()));
+    }
+
+    #[tokio::test]
+    async fn rejects_an_instream_cut_off_mid_chunk() {
+        let dir = TempDir::new().unwrap();
+        let (mut client, mut server) = duplex(64 * 1024);
+        client.write_u32(10).await.unwrap();
+        client.write_all(b"short").await.unwrap();
+        drop(client);
+
+        let result = receive_stream(&context(1024), &mut server, dir.path(), &dir.path().join("stream")).await;
+        assert_eq!(result, Err("Stream ended in the middle of a chunk. ERROR".to_string()));
+    }
+}
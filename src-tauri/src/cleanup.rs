@@ -0,0 +1,135 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::quarantine::{self, Quarantine};
+use crate::settings::{self, Settings};
+use crate::virus_total;
+use crate::AppState;
+
+// Temp files younger than this may still be in use by a write in progress
+const TEMP_FILE_MIN_AGE_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanupReport {
+    pub dry_run: bool,
+    pub quarantine_purged: Vec<String>,
+    pub history_pruned: usize,
+    pub cache_evicted: usize,
+    pub temp_files_removed: Vec<String>,
+    // Vault payloads or metadata whose other half was never written
+    pub quarantine_orphans_removed: Vec<String>,
+    pub bytes_freed: u64,
+    pub errors: Vec<String>,
+}
+
+// Apply the retention settings. With `dry_run` set nothing is removed and the
+// report lists what would have been.
+pub async fn run(state: &AppState, settings: &Settings, dry_run: bool) -> CleanupReport {
+    let mut report = CleanupReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    match Quarantine::open(&settings.quarantine_settings) {
+        Ok(vault) => {
+            if let Some(days) = settings.quarantine_settings.auto_delete_after_days {
+                purge_expired_quarantine(&vault, days, dry_run, &mut report);
+            }
+            for path in vault.orphaned_files(TEMP_FILE_MIN_AGE_SECS) {
+                if let Some(removed) = remove_file(&path, dry_run, &mut report) {
+                    report.quarantine_orphans_removed.push(removed);
+                }
+            }
+        }
+        Err(e) => report.errors.push(e),
+    }
+
+    report.history_pruned = prune_history(state, settings.scan_history_limit as usize, dry_run).await;
+    report.cache_evicted = virus_total::evict_expired_cache(dry_run);
+
+    for path in stale_temp_files(&settings::data_dir()) {
+        if let Some(removed) = remove_file(&path, dry_run, &mut report) {
+            report.temp_files_removed.push(removed);
+        }
+    }
+
+    log::info!(
+        "Cleanup{}: {} quarantine items, {} history entries, {} cache entries, {} temp files, {} quarantine orphans",
+        if dry_run { " (dry run)" } else { "" },
+        report.quarantine_purged.len(),
+        report.history_pruned,
+        report.cache_evicted,
+        report.temp_files_removed.len(),
+        report.quarantine_orphans_removed.len(),
+    );
+    report
+}
+
+fn purge_expired_quarantine(vault: &Quarantine, days: u32, dry_run: bool, report: &mut CleanupReport) {
+    let items = match vault.list() {
+        Ok(items) => items,
+        Err(e) => {
+            report.errors.push(e);
+            return;
+        }
+    };
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
+    for item in items.into_iter().filter(|item| item.quarantined_at < cutoff) {
+        if !dry_run {
+            if let Err(e) = vault.purge(&item.id) {
+                report.errors.push(e);
+                continue;
+            }
+        }
+        report.bytes_freed += item.file_size;
+        report.quarantine_purged.push(item.id);
+    }
+}
+
+// Keep only the newest `limit` history entries
+async fn prune_history(state: &AppState, limit: usize, dry_run: bool) -> usize {
+    let mut history = state.scan_history.lock().await;
+    if dry_run {
+        return history.len().saturating_sub(limit);
+    }
+    virus_total::update_history(&mut history, move |history| {
+        let excess = history.len().saturating_sub(limit);
+        history.sort_by_key(|result| result.scan_date);
+        history.drain(..excess);
+        excess
+    }).await
+}
+
+// Leftovers from atomic writes that were interrupted before the rename
+fn stale_temp_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries.flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "tmp"))
+        .filter(|entry| quarantine::is_older_than(&entry.path(), TEMP_FILE_MIN_AGE_SECS))
+        .map(|entry| entry.path())
+        .collect()
+}
+
+// Returns the removed path for the report, or None if the removal failed
+fn remove_file(path: &Path, dry_run: bool, report: &mut CleanupReport) -> Option<String> {
+    let size = fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    if !dry_run {
+        if let Err(e) = quarantine::remove_readonly(path) {
+            report.errors.push(format!("Failed to remove {}: {}", path.display(), e));
+            return None;
+        }
+    }
+    report.bytes_freed += size;
+    Some(path.to_string_lossy().to_string())
+}
+
+// Tauri command to run the cleanup now, optionally as a dry run
+#[tauri::command]
+pub async fn run_cleanup(dry_run: bool, state: tauri::State<'_, AppState>) -> Result<CleanupReport, String> {
+    let settings = Settings::load()?;
+    Ok(run(&state, &settings, dry_run).await)
+}
@@ -1,37 +1,117 @@
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use crate::settings::FieldError;
+use crate::throttle::ThrottleConfig;
+use crate::RetryPolicy;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ScannerConfig {
     pub enabled: bool,
-    pub scan_interval: Duration,
-    pub batch_size: usize,
+    pub scan_interval_secs: u64,
     pub max_concurrent_scans: usize,
-    pub retry_attempts: usize,
-    pub retry_delay: Duration,
+    pub retry: RetryPolicy,
+    pub throttle: ThrottleConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct MonitorConfig {
+// Periodic re-lookup of hashes VirusTotal reported clean, which vendors often flag days later
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RelookupConfig {
     pub enabled: bool,
+    // Only files scanned clean within this many days are looked up again
+    pub window_days: u32,
+    pub interval_hours: u64,
+}
+
+impl Default for RelookupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            window_days: 14,
+            interval_hours: 24,
+        }
+    }
+}
+
+impl RelookupConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.window_days == 0 {
+            errors.push(FieldError::new("relookup.window_days", "Re-lookup window must be at least one day"));
+        }
+        if self.interval_hours == 0 {
+            errors.push(FieldError::new("relookup.interval_hours", "Re-lookup interval must be at least one hour"));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteMode {
+    // Move to the freedesktop trash so the file can still be recovered
+    Trash,
+    Permanent,
+    // Overwrite the contents before unlinking
+    SecureOverwrite,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeleteConfig {
+    pub default_mode: DeleteMode,
+    pub overwrite_passes: u32,
+    // Deletable locations besides Downloads, Desktop, Documents, monitored paths and removable media
+    pub allowed_roots: Vec<PathBuf>,
+}
+
+impl Default for DeleteConfig {
+    fn default() -> Self {
+        Self {
+            default_mode: DeleteMode::Trash,
+            overwrite_passes: 1,
+            allowed_roots: Vec::new(),
+        }
+    }
+}
+
+impl DeleteConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if !(1..=7).contains(&self.overwrite_passes) {
+            errors.push(FieldError::new("delete.overwrite_passes", "Overwrite passes must be between 1 and 7"));
+        }
+        if self.allowed_roots.iter().any(|root| !root.is_absolute()) {
+            errors.push(FieldError::new("delete.allowed_roots", "Allowed roots must be absolute paths"));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MonitorConfig {
     pub monitored_paths: Vec<PathBuf>,
     pub excluded_paths: Vec<PathBuf>,
+    pub ignored_patterns: Vec<String>,
     pub file_extensions: Vec<String>,
     pub min_file_size: u64,
     pub max_file_size: u64,
-    pub debounce_duration: Duration,
+    pub debounce_ms: u64,
+    pub max_recursion_depth: u32,
 }
 
 impl Default for ScannerConfig {
     fn default() -> Self {
         Self {
             enabled: true,
-            scan_interval: Duration::from_secs(3600), // 1 hour
-            batch_size: 10,
+            scan_interval_secs: 3600, // 1 hour
+            // The free VirusTotal tier allows 4 lookups a minute, so keep this small
             max_concurrent_scans: 2,
-            retry_attempts: 3,
-            retry_delay: Duration::from_secs(300), // 5 minutes
+            // Most failures are rate limits, which take about a minute to clear
+            retry: RetryPolicy {
+                base_delay_ms: 60_000,
+                max_delay_ms: 300_000,
+                ..RetryPolicy::default()
+            },
+            throttle: ThrottleConfig::default(),
         }
     }
 }
@@ -39,46 +119,73 @@ impl Default for ScannerConfig {
 impl Default for MonitorConfig {
     fn default() -> Self {
         Self {
-            enabled: true,
-            monitored_paths: vec![],
-            excluded_paths: vec![],
-            file_extensions: vec![
-                "exe".to_string(), "dll".to_string(), "sys".to_string(),
-                "msi".to_string(), "zip".to_string(), "rar".to_string(),
+            monitored_paths: Vec::new(),
+            excluded_paths: Vec::new(),
+            ignored_patterns: vec![
+                String::from("**/node_modules/**"),
+                String::from("**/.git/**"),
+                String::from("**/target/**"),
+                String::from("**/*.tmp"),
             ],
-            min_file_size: 1024, // 1KB
-            max_file_size: 100 * 1024 * 1024, // 100MB
-            debounce_duration: Duration::from_secs(1),
+            file_extensions: [
+                "exe", "dll", "sys", "msi", "bat", "cmd", "ps1", "vbs", "js", "jar",
+                "scr", "zip", "rar", "pdf", "doc", "docx",
+            ].iter().map(|ext| ext.to_string()).collect(),
+            min_file_size: 1, // Skip empty files
+            max_file_size: 32 * 1024 * 1024, // Largest upload the /files endpoint accepts
+            debounce_ms: 500,
+            max_recursion_depth: 5,
         }
     }
 }
 
-impl MonitorConfig {
-    pub fn should_monitor_file(&self, path: &PathBuf) -> bool {
-        // Check if monitoring is enabled
-        if !self.enabled {
-            return false;
+impl ScannerConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.scan_interval_secs == 0 {
+            errors.push(FieldError::new("scanner.scan_interval_secs", "Scan interval must be positive"));
+        }
+        if self.max_concurrent_scans == 0 {
+            errors.push(FieldError::new("scanner.max_concurrent_scans", "At least one concurrent scan is required"));
         }
+        for (field, message) in self.retry.problems() {
+            errors.push(FieldError::new(&format!("scanner.retry.{}", field), message));
+        }
+        self.throttle.validate(errors);
+    }
+}
 
+impl MonitorConfig {
+    // Inside one of the monitored folders, at most max_recursion_depth folders down
+    pub fn is_within_monitored_paths(&self, path: &Path) -> bool {
+        self.monitored_paths.iter().any(|root| match path.strip_prefix(root) {
+            Ok(relative) => relative.components().count() <= self.max_recursion_depth as usize + 1,
+            Err(_) => false,
+        })
+    }
+
+    pub fn should_monitor_file(&self, path: &Path) -> bool {
         // Check if path is in excluded paths
         if self.excluded_paths.iter().any(|excluded| path.starts_with(excluded)) {
             return false;
         }
 
-        // Check if path is in monitored paths
-        if !self.monitored_paths.iter().any(|monitored| path.starts_with(monitored)) {
-            return false;
+        // Skip paths matching ignore patterns
+        let path_str = path.to_string_lossy();
+        for pattern in &self.ignored_patterns {
+            if glob::Pattern::new(pattern).map(|p| p.matches(&path_str)).unwrap_or(false) {
+                return false;
+            }
         }
 
-        // Check file extension
-        if let Some(ext) = path.extension() {
-            if !self.file_extensions.iter().any(|allowed_ext| 
+        // Check file extension, an empty list allows everything. Files without one,
+        // such as ELF binaries and scripts, are always checked.
+        if let (false, Some(ext)) = (self.file_extensions.is_empty(), path.extension()) {
+            let allowed = self.file_extensions.iter().any(|allowed_ext|
                 allowed_ext.eq_ignore_ascii_case(&ext.to_string_lossy())
-            ) {
+            );
+            if !allowed {
                 return false;
             }
-        } else {
-            return false;
         }
 
         // Check file size if file exists
@@ -91,4 +198,28 @@ impl MonitorConfig {
 
         true
     }
+
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.min_file_size > self.max_file_size {
+            errors.push(FieldError::new("monitor.min_file_size", "Minimum file size must not exceed the maximum file size"));
+        }
+        if self.max_file_size == 0 {
+            errors.push(FieldError::new("monitor.max_file_size", "Maximum file size must be positive"));
+        }
+        for path in &self.monitored_paths {
+            if !path.is_dir() {
+                errors.push(FieldError::new("monitor.monitored_paths", format!("Directory does not exist: {}", path.display())));
+            }
+        }
+        for pattern in &self.ignored_patterns {
+            if let Err(e) = glob::Pattern::new(pattern) {
+                errors.push(FieldError::new("monitor.ignored_patterns", format!("Invalid pattern {}: {}", pattern, e)));
+            }
+        }
+        for ext in &self.file_extensions {
+            if ext.is_empty() || ext.contains('.') || ext.contains(std::path::MAIN_SEPARATOR) {
+                errors.push(FieldError::new("monitor.file_extensions", format!("Invalid extension: {:?}", ext)));
+            }
+        }
+    }
 }
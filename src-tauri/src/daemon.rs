@@ -0,0 +1,435 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+use crate::background_scanner::BackgroundScannerStatus;
+use crate::file_monitor::FileMonitor;
+use crate::host::{Headless, Host};
+use crate::scan_queue::ScanPriority;
+use crate::settings::{self, Settings};
+use crate::status_indicator::{self, ProtectionStatus};
+use crate::{logger, notifications, siem, AppState};
+
+pub fn default_pid_path() -> PathBuf {
+    settings::data_dir().join("daemon.pid")
+}
+
+pub fn default_socket_path() -> PathBuf {
+    settings::data_dir().join("daemon.sock")
+}
+
+// One JSON object per line on the control socket, answered by one ControlResponse line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    Status,
+    Reload,
+    Stop,
+    Pause,
+    Resume,
+    Scan { paths: Vec<String> },
+    // Keeps the connection open and writes each event as a line
+    Events,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default)]
+    pub data: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ControlResponse {
+    fn from_result(result: Result<Value, String>) -> Self {
+        match result {
+            Ok(data) => Self { ok: true, data, error: None },
+            Err(e) => Self { ok: false, data: Value::Null, error: Some(e) },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub summary: String,
+    pub protection: ProtectionStatus,
+    pub monitoring: bool,
+    pub scanner: BackgroundScannerStatus,
+}
+
+struct Daemon {
+    host: Host,
+    started_at: chrono::DateTime<chrono::Utc>,
+    shutdown: CancellationToken,
+    // What the servers were last started with, so a reload leaves unchanged ones alone
+    settings: tokio::sync::Mutex<Settings>,
+}
+
+// Run the monitor, background scanner, scheduler and quarantine policy without a window until
+// SIGTERM, SIGINT or a stop request. SIGHUP reloads the settings.
+pub async fn run(pid_path: &Path, socket_path: &Path) -> Result<(), String> {
+    let settings = Settings::load()?;
+    logger::init(settings.logging.clone());
+    if settings.api_key.is_empty() {
+        return Err("No API key configured; finish setup in the app first".to_string());
+    }
+
+    // Work spawned through Tauri's runtime helpers runs on this runtime too
+    tauri::async_runtime::set(tokio::runtime::Handle::current());
+
+    // Held until the daemon exits; the lock is what marks a daemon as running
+    let pid_file = write_pid_file(pid_path)?;
+    let listener = bind_socket(socket_path);
+    let result = match listener {
+        Ok(listener) => serve(listener, settings).await,
+        Err(e) => Err(e),
+    };
+
+    let _ = std::fs::remove_file(socket_path);
+    let _ = std::fs::remove_file(pid_path);
+    drop(pid_file);
+    result
+}
+
+async fn serve(listener: UnixListener, settings: Settings) -> Result<(), String> {
+    let mut terminate = signal(SignalKind::terminate())
+        .map_err(|e| format!("Failed to listen for SIGTERM: {}", e))?;
+    let mut interrupt = signal(SignalKind::interrupt())
+        .map_err(|e| format!("Failed to listen for SIGINT: {}", e))?;
+    let mut hangup = signal(SignalKind::hangup())
+        .map_err(|e| format!("Failed to listen for SIGHUP: {}", e))?;
+
+    let daemon = Arc::new(Daemon {
+        host: Host::Headless(Arc::new(Headless::new(AppState::headless(settings.api_key.clone())))),
+        started_at: chrono::Utc::now(),
+        shutdown: CancellationToken::new(),
+        settings: tokio::sync::Mutex::new(settings.clone()),
+    });
+    start_subsystems(&daemon.host, &settings).await;
+    log::info!("Daemon started with pid {}", std::process::id());
+
+    loop {
+        tokio::select! {
+            _ = daemon.shutdown.cancelled() => break,
+            _ = terminate.recv() => break,
+            _ = interrupt.recv() => break,
+            _ = hangup.recv() => {
+                if let Err(e) = reload(&daemon).await {
+                    log::error!("Failed to reload settings: {}", e);
+                }
+            }
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let daemon = daemon.clone();
+                    tokio::spawn(async move { handle_connection(&daemon, stream).await });
+                }
+                Err(e) => log::warn!("Failed to accept control connection: {}", e),
+            },
+        }
+    }
+
+    log::info!("Daemon stopping");
+    let state = daemon.host.state();
+    state.background_scanner.stop_scanning();
+    state.file_monitor.lock().await.stop_monitoring().await?;
+    Ok(())
+}
+
+// Mirrors the app's startup, minus the window and tray
+async fn start_subsystems(host: &Host, settings: &Settings) {
+    let state = host.state();
+    state.error_handler.lock().await.attach(host.clone());
+    notifications::start_digest(host.clone());
+    siem::start_forwarder();
+    state.rest_api.apply(host, &settings.rest_api).await;
+    state.clamd.apply(host, &settings.clamd).await;
+
+    state.background_scanner.load_queue().await;
+    if settings.scanner.enabled {
+        state.background_scanner.set_config(settings.scanner.clone()).await;
+        state.background_scanner.start_scanning(host.clone());
+    }
+
+    state.scheduler.load().await;
+    state.scheduler.sync_builtin_jobs(settings).await;
+    state.scheduler.start(host.clone());
+
+    *state.file_monitor.lock().await = FileMonitor::new(settings.clone());
+    if let Err(e) = state.file_monitor.lock().await.start_monitoring(host.clone()).await {
+        log::error!("Failed to start file monitoring: {}", e);
+    }
+}
+
+// Apply the settings file again, restarting the monitor so it picks up the new filters.
+// The REST API and clamd servers are only restarted when their config changed.
+async fn reload(daemon: &Daemon) -> Result<(), String> {
+    let settings = Settings::load()?;
+    let host = &daemon.host;
+    let state = host.state();
+    logger::reconfigure(settings.logging.clone());
+
+    if !settings.api_key.is_empty() {
+        *state.api_key.lock().await = Some(settings.api_key.clone());
+    }
+
+    state.background_scanner.set_config(settings.scanner.clone()).await;
+    if settings.scanner.enabled {
+        state.background_scanner.start_scanning(host.clone());
+    } else {
+        state.background_scanner.stop_scanning();
+    }
+    state.scheduler.sync_builtin_jobs(&settings).await;
+
+    let mut applied = daemon.settings.lock().await;
+    if applied.rest_api != settings.rest_api {
+        state.rest_api.apply(host, &settings.rest_api).await;
+    }
+    if applied.clamd != settings.clamd {
+        state.clamd.apply(host, &settings.clamd).await;
+    }
+    *applied = settings.clone();
+    drop(applied);
+
+    state.file_monitor.lock().await.apply_settings(host, settings).await?;
+
+    state.status_indicator.refresh();
+    log::info!("Settings reloaded");
+    Ok(())
+}
+
+async fn handle_connection(daemon: &Daemon, stream: UnixStream) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let request = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => request,
+            Err(e) => {
+                let response = ControlResponse::from_result(Err(format!("Invalid request: {}", e)));
+                if write_line(&mut writer, &response).await.is_err() {
+                    return;
+                }
+                continue;
+            }
+        };
+
+        if let ControlRequest::Events = request {
+            stream_events(daemon, &mut writer).await;
+            return;
+        }
+
+        let stop = matches!(request, ControlRequest::Stop);
+        let response = ControlResponse::from_result(execute(daemon, request).await);
+        if write_line(&mut writer, &response).await.is_err() {
+            return;
+        }
+        // Answer first so the client knows the request arrived
+        if stop {
+            daemon.shutdown.cancel();
+        }
+    }
+}
+
+async fn execute(daemon: &Daemon, request: ControlRequest) -> Result<Value, String> {
+    let host = &daemon.host;
+    let state = host.state();
+
+    match request {
+        ControlRequest::Status => {
+            let protection = status_indicator::compute(state).await;
+            let status = DaemonStatus {
+                pid: std::process::id(),
+                started_at: daemon.started_at,
+                summary: protection.description(),
+                protection,
+                monitoring: state.file_monitor.lock().await.is_monitoring(),
+                scanner: state.background_scanner.status().await,
+            };
+            serde_json::to_value(status).map_err(|e| format!("Failed to serialize status: {}", e))
+        }
+        ControlRequest::Reload => reload(daemon).await.map(|()| Value::Null),
+        ControlRequest::Stop => Ok(Value::Null),
+        ControlRequest::Pause => {
+            state.background_scanner.pause(host);
+            Ok(Value::Null)
+        }
+        ControlRequest::Resume => {
+            state.background_scanner.resume(host);
+            Ok(Value::Null)
+        }
+        ControlRequest::Scan { paths } => {
+            let mut queued = 0;
+            for path in paths {
+                let path = PathBuf::from(path);
+                if !path.is_file() {
+                    return Err(format!("Not a file: {}", path.display()));
+                }
+                if state.background_scanner.add_to_queue(path, ScanPriority::UserInitiated).await {
+                    queued += 1;
+                }
+            }
+            Ok(json!({ "queued": queued }))
+        }
+        ControlRequest::Events => Err("Events are streamed, not answered".to_string()),
+    }
+}
+
+async fn stream_events(daemon: &Daemon, writer: &mut (impl AsyncWriteExt + Unpin)) {
+    let Host::Headless(headless) = &daemon.host else {
+        return;
+    };
+    let mut events = headless.subscribe();
+
+    loop {
+        let event = tokio::select! {
+            _ = daemon.shutdown.cancelled() => return,
+            event = events.recv() => event,
+        };
+        match event {
+            Ok(event) => {
+                if write_line(writer, &event).await.is_err() {
+                    return;
+                }
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Lagged(skipped)) => {
+                log::warn!("Event subscriber fell behind, skipped {} events", skipped);
+            }
+            Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+async fn write_line<T: Serialize>(writer: &mut (impl AsyncWriteExt + Unpin), value: &T) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
+
+// Refuse to start next to a running daemon by taking an exclusive lock on the PID file.
+// A PID file left by a crash is unlocked and gets reused. The file is written in place
+// rather than renamed over, since the lock belongs to the open file.
+fn write_pid_file(path: &Path) -> Result<std::fs::File, String> {
+    use std::io::{Read, Seek, Write};
+    use std::os::unix::fs::MetadataExt;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create PID file directory: {}", e))?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| format!("Failed to open PID file: {}", e))?;
+
+    let mut content = String::new();
+    let _ = file.read_to_string(&mut content);
+    let previous = content.trim().parse::<i32>().ok();
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(std::fs::TryLockError::WouldBlock) => {
+            return Err(match previous {
+                Some(pid) => format!("Daemon already running with pid {}", pid),
+                None => "Daemon already running".to_string(),
+            });
+        }
+        // Some network filesystems have no locks, fall back to checking the recorded process
+        Err(std::fs::TryLockError::Error(e)) => {
+            log::warn!("Failed to lock PID file, checking the recorded process instead: {}", e);
+            if let Some(pid) = previous.filter(|pid| process_exists(*pid)) {
+                return Err(format!("Daemon already running with pid {}", pid));
+            }
+        }
+    }
+
+    // A daemon that was exiting may have removed the file between our open and lock
+    let opened = file.metadata().map_err(|e| format!("Failed to read PID file metadata: {}", e))?;
+    let current = std::fs::metadata(path).map_err(|e| format!("Failed to read PID file metadata: {}", e))?;
+    if (opened.dev(), opened.ino()) != (current.dev(), current.ino()) {
+        return Err("PID file was replaced while starting, try again".to_string());
+    }
+
+    file.set_len(0)
+        .and_then(|()| file.rewind())
+        .and_then(|()| writeln!(file, "{}", std::process::id()))
+        .and_then(|()| file.sync_all())
+        .map_err(|e| format!("Failed to write PID file: {}", e))?;
+    Ok(file)
+}
+
+// Signal 0 only checks that the process exists. EPERM means it exists but belongs to
+// another user, which still counts as running.
+fn process_exists(pid: i32) -> bool {
+    // SAFETY: kill with signal 0 delivers nothing; it takes plain integers and touches no memory
+    if unsafe { libc::kill(pid, 0) } == 0 {
+        return true;
+    }
+    std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+fn bind_socket(path: &Path) -> Result<UnixListener, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // Only reached once the PID file showed no other daemon, so any socket here is stale
+    if std::fs::symlink_metadata(path).is_ok() {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove stale socket {}: {}", path.display(), e))?;
+    }
+    let listener = UnixListener::bind(path)
+        .map_err(|e| format!("Failed to listen on {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to restrict socket permissions: {}", e))?;
+    Ok(listener)
+}
+
+// Client side, used by the CLI
+
+async fn connect(socket_path: &Path) -> Result<UnixStream, String> {
+    UnixStream::connect(socket_path).await
+        .map_err(|e| format!("Failed to reach the daemon at {}: {}", socket_path.display(), e))
+}
+
+// Send one request and return the data of a successful answer
+pub async fn request(socket_path: &Path, request: &ControlRequest) -> Result<Value, String> {
+    let stream = connect(socket_path).await?;
+    let (reader, mut writer) = stream.into_split();
+    write_line(&mut writer, request).await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let line = BufReader::new(reader).lines().next_line().await
+        .map_err(|e| format!("Failed to read response: {}", e))?
+        .ok_or_else(|| "The daemon closed the connection".to_string())?;
+    let response: ControlResponse = serde_json::from_str(&line)
+        .map_err(|e| format!("Invalid response from daemon: {}", e))?;
+
+    match response.error {
+        Some(e) if !response.ok => Err(e),
+        _ => Ok(response.data),
+    }
+}
+
+// Call `on_event` with each event until the daemon stops
+pub async fn follow_events(socket_path: &Path, mut on_event: impl FnMut(Value)) -> Result<(), String> {
+    let stream = connect(socket_path).await?;
+    let (reader, mut writer) = stream.into_split();
+    write_line(&mut writer, &ControlRequest::Events).await
+        .map_err(|e| format!("Failed to send request: {}", e))?;
+
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await.map_err(|e| format!("Failed to read event: {}", e))? {
+        match serde_json::from_str(&line) {
+            Ok(event) => on_event(event),
+            Err(e) => log::warn!("Skipping malformed event: {}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::host::Host;
+
+// Consecutive failures after which a subsystem counts as failing rather than degraded
+const FAILING_THRESHOLD: u32 = 3;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ErrorCategory {
+    ApiError,
+    // VirusTotal refused the request because the API key's quota is used up
+    QuotaExceeded,
+    FileSystemError,
+    NetworkError,
+    ConfigurationError,
+    UnknownError,
+}
+
+impl ErrorCategory {
+    // Socket-level kinds are network failures, everything else happened on disk
+    pub fn from_io(kind: std::io::ErrorKind) -> Self {
+        use std::io::ErrorKind;
+
+        match kind {
+            ErrorKind::ConnectionRefused
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NotConnected
+            | ErrorKind::AddrInUse
+            | ErrorKind::AddrNotAvailable
+            | ErrorKind::TimedOut => ErrorCategory::NetworkError,
+            _ => ErrorCategory::FileSystemError,
+        }
+    }
+
+    // A rejected credential is something the user has to fix in the settings
+    pub fn from_status(status: u16) -> Self {
+        match status {
+            401 | 403 => ErrorCategory::ConfigurationError,
+            429 => ErrorCategory::QuotaExceeded,
+            _ => ErrorCategory::ApiError,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Subsystem {
+    Api,
+    Monitor,
+    Quarantine,
+    Scanner,
+    Webhooks,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    Degraded,
+    Failing,
+}
+
+// One kind of failure, counted while it keeps recurring until an operation succeeds again
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorRecord {
+    pub id: String,
+    pub subsystem: Subsystem,
+    pub category: ErrorCategory,
+    pub operation: String,
+    pub message: String,
+    pub context: HashMap<String, String>,
+    pub count: u32,
+    pub first_seen: chrono::DateTime<chrono::Utc>,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SubsystemHealth {
+    pub subsystem: Subsystem,
+    pub status: HealthStatus,
+    pub active_errors: usize,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    pub last_success: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorResolved {
+    pub id: String,
+    pub subsystem: Subsystem,
+    pub operation: String,
+    pub count: u32,
+}
+
+// Retry settings shared by the retry helpers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    // Total attempts including the first one
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+    // Up to this fraction of each delay is added or removed at random
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_ms: 2000,
+            max_delay_ms: 60_000,
+            jitter: 0.25,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // Exponential backoff for the given failed attempt (1-based), capped and jittered
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let base = self.base_delay_ms.saturating_mul(1 << exponent).min(self.max_delay_ms);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = if jitter > 0.0 {
+            rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            1.0
+        };
+        Duration::from_millis((base as f64 * factor) as u64)
+    }
+
+    // Problems with the policy, as (field, message) pairs
+    pub fn problems(&self) -> Vec<(&'static str, &'static str)> {
+        let mut problems = Vec::new();
+        if !(1..=10).contains(&self.max_attempts) {
+            problems.push(("max_attempts", "Attempts must be between 1 and 10"));
+        }
+        if self.max_delay_ms < self.base_delay_ms {
+            problems.push(("max_delay_ms", "Maximum delay must not be below the base delay"));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            problems.push(("jitter", "Jitter must be between 0 and 1"));
+        }
+        problems
+    }
+}
+
+// Tracks errors per subsystem and operation and reports them to the frontend
+#[derive(Debug, Default)]
+pub struct ErrorHandler {
+    host: Option<Host>,
+    active: HashMap<(Subsystem, String, ErrorCategory), ErrorRecord>,
+    health: HashMap<Subsystem, SubsystemHealth>,
+}
+
+impl ErrorHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Events are only emitted once the handler knows where it runs
+    pub fn attach(&mut self, host: Host) {
+        self.host = Some(host);
+    }
+
+    pub fn report_error(
+        &mut self,
+        subsystem: Subsystem,
+        category: ErrorCategory,
+        operation: &str,
+        message: &str,
+        context: HashMap<String, String>,
+    ) -> ErrorRecord {
+        let now = chrono::Utc::now();
+        let record = self.active.entry((subsystem, operation.to_string(), category))
+            .and_modify(|record| {
+                record.count += 1;
+                record.last_seen = now;
+                record.message = message.to_string();
+                record.context.extend(context.clone());
+            })
+            .or_insert_with(|| ErrorRecord {
+                id: uuid::Uuid::new_v4().to_string(),
+                subsystem,
+                category,
+                operation: operation.to_string(),
+                message: message.to_string(),
+                context,
+                count: 1,
+                first_seen: now,
+                last_seen: now,
+            })
+            .clone();
+
+        let health = self.health_entry(subsystem);
+        health.consecutive_failures += 1;
+        health.last_error = Some(message.to_string());
+        self.refresh_health(subsystem);
+
+        log::error!("[{:?}] {} failed: {}", subsystem, operation, message);
+        self.emit("error-occurred", &record);
+        record
+    }
+
+    // Shorthand for the common case of a message with no extra context
+    pub fn report(&mut self, subsystem: Subsystem, category: ErrorCategory, operation: &str, message: &str) -> ErrorRecord {
+        self.report_error(subsystem, category, operation, message, HashMap::new())
+    }
+
+    // Mark an operation as working again, resolving whatever errors it had
+    pub fn report_success(&mut self, subsystem: Subsystem, operation: &str) {
+        let resolved_keys: Vec<_> = self.active.keys()
+            .filter(|(record_subsystem, record_operation, _)| *record_subsystem == subsystem && record_operation == operation)
+            .cloned()
+            .collect();
+
+        let health = self.health_entry(subsystem);
+        health.consecutive_failures = 0;
+        health.last_success = Some(chrono::Utc::now());
+
+        for key in resolved_keys {
+            if let Some(record) = self.active.remove(&key) {
+                log::info!("[{:?}] {} recovered after {} errors", subsystem, operation, record.count);
+                self.emit("error-resolved", &ErrorResolved {
+                    id: record.id,
+                    subsystem,
+                    operation: record.operation,
+                    count: record.count,
+                });
+            }
+        }
+        self.refresh_health(subsystem);
+    }
+
+    pub fn active_errors(&self) -> Vec<ErrorRecord> {
+        let mut errors: Vec<ErrorRecord> = self.active.values().cloned().collect();
+        errors.sort_by_key(|record| std::cmp::Reverse(record.last_seen));
+        errors
+    }
+
+    pub fn last_error(&self) -> Option<&ErrorRecord> {
+        self.active.values().max_by_key(|record| record.last_seen)
+    }
+
+    pub fn health(&self) -> Vec<SubsystemHealth> {
+        [Subsystem::Api, Subsystem::Monitor, Subsystem::Quarantine, Subsystem::Scanner, Subsystem::Webhooks].iter()
+            .map(|subsystem| self.health.get(subsystem).cloned().unwrap_or_else(|| healthy(*subsystem)))
+            .collect()
+    }
+
+    // Forget every active error, e.g. after the user dismissed them
+    pub fn clear(&mut self) {
+        let subsystems: Vec<Subsystem> = self.health.keys().copied().collect();
+        for (_, record) in std::mem::take(&mut self.active) {
+            self.emit("error-resolved", &ErrorResolved {
+                id: record.id,
+                subsystem: record.subsystem,
+                operation: record.operation,
+                count: record.count,
+            });
+        }
+        for subsystem in subsystems {
+            self.health_entry(subsystem).consecutive_failures = 0;
+            self.refresh_health(subsystem);
+        }
+    }
+
+    fn health_entry(&mut self, subsystem: Subsystem) -> &mut SubsystemHealth {
+        self.health.entry(subsystem).or_insert_with(|| healthy(subsystem))
+    }
+
+    fn refresh_health(&mut self, subsystem: Subsystem) {
+        let active_errors = self.active.keys().filter(|(s, _, _)| *s == subsystem).count();
+        let health = self.health_entry(subsystem);
+        health.active_errors = active_errors;
+        health.status = if health.consecutive_failures >= FAILING_THRESHOLD {
+            HealthStatus::Failing
+        } else if active_errors > 0 {
+            HealthStatus::Degraded
+        } else {
+            HealthStatus::Healthy
+        };
+    }
+
+    fn emit<S: Serialize + Clone>(&self, event: &str, payload: &S) {
+        if let Some(host) = &self.host {
+            if let Err(e) = host.emit(event, payload.clone()) {
+                log::error!("Failed to emit {} event: {}", event, e);
+            }
+        }
+    }
+}
+
+fn healthy(subsystem: Subsystem) -> SubsystemHealth {
+    SubsystemHealth {
+        subsystem,
+        status: HealthStatus::Healthy,
+        active_errors: 0,
+        consecutive_failures: 0,
+        last_error: None,
+        last_success: None,
+    }
+}
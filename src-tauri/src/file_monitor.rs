@@ -1,18 +1,25 @@
-use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use notify::{Watcher, RecursiveMode, EventKind};
-use tauri::{WebviewWindow, Emitter};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use notify::{Watcher, RecommendedWatcher, RecursiveMode, EventKind};
+use notify::event::ModifyKind;
+use tokio::time::Instant;
+use crate::host::Host;
+use crate::scan_queue::ScanPriority;
 use crate::settings::Settings;
+use crate::{ErrorCategory, Subsystem};
 
 pub struct FileMonitor {
     is_monitoring: bool,
+    // Dropping the watcher ends the event loop
+    watcher: Option<RecommendedWatcher>,
     download_path: PathBuf,
     settings: Settings,
 }
 
 impl Default for FileMonitor {
     fn default() -> Self {
-        Self::new(Settings::default())
+        Self::new(Settings::load().unwrap_or_default())
     }
 }
 
@@ -22,100 +29,134 @@ impl FileMonitor {
         
         FileMonitor {
             is_monitoring: false,
+            watcher: None,
             download_path,
             settings,
         }
     }
     
-    fn should_monitor_file(path: &Path, settings: &Settings) -> bool {
-        if let Some(extension) = path.extension() {
-            if let Some(ext_str) = extension.to_str() {
-                return settings.file_type_filters.contains(&ext_str.to_lowercase());
-            }
-        }
-        // If no extension or not in filters, use default behavior
-        path.is_file()
-    }
-    
     pub async fn start_monitoring(
         &mut self,
-        window: WebviewWindow,
+        host: Host,
     ) -> Result<(), String> {
-        println!("Starting file monitoring");
+        log::info!("Starting file monitoring");
         
         if self.is_monitoring {
             return Ok(());
         }
         
-        self.is_monitoring = true;
-        
         // Create a channel for the watcher to send events
-        let (tx, rx) = std::sync::mpsc::channel();
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
         
         // Create a watcher
-        let mut watcher = notify::recommended_watcher(tx)
-            .map_err(|e| format!("Failed to create watcher: {}", e))?;
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = tx.send(event);
+        }).map_err(|e| format!("Failed to create watcher: {}", e))?;
         
         // Watch the downloads directory
         watcher.watch(&self.download_path, RecursiveMode::NonRecursive)
             .map_err(|e| format!("Failed to watch downloads directory: {}", e))?;
+        log::info!("Monitoring directory: {}", self.download_path.display());
+
+        // And the folders from the settings, down to the configured depth
+        for path in &self.settings.monitor.monitored_paths {
+            match watcher.watch(path, RecursiveMode::Recursive) {
+                Ok(()) => log::info!("Monitoring directory: {}", path.display()),
+                Err(e) => log::error!("Failed to watch {}: {}", path.display(), e),
+            }
+        }
+
+        self.watcher = Some(watcher);
+        self.is_monitoring = true;
         
-        println!("Monitoring directory: {}", self.download_path.display());
-        
-        // Clone window and settings for the async task
-        let window_clone = window.clone();
+        // Clone settings for the async task
         let settings = self.settings.clone();
+        let download_path = self.download_path.clone();
+        let debounce = Duration::from_millis(settings.monitor.debounce_ms);
         
         // Spawn a task to handle file events
         tokio::spawn(async move {
-            let mut watcher_map = HashMap::new();
-            watcher_map.insert("downloads".to_string(), watcher);
-            
-            // Process events from the watcher
-            while let Ok(event_result) = rx.recv() {
+            // New files wait until they have not been written to for the debounce time,
+            // so a download is checked once it is complete
+            let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+
+            loop {
+                let next_due = pending.values().min().copied();
+                let event_result = tokio::select! {
+                    received = rx.recv() => match received {
+                        Some(event_result) => event_result,
+                        // The watcher was dropped
+                        None => break,
+                    },
+                    _ = sleep_until(next_due) => {
+                        let now = Instant::now();
+                        let due: Vec<PathBuf> = pending.iter()
+                            .filter(|(_, deadline)| **deadline <= now)
+                            .map(|(path, _)| path.clone())
+                            .collect();
+                        for path in due {
+                            pending.remove(&path);
+                            let watched = path.parent() == Some(download_path.as_path())
+                                || settings.monitor.is_within_monitored_paths(&path);
+                            if watched && path.is_file() && settings.monitor.should_monitor_file(&path) {
+                                file_detected(&host, &settings, path).await;
+                            }
+                        }
+                        continue;
+                    }
+                };
+
                 match event_result {
-                    Ok(event) => {
-                        if let EventKind::Create(_) = event.kind {
+                    Ok(event) => match event.kind {
+                        EventKind::Create(_) => {
+                            for path in event.paths {
+                                pending.insert(path, Instant::now() + debounce);
+                            }
+                        }
+                        EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Any) => {
                             for path in event.paths {
-                                if path.is_file() && Self::should_monitor_file(&path, &settings) {
-                                    let _file_name = path.file_name()
-                                        .and_then(|n| n.to_str())
-                                        .unwrap_or("unknown file")
-                                        .to_string();
-                                    
-                                    println!("New file detected: {}", path.display());
-                                    
-                                    // Emit an event to the frontend
-                                    if let Err(e) = window_clone.emit("file-detected", path.to_string_lossy().to_string()) {
-                                        eprintln!("Failed to emit file-detected event: {}", e);
-                                    }
-                                    
-                                    // Show a notification
-                                    if let Err(e) = window_clone.emit("new-file-detected", path.to_string_lossy().to_string()) {
-                                        eprintln!("Failed to emit new-file-detected event: {}", e);
-                                    }
+                                if let Some(deadline) = pending.get_mut(&path) {
+                                    *deadline = Instant::now() + debounce;
                                 }
                             }
                         }
+                        _ => {}
                     },
-                    Err(e) => eprintln!("Watch error: {:?}", e),
+                    Err(e) => {
+                        let state = host.state();
+                        let category = match &e.kind {
+                            notify::ErrorKind::Io(io) => ErrorCategory::from_io(io.kind()),
+                            _ => ErrorCategory::FileSystemError,
+                        };
+                        state.error_handler.lock().await.report(Subsystem::Monitor, category, "watch", &e.to_string());
+                    }
                 }
             }
             
-            println!("File monitoring stopped");
+            log::info!("File monitoring stopped");
         });
         
         Ok(())
     }
+
+    // Take on new settings, restarting the watcher if it was running
+    pub async fn apply_settings(&mut self, host: &Host, settings: Settings) -> Result<(), String> {
+        let was_monitoring = self.is_monitoring();
+        self.stop_monitoring().await?;
+        self.update_settings(settings);
+        if was_monitoring {
+            self.start_monitoring(host.clone()).await?;
+        }
+        Ok(())
+    }
     
-    #[allow(dead_code)]
     pub async fn stop_monitoring(&mut self) -> Result<(), String> {
-        println!("Stopping file monitoring");
+        log::info!("Stopping file monitoring");
+        self.watcher = None;
         self.is_monitoring = false;
         Ok(())
     }
     
-    #[allow(dead_code)]
     pub fn is_monitoring(&self) -> bool {
         self.is_monitoring
     }
@@ -128,12 +169,57 @@ impl FileMonitor {
         self.download_path = path;
     }
     
-    #[allow(dead_code)]
     pub fn update_settings(&mut self, settings: Settings) {
         self.settings = settings;
     }
 }
 
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn file_detected(host: &Host, settings: &Settings, path: PathBuf) {
+    log::info!("New file detected: {}", path.display());
+
+    // Emit an event to the frontend
+    if let Err(e) = host.emit("file-detected", path.to_string_lossy().to_string()) {
+        log::error!("Failed to emit file-detected event: {}", e);
+    }
+
+    // Show a notification
+    if let Err(e) = host.emit("new-file-detected", path.to_string_lossy().to_string()) {
+        log::error!("Failed to emit new-file-detected event: {}", e);
+    }
+
+    // Queue new downloads behind anything the user asked for
+    if settings.auto_scan_downloads {
+        let state = host.state();
+        state.background_scanner.add_to_queue(path, ScanPriority::NewDownload).await;
+    }
+}
+
+// Start or stop watching the downloads folder, e.g. from the tray
+pub async fn set_monitoring(host: &Host, enabled: bool) -> Result<(), String> {
+    let state = host.state();
+    if !*state.is_setup_complete.lock().await {
+        return Err("Setup not complete".to_string());
+    }
+
+    let mut monitor = state.file_monitor.lock().await;
+    if enabled {
+        monitor.start_monitoring(host.clone()).await?;
+    } else {
+        monitor.stop_monitoring().await?;
+    }
+    drop(monitor);
+
+    state.status_indicator.refresh();
+    Ok(())
+}
+
 // Tauri commands for file monitoring
 #[tauri::command]
 pub async fn get_download_path(
@@ -162,24 +248,12 @@ pub async fn set_download_path(
 #[tauri::command]
 pub async fn scan_downloads_folder(
     state: tauri::State<'_, crate::AppState>,
-) -> Result<(), String> {
-    let file_monitor = state.file_monitor.lock().await;
-    let download_path = file_monitor.get_download_path().to_string_lossy().to_string();
-    
-    // Scan all files in the downloads folder
-    let entries = std::fs::read_dir(download_path)
-        .map_err(|e| format!("Failed to read downloads directory: {}", e))?;
-    
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_file() {
-                // Emit an event to scan this file
-                println!("Found file to scan: {}", path.display());
-                // We'll let the frontend handle the actual scanning
-            }
-        }
-    }
-    
-    Ok(())
+    app_handle: tauri::AppHandle,
+) -> Result<String, String> {
+    let download_path = {
+        let file_monitor = state.file_monitor.lock().await;
+        file_monitor.get_download_path().to_path_buf()
+    };
+
+    crate::folder_scan::start_folder_scan(Host::App(app_handle), download_path, false).await
 }
@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::Serialize;
+use tauri::AppHandle;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use crate::host::Host;
+use crate::scan_queue::ScanPriority;
+use crate::settings::Settings;
+use crate::virus_total::{self, ScanResult, ScanStatus};
+use crate::AppState;
+
+// How often files still waiting on the scanner are checked for having left its queue
+const PENDING_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub enum FileOutcome {
+    CachedClean,
+    Scanned,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderScanProgress {
+    pub job_id: String,
+    pub file_path: String,
+    pub index: usize,
+    pub total: usize,
+    pub outcome: FileOutcome,
+    pub status: Option<ScanStatus>,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FolderScanSummary {
+    pub job_id: String,
+    pub folder: String,
+    pub files_found: usize,
+    pub files_filtered: usize,
+    pub skipped_cached: usize,
+    pub scanned: usize,
+    pub clean: usize,
+    pub suspicious: usize,
+    pub malicious: usize,
+    pub failed: usize,
+    pub cancelled: bool,
+    // Files left in the queue because the background scanner is stopped. They are
+    // scanned once it is started again.
+    pub queued: usize,
+    pub scanner_stopped: bool,
+}
+
+// Start scanning a folder in the background and return the job id
+pub async fn start_folder_scan(
+    host: Host,
+    folder: PathBuf,
+    recursive: bool,
+) -> Result<String, String> {
+    if !folder.is_dir() {
+        return Err(format!("Not a directory: {}", folder.display()));
+    }
+
+    let settings = Settings::load()?;
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let token = CancellationToken::new();
+
+    {
+        let state = host.state();
+        state.folder_scans.lock().await.insert(job_id.clone(), token.clone());
+    }
+
+    let job = job_id.clone();
+    tokio::spawn(async move {
+        let summary = run_folder_scan(&host, &job, &folder, recursive, &settings, &token).await;
+
+        let state = host.state();
+        state.folder_scans.lock().await.remove(&job);
+
+        if let Err(e) = host.emit("folder-scan-complete", &summary) {
+            log::error!("Failed to emit folder-scan-complete event: {}", e);
+        }
+    });
+
+    Ok(job_id)
+}
+
+async fn run_folder_scan(
+    host: &Host,
+    job_id: &str,
+    folder: &Path,
+    recursive: bool,
+    settings: &Settings,
+    token: &CancellationToken,
+) -> FolderScanSummary {
+    let state = host.state();
+    let mut summary = FolderScanSummary {
+        job_id: job_id.to_string(),
+        folder: folder.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+
+    let files = collect_files(folder, recursive);
+    summary.files_found = files.len();
+
+    let files: Vec<PathBuf> = files.into_iter()
+        .filter(|path| settings.monitor.should_monitor_file(path))
+        .collect();
+    summary.files_filtered = summary.files_found - files.len();
+
+    let total = files.len();
+    let mut index = 0;
+
+    // Hash everything first so files already known to be clean never hit the API
+    let mut pending: HashMap<PathBuf, String> = HashMap::new();
+    for path in files {
+        if token.is_cancelled() {
+            summary.cancelled = true;
+            return summary;
+        }
+
+        match virus_total::calculate_file_hash(&path).await {
+            Ok(hash) => match virus_total::cached_result(&hash) {
+                Some(cached) if cached.status == ScanStatus::Clean => {
+                    index += 1;
+                    summary.skipped_cached += 1;
+                    summary.clean += 1;
+                    emit_file_progress(host, FolderScanProgress {
+                        job_id: job_id.to_string(),
+                        file_path: path.to_string_lossy().to_string(),
+                        index,
+                        total,
+                        outcome: FileOutcome::CachedClean,
+                        status: Some(ScanStatus::Clean),
+                        message: None,
+                    });
+                }
+                _ => {
+                    pending.insert(path, hash);
+                }
+            },
+            Err(e) => {
+                index += 1;
+                summary.failed += 1;
+                emit_file_progress(host, FolderScanProgress {
+                    job_id: job_id.to_string(),
+                    file_path: path.to_string_lossy().to_string(),
+                    index,
+                    total,
+                    outcome: FileOutcome::Failed,
+                    status: None,
+                    message: Some(e),
+                });
+            }
+        }
+    }
+    if pending.is_empty() {
+        return summary;
+    }
+
+    // The background scanner does the scanning, so quarantine, notifications and webhooks
+    // apply as for any other scan and the API rate limit is shared
+    let scanner = &state.background_scanner;
+    let mut completions = scanner.subscribe_completions();
+    for (path, hash) in &pending {
+        scanner.add_hashed_to_queue(path.clone(), Some(hash.clone()), ScanPriority::UserInitiated).await;
+    }
+    // A scanner the user stopped or disabled stays stopped
+    if !scanner.is_scanning() {
+        log::info!("Background scanner is stopped, folder scan {} left {} files queued", job_id, pending.len());
+        summary.queued = pending.len();
+        summary.scanner_stopped = true;
+        return summary;
+    }
+
+    let mut check = tokio::time::interval(PENDING_CHECK_INTERVAL);
+    while !pending.is_empty() {
+        let finished: Vec<(PathBuf, Result<ScanResult, String>)> = tokio::select! {
+            biased;
+            _ = token.cancelled() => {
+                for path in pending.keys() {
+                    scanner.remove_queued_path(path).await;
+                }
+                summary.cancelled = true;
+                return summary;
+            }
+            completion = completions.recv() => match completion {
+                Ok(completion) => {
+                    // A duplicate of a file already queued elsewhere finishes under the other path
+                    let hash = completion.file_hash.as_deref()
+                        .or(completion.result.as_ref().ok().map(|result| result.file_hash.as_str()));
+                    pending.iter()
+                        .filter(|(path, file_hash)| **path == completion.path || Some(file_hash.as_str()) == hash)
+                        .map(|(path, _)| (path.clone(), completion.result.clone()))
+                        .collect()
+                }
+                // Anything missed is picked up by the check below
+                Err(broadcast::error::RecvError::Lagged(_)) => Vec::new(),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = check.tick() => {
+                // Removed from the queue by the user, or its completion was missed
+                let mut gone = Vec::new();
+                for (path, hash) in &pending {
+                    if !scanner.is_queued(path, Some(hash)).await {
+                        let result = virus_total::cached_result(hash)
+                            .ok_or_else(|| "Removed from the scan queue".to_string());
+                        gone.push((path.clone(), result));
+                    }
+                }
+                gone
+            }
+        };
+
+        for (path, result) in finished {
+            pending.remove(&path);
+            index += 1;
+            let file_path = path.to_string_lossy().to_string();
+            let progress = match result {
+                Ok(scan_result) => {
+                    summary.scanned += 1;
+                    match scan_result.status {
+                        ScanStatus::Malicious => summary.malicious += 1,
+                        ScanStatus::Suspicious => summary.suspicious += 1,
+                        _ => summary.clean += 1,
+                    }
+                    FolderScanProgress {
+                        job_id: job_id.to_string(),
+                        file_path,
+                        index,
+                        total,
+                        outcome: FileOutcome::Scanned,
+                        status: Some(scan_result.status),
+                        message: None,
+                    }
+                }
+                Err(e) => {
+                    summary.failed += 1;
+                    FolderScanProgress {
+                        job_id: job_id.to_string(),
+                        file_path,
+                        index,
+                        total,
+                        outcome: FileOutcome::Failed,
+                        status: None,
+                        message: Some(e),
+                    }
+                }
+            };
+            emit_file_progress(host, progress);
+        }
+    }
+
+    summary
+}
+
+fn emit_file_progress(host: &Host, progress: FolderScanProgress) {
+    if let Err(e) = host.emit("folder-scan-progress", &progress) {
+        log::error!("Failed to emit folder-scan-progress event: {}", e);
+    }
+}
+
+// Collect regular files below a folder; symlinks are never followed
+pub fn collect_files(root: &Path, recursive: bool) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::error!("Failed to read directory {}: {}", dir.display(), e);
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else { continue };
+            if file_type.is_dir() {
+                if recursive {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+
+    files.sort();
+    files
+}
+
+// Tauri commands for folder scans
+#[tauri::command]
+pub async fn scan_folder(
+    path: String,
+    recursive: bool,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    start_folder_scan(Host::App(app_handle), PathBuf::from(path), recursive).await
+}
+
+#[tauri::command]
+pub async fn cancel_folder_scan(
+    job_id: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<bool, String> {
+    let scans = state.folder_scans.lock().await;
+    match scans.get(&job_id) {
+        Some(token) => {
+            token.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::broadcast;
+use crate::AppState;
+
+// Events buffered for each daemon subscriber before the oldest are dropped
+const EVENT_BACKLOG: usize = 256;
+
+// Where the subsystems run: inside the desktop app, or in the daemon without a webview
+#[derive(Clone)]
+pub enum Host {
+    App(AppHandle),
+    Headless(Arc<Headless>),
+}
+
+// Stands in for the app on machines without a display
+pub struct Headless {
+    state: AppState,
+    events: broadcast::Sender<HostEvent>,
+}
+
+// An event the app would have sent to its frontend
+#[derive(Debug, Clone, Serialize)]
+pub struct HostEvent {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+impl Headless {
+    pub fn new(state: AppState) -> Self {
+        Self {
+            state,
+            events: broadcast::channel(EVENT_BACKLOG).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<HostEvent> {
+        self.events.subscribe()
+    }
+}
+
+impl Host {
+    pub fn state(&self) -> &AppState {
+        match self {
+            Host::App(app_handle) => app_handle.state::<AppState>().inner(),
+            Host::Headless(headless) => &headless.state,
+        }
+    }
+
+    // Send an event to the frontend, or to whoever follows the daemon's events
+    pub fn emit<S: Serialize + Clone>(&self, event: &str, payload: S) -> tauri::Result<()> {
+        match self {
+            Host::App(app_handle) => app_handle.emit(event, payload),
+            Host::Headless(headless) => {
+                let payload = serde_json::to_value(payload)?;
+                log::debug!("Event {}: {}", event, payload);
+                // Having no subscribers is normal
+                let _ = headless.events.send(HostEvent { event: event.to_string(), payload });
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::fmt::Debug for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Host::App(_) => f.write_str("Host::App"),
+            Host::Headless(_) => f.write_str("Host::Headless"),
+        }
+    }
+}
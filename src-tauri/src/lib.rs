@@ -1,34 +1,283 @@
 // Error handling
-#[derive(Debug, Clone)]
-pub enum ErrorCategory {
-    ApiError,
-    FileSystemError,
-    NetworkError,
-    ConfigurationError,
-    UnknownError,
-}
-
-#[derive(Debug, Clone)]
-pub struct ErrorHandler {
-    pub last_error: Option<String>,
-    pub error_category: Option<ErrorCategory>,
-}
-
-impl ErrorHandler {
-    pub fn new() -> Self {
-        ErrorHandler {
-            last_error: None,
-            error_category: None,
+pub mod error_handler;
+#[macro_use]
+mod macros;
+
+pub use error_handler::{ErrorCategory, ErrorHandler, RetryPolicy, Subsystem};
+
+pub mod audit;
+mod background_scanner;
+mod clamd;
+mod cleanup;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod settings;
+mod siem;
+mod throttle;
+pub mod virus_total;
+mod webhooks;
+mod file_monitor;
+mod logger;
+mod notifications;
+pub mod folder_scan;
+pub mod host;
+pub mod quarantine;
+mod relookup;
+mod removable_media;
+mod rest_api;
+mod safe_delete;
+mod sample_export;
+mod scan_queue;
+pub mod scanner;
+mod scheduler;
+mod status_indicator;
+mod tray;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{State, Manager};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use settings::Settings;
+use background_scanner::BackgroundScanner;
+use clamd::ClamdServer;
+use file_monitor::FileMonitor;
+use host::Host;
+use rest_api::RestApi;
+use scheduler::Scheduler;
+use status_indicator::StatusIndicator;
+use virus_total::ScanResult;
+use error_handler::{ErrorRecord, SubsystemHealth};
+
+#[derive(Default)]
+pub struct AppState {
+    api_key: Arc<Mutex<Option<String>>>,
+    file_monitor: Arc<Mutex<FileMonitor>>,
+    scan_history: Arc<Mutex<Vec<ScanResult>>>,
+    is_setup_complete: Arc<Mutex<bool>>,
+    folder_scans: Arc<Mutex<HashMap<String, CancellationToken>>>,
+    background_scanner: BackgroundScanner,
+    scheduler: Scheduler,
+    error_handler: Arc<Mutex<ErrorHandler>>,
+    status_indicator: StatusIndicator,
+    rest_api: RestApi,
+    clamd: ClamdServer,
+}
+
+impl AppState {
+    // State for scanning outside the app window, e.g. from the command line
+    pub fn headless(api_key: String) -> Self {
+        Self {
+            api_key: Arc::new(Mutex::new(Some(api_key))),
+            scan_history: Arc::new(Mutex::new(virus_total::load_history())),
+            is_setup_complete: Arc::new(Mutex::new(true)),
+            ..Self::default()
         }
     }
+}
 
-    pub fn set_error(&mut self, error: String, category: ErrorCategory) {
-        self.last_error = Some(error);
-        self.error_category = Some(category);
+#[tauri::command]
+async fn initialize_api(
+    api_key: String,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    // Test the API key first
+    let is_valid = virus_total::test_api_key(api_key.clone()).await?;
+
+    if !is_valid {
+        return Err("Invalid API key".to_string());
     }
 
-    pub fn clear_error(&mut self) {
-        self.last_error = None;
-        self.error_category = None;
+    // Store the API key
+    *state.api_key.lock().await = Some(api_key.clone());
+    audit::record_api_key_change(&api_key);
+
+    // Initialize file monitor with the saved settings
+    let settings = Settings::load().unwrap_or_default();
+    let mut monitor = state.file_monitor.lock().await;
+    *monitor = FileMonitor::new(settings);
+
+    // Mark setup as complete
+    *state.is_setup_complete.lock().await = true;
+
+    Ok(true)
+}
+
+#[tauri::command]
+async fn start_monitoring(
+    app_handle: tauri::AppHandle,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    // Check if setup is complete
+    let is_setup_complete = *state.is_setup_complete.lock().await;
+    if !is_setup_complete {
+        return Err("Setup not complete".to_string());
     }
+
+    // Start monitoring
+    let mut monitor = state.file_monitor.lock().await;
+    monitor.start_monitoring(Host::App(app_handle)).await?;
+    state.status_indicator.refresh();
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_monitoring(app_handle: tauri::AppHandle) -> Result<(), String> {
+    file_monitor::set_monitoring(&Host::App(app_handle), false).await
+}
+
+#[derive(serde::Serialize)]
+struct ErrorSummary {
+    errors: Vec<ErrorRecord>,
+    health: Vec<SubsystemHealth>,
+}
+
+#[tauri::command]
+async fn get_error_summary(state: State<'_, AppState>) -> Result<ErrorSummary, String> {
+    let error_handler = state.error_handler.lock().await;
+    Ok(ErrorSummary {
+        errors: error_handler.active_errors(),
+        health: error_handler.health(),
+    })
+}
+
+#[tauri::command]
+async fn clear_errors(state: State<'_, AppState>) -> Result<(), String> {
+    state.error_handler.lock().await.clear();
+    Ok(())
+}
+
+pub fn run() {
+    logger::init(Settings::load().unwrap_or_default().logging);
+
+    tauri::Builder::default()
+        .manage(AppState::default())
+        .plugin(tauri_plugin_dialog::init())
+        .plugin(tauri_plugin_fs::init())
+        .plugin(tauri_plugin_notification::init())
+        .plugin(tauri_plugin_shell::init())
+        .setup(|app| {
+            // Ensure the main window is created and visible
+            let main_window = app.get_webview_window("main").unwrap();
+            main_window.show().unwrap();
+            main_window.set_focus().unwrap();
+
+            let host = Host::App(app.handle().clone());
+
+            // Watch for USB sticks and other removable volumes being mounted
+            removable_media::start_watcher(host.clone());
+
+            // Report errors to the frontend from here on
+            let state = app.state::<AppState>();
+            tauri::async_runtime::block_on(state.error_handler.lock()).attach(host.clone());
+
+            // Batch clean results into a periodic summary
+            notifications::start_digest(host.clone());
+
+            // Forward security events spooled by this or an earlier run
+            siem::start_forwarder();
+
+            // Show the protection status in the tray and keep it current
+            tauri::async_runtime::block_on(state.status_indicator.start(app.handle().clone()))?;
+
+            let settings = Settings::load().unwrap_or_default();
+
+            // Serve scan requests from local tools when enabled
+            tauri::async_runtime::block_on(state.rest_api.apply(&host, &settings.rest_api));
+
+            // Answer clamd clients such as mail filters when enabled
+            tauri::async_runtime::block_on(state.clamd.apply(&host, &settings.clamd));
+
+            // Restore queued scans and start the background scanner with the persisted config
+            tauri::async_runtime::spawn(async move {
+                let state = host.state();
+                *state.scan_history.lock().await = virus_total::load_history();
+                state.background_scanner.load_queue().await;
+                if settings.scanner.enabled {
+                    state.background_scanner.set_config(settings.scanner.clone()).await;
+                    state.background_scanner.start_scanning(host.clone());
+                }
+
+                // Resume recurring jobs, catching up on any runs missed while the app was closed
+                state.scheduler.load().await;
+                state.scheduler.sync_builtin_jobs(&settings).await;
+                state.scheduler.start(host.clone());
+            });
+            Ok(())
+        })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                // Hide the window instead of closing it
+                window.hide().unwrap_or_default();
+                api.prevent_close();
+            }
+        })
+        .invoke_handler(tauri::generate_handler![
+            initialize_api,
+            start_monitoring,
+            stop_monitoring,
+            get_error_summary,
+            clear_errors,
+            virus_total::scan_file,
+            virus_total::delete_file,
+            virus_total::test_api_key,
+            virus_total::is_setup_complete,
+            virus_total::get_scan_history,
+            virus_total::clear_scan_history,
+            file_monitor::get_download_path,
+            logger::get_recent_logs,
+            file_monitor::set_download_path,
+            file_monitor::scan_downloads_folder,
+            cleanup::run_cleanup,
+            folder_scan::scan_folder,
+            folder_scan::cancel_folder_scan,
+            removable_media::get_removable_media,
+            removable_media::scan_removable_media,
+            removable_media::set_removable_media_policy,
+            removable_media::get_removable_media_prompts,
+            removable_media::answer_removable_media_prompt,
+            audit::get_audit_log,
+            audit::verify_audit_log,
+            audit::export_audit_log,
+            background_scanner::start_background_scanner,
+            background_scanner::stop_background_scanner,
+            background_scanner::pause_background_scanner,
+            background_scanner::resume_background_scanner,
+            background_scanner::get_throttle_state,
+            background_scanner::enqueue_scan,
+            background_scanner::get_scan_queue,
+            background_scanner::move_queued_scan,
+            background_scanner::remove_queued_scan,
+            background_scanner::get_background_scanner_status,
+            quarantine::quarantine_file,
+            quarantine::list_quarantine,
+            quarantine::restore_file,
+            quarantine::purge_quarantine,
+            quarantine::get_auto_quarantine_log,
+            quarantine::undo_auto_quarantine,
+            notifications::get_pending_digest,
+            notifications::send_digest_now,
+            relookup::recheck_clean_files,
+            rest_api::generate_rest_api_token,
+            sample_export::export_quarantine_samples,
+            scheduler::list_scheduled_jobs,
+            scheduler::create_scheduled_job,
+            scheduler::update_scheduled_job,
+            scheduler::delete_scheduled_job,
+            scheduler::run_scheduled_job_now,
+            status_indicator::get_protection_status,
+            status_indicator::acknowledge_threats,
+            webhooks::test_webhook,
+            siem::get_siem_status,
+            siem::test_siem_connection,
+            settings::get_settings,
+            settings::update_settings,
+            settings::validate_settings,
+            settings::reset_settings,
+            settings::get_quarantine_settings,
+            settings::update_quarantine_settings,
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
 }
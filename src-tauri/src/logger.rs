@@ -0,0 +1,283 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::{Mutex, RwLock};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use crate::settings::{self, FieldError};
+
+const LOG_FILE_NAME: &str = "app.log";
+// Targets of the library and of the app binary that wraps it
+const CRATE_PREFIXES: [&str; 2] = ["virus_scanner_app_lib", "virus_scanner_app"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    // Default level: error, warn, info, debug or trace
+    pub level: String,
+    // Overrides keyed by module, e.g. "virus_total" or "reqwest"
+    pub module_levels: HashMap<String, String>,
+    pub max_file_size: u64,
+    pub max_files: usize,
+    pub max_age_days: u32,
+    // Mirror records to stderr, handy when running from a terminal
+    pub console: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".to_string(),
+            module_levels: HashMap::new(),
+            max_file_size: 10 * 1024 * 1024,
+            max_files: 5,
+            max_age_days: 14,
+            console: true,
+        }
+    }
+}
+
+impl LogConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if LevelFilter::from_str(&self.level).is_err() {
+            errors.push(FieldError::new("logging.level", format!("Unknown log level: {}", self.level)));
+        }
+        for (module, level) in &self.module_levels {
+            if LevelFilter::from_str(level).is_err() {
+                errors.push(FieldError::new("logging.module_levels", format!("Unknown log level for {}: {}", module, level)));
+            }
+        }
+        if self.max_file_size < 64 * 1024 {
+            errors.push(FieldError::new("logging.max_file_size", "Log files must be allowed at least 64 KB"));
+        }
+        if self.max_files == 0 {
+            errors.push(FieldError::new("logging.max_files", "At least one log file must be kept"));
+        }
+        if self.max_age_days == 0 {
+            errors.push(FieldError::new("logging.max_age_days", "Logs must be kept for at least one day"));
+        }
+    }
+
+    fn default_filter(&self) -> LevelFilter {
+        LevelFilter::from_str(&self.level).unwrap_or(LevelFilter::Info)
+    }
+
+    // The most specific module override wins, otherwise the default level applies
+    fn filter_for(&self, target: &str) -> LevelFilter {
+        let local = CRATE_PREFIXES.iter()
+            .find_map(|prefix| target.strip_prefix(prefix)?.strip_prefix("::"))
+            .unwrap_or(target);
+
+        self.module_levels.iter()
+            .filter(|(module, _)| [target, local].iter().any(|name| {
+                *name == module.as_str() || name.starts_with(&format!("{}::", module))
+            }))
+            .max_by_key(|(module, _)| module.len())
+            .and_then(|(_, level)| LevelFilter::from_str(level).ok())
+            .unwrap_or_else(|| self.default_filter())
+    }
+
+    fn max_filter(&self) -> LevelFilter {
+        self.module_levels.values()
+            .filter_map(|level| LevelFilter::from_str(level).ok())
+            .chain(std::iter::once(self.default_filter()))
+            .max()
+            .unwrap_or(LevelFilter::Info)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+struct FileLogger {
+    config: RwLock<LogConfig>,
+    file: Mutex<Option<File>>,
+    dir: PathBuf,
+}
+
+static LOGGER: once_cell::sync::OnceCell<&'static FileLogger> = once_cell::sync::OnceCell::new();
+
+fn log_dir() -> PathBuf {
+    settings::data_dir().join("logs")
+}
+
+// Install the logger. Later calls only update the configuration.
+pub fn init(config: LogConfig) {
+    if let Some(logger) = LOGGER.get() {
+        apply_config(logger, config);
+        return;
+    }
+
+    let logger: &'static FileLogger = Box::leak(Box::new(FileLogger {
+        config: RwLock::new(config.clone()),
+        file: Mutex::new(None),
+        dir: log_dir(),
+    }));
+
+    if LOGGER.set(logger).is_ok() && log::set_logger(logger).is_ok() {
+        log::set_max_level(config.max_filter());
+        logger.prune_rotated(&config);
+    }
+}
+
+// Apply new levels and limits from the settings
+pub fn reconfigure(config: LogConfig) {
+    if let Some(logger) = LOGGER.get() {
+        apply_config(logger, config);
+    }
+}
+
+fn apply_config(logger: &FileLogger, config: LogConfig) {
+    log::set_max_level(config.max_filter());
+    *logger.config.write().unwrap() = config;
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.config.read().unwrap().filter_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let entry = LogRecord {
+            timestamp: chrono::Utc::now(),
+            level: record.level().to_string(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+        };
+        let config = self.config.read().unwrap().clone();
+
+        if config.console {
+            eprintln!("[{} {} {}] {}", entry.timestamp.format("%H:%M:%S"), entry.level, entry.target, entry.message);
+        }
+
+        let Ok(line) = serde_json::to_string(&entry) else { return };
+        if let Err(e) = self.write_line(&line, &config) {
+            eprintln!("Failed to write log file: {}", e);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.file.lock().unwrap().as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+impl FileLogger {
+    fn current_path(&self) -> PathBuf {
+        self.dir.join(LOG_FILE_NAME)
+    }
+
+    fn write_line(&self, line: &str, config: &LogConfig) -> std::io::Result<()> {
+        let mut file = self.file.lock().unwrap();
+
+        let size = fs::metadata(self.current_path()).map(|m| m.len()).unwrap_or(0);
+        if size + line.len() as u64 + 1 > config.max_file_size && size > 0 {
+            *file = None;
+            self.rotate(config)?;
+        }
+
+        if file.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            *file = Some(OpenOptions::new().create(true).append(true).open(self.current_path())?);
+        }
+
+        let handle = file.as_mut().expect("log file opened above");
+        writeln!(handle, "{}", line)
+    }
+
+    // Move the current file aside with a timestamp and drop whatever falls outside the limits
+    fn rotate(&self, config: &LogConfig) -> std::io::Result<()> {
+        let rotated = self.dir.join(format!("app-{}.log", chrono::Utc::now().format("%Y%m%dT%H%M%S%.3f")));
+        fs::rename(self.current_path(), rotated)?;
+        self.prune_rotated(config);
+        Ok(())
+    }
+
+    fn prune_rotated(&self, config: &LogConfig) {
+        let cutoff = std::time::SystemTime::now()
+            - std::time::Duration::from_secs(config.max_age_days as u64 * 86400);
+
+        let mut rotated = rotated_files(&self.dir);
+        // Newest first; the current file counts toward max_files
+        rotated.reverse();
+        for (index, path) in rotated.iter().enumerate() {
+            let expired = fs::metadata(path)
+                .and_then(|m| m.modified())
+                .map(|modified| modified < cutoff)
+                .unwrap_or(false);
+            if index + 1 >= config.max_files || expired {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+}
+
+// Rotated files, oldest first. Their timestamped names sort chronologically.
+fn rotated_files(dir: &std::path::Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir).into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("app-") && name.ends_with(".log"))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+// The newest `limit` records at or above `min_level`, oldest first
+pub fn recent_logs(limit: usize, min_level: Option<Level>) -> Vec<LogRecord> {
+    let dir = log_dir();
+    let mut files = rotated_files(&dir);
+    files.push(dir.join(LOG_FILE_NAME));
+
+    let mut records = VecDeque::with_capacity(limit);
+    // Read newest files first and stop once enough records are collected
+    for path in files.iter().rev() {
+        let Ok(file) = File::open(path) else { continue };
+        let mut from_file: Vec<LogRecord> = BufReader::new(file).lines()
+            .map_while(Result::ok)
+            .filter_map(|line| serde_json::from_str::<LogRecord>(&line).ok())
+            .filter(|record| match (min_level, Level::from_str(&record.level)) {
+                (Some(min), Ok(level)) => level <= min,
+                _ => true,
+            })
+            .collect();
+
+        while let Some(record) = from_file.pop() {
+            if records.len() == limit {
+                break;
+            }
+            records.push_front(record);
+        }
+        if records.len() == limit {
+            break;
+        }
+    }
+
+    records.into()
+}
+
+// Tauri command for the log viewer
+#[tauri::command]
+pub async fn get_recent_logs(limit: Option<usize>, level: Option<String>) -> Result<Vec<LogRecord>, String> {
+    let min_level = level
+        .map(|level| Level::from_str(&level).map_err(|_| format!("Unknown log level: {}", level)))
+        .transpose()?;
+    Ok(recent_logs(limit.unwrap_or(200), min_level))
+}
@@ -1,30 +1,46 @@
+// Run `$body` (an expression producing a future of `Result<T, E>`) until it succeeds or
+// the policy's attempt cap is reached, sleeping with jittered exponential backoff in
+// between. The final failure is reported to the error handler under the category
+// `$category` gives for the error, and a success resolves any errors recorded earlier
+// for the same operation. An optional predicate on the error stops retrying early for
+// failures that cannot succeed on a later attempt.
+//
+//     retry_with_backoff!(state.error_handler, Subsystem::Api, "lookup_hash", policy, vt.lookup_hash(path, hash), |e: &ScanError| e.category)
 #[macro_export]
 macro_rules! retry_with_backoff {
-    ($error_handler:expr, $operation:expr, $body:expr) => {{
+    ($error_handler:expr, $subsystem:expr, $operation:expr, $policy:expr, $body:expr, $category:expr) => {
+        $crate::retry_with_backoff!($error_handler, $subsystem, $operation, $policy, $body, $category, |_| true)
+    };
+    ($error_handler:expr, $subsystem:expr, $operation:expr, $policy:expr, $body:expr, $category:expr, $should_retry:expr) => {{
+        let policy: &$crate::error_handler::RetryPolicy = &$policy;
+        let max_attempts = policy.max_attempts.max(1);
         let mut attempts = 0;
-        let max_attempts = 3;
-        let mut last_error = None;
 
         loop {
             match $body.await {
                 Ok(result) => {
-                    // Report success if we had to retry
-                    if attempts > 0 {
-                        $error_handler.lock().await.report_success($operation).await;
-                    }
+                    $error_handler.lock().await.report_success($subsystem, $operation);
                     break Ok(result);
                 }
                 Err(e) => {
                     attempts += 1;
-                    last_error = Some(e.to_string());
-                    
-                    if attempts >= max_attempts {
-                        break Err(last_error.unwrap());
+                    let message = e.to_string();
+
+                    if attempts >= max_attempts || !($should_retry)(&e) {
+                        let mut context = std::collections::HashMap::new();
+                        context.insert("attempts".to_string(), attempts.to_string());
+                        $error_handler.lock().await.report_error(
+                            $subsystem,
+                            ($category)(&e),
+                            $operation,
+                            &message,
+                            context,
+                        );
+                        break Err(e);
                     }
-                    
-                    // Exponential backoff
-                    let delay = std::time::Duration::from_secs(2u64.pow(attempts));
-                    tokio::time::sleep(delay).await;
+
+                    log::warn!("{} failed (attempt {}/{}): {}", $operation, attempts, max_attempts, message);
+                    tokio::time::sleep(policy.delay_for(attempts)).await;
                 }
             }
         }
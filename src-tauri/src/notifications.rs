@@ -0,0 +1,367 @@
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tauri_plugin_notification::NotificationExt;
+use tokio::time::{sleep, Duration};
+use crate::host::Host;
+use crate::settings::{FieldError, Settings};
+use crate::virus_total::{ScanResult, ScanStatus};
+
+const RATE_WINDOW: Duration = Duration::from_secs(3600);
+const TIME_FORMAT: &str = "%H:%M";
+
+// Notifications shown in the last hour and what is waiting for the next digest
+static DISPATCH: once_cell::sync::Lazy<std::sync::Mutex<Dispatch>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(Dispatch::default()));
+
+#[derive(Default)]
+struct Dispatch {
+    shown: VecDeque<Instant>,
+    digest: Digest,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Digest {
+    pub clean: usize,
+    pub suspicious: usize,
+    pub malicious: usize,
+    pub other: usize,
+    // Notifications held back by quiet hours or the rate limit
+    pub held_back: Vec<HeldNotification>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HeldNotification {
+    pub title: String,
+    pub body: String,
+}
+
+impl Digest {
+    fn is_empty(&self) -> bool {
+        self.clean + self.suspicious + self.malicious + self.other == 0 && self.held_back.is_empty()
+    }
+
+    fn add_result(&mut self, status: &ScanStatus) {
+        match status {
+            ScanStatus::Clean => self.clean += 1,
+            ScanStatus::Suspicious => self.suspicious += 1,
+            ScanStatus::Malicious => self.malicious += 1,
+            _ => self.other += 1,
+        }
+    }
+
+    fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        for (count, label) in [
+            (self.malicious, "malicious"),
+            (self.suspicious, "suspicious"),
+            (self.clean, "clean"),
+            (self.other, "other"),
+        ] {
+            if count > 0 {
+                parts.push(format!("{} {}", count, label));
+            }
+        }
+
+        let mut summary = String::new();
+        if !parts.is_empty() {
+            summary = format!("Scanned {} files: {}.", self.clean + self.suspicious + self.malicious + self.other, parts.join(", "));
+        }
+        if !self.held_back.is_empty() {
+            if !summary.is_empty() {
+                summary.push(' ');
+            }
+            let held: Vec<String> = self.held_back.iter()
+                .map(|held| format!("{}: {}", held.title, held.body))
+                .collect();
+            summary.push_str(&format!("{} notifications held back: {}.", held.len(), held.join("; ")));
+        }
+        summary
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Normal,
+    // May break through quiet hours and is never rate limited
+    Critical,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleAction {
+    Notify,
+    Digest,
+    Suppress,
+}
+
+// Matches a scan result when every condition that is set matches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationRule {
+    pub name: String,
+    // Any of these verdicts; empty matches all
+    #[serde(default)]
+    pub verdicts: Vec<ScanStatus>,
+    // Glob on the scanned file's path
+    #[serde(default)]
+    pub path_pattern: Option<String>,
+    // Any of these engines flagged the file; empty matches all
+    #[serde(default)]
+    pub engines: Vec<String>,
+    pub action: RuleAction,
+}
+
+impl NotificationRule {
+    fn matches(&self, result: &ScanResult) -> bool {
+        if !self.verdicts.is_empty() && !self.verdicts.contains(&result.status) {
+            return false;
+        }
+        if let Some(pattern) = &self.path_pattern {
+            let matched = glob::Pattern::new(pattern)
+                .map(|pattern| pattern.matches_path(Path::new(&result.file_path)))
+                .unwrap_or(false);
+            if !matched {
+                return false;
+            }
+        }
+        if !self.engines.is_empty() {
+            let flagged = result.vendor_results.as_ref().is_some_and(|vendors| {
+                vendors.iter().any(|(engine, entry)| {
+                    entry.detected && self.engines.iter().any(|wanted| wanted.eq_ignore_ascii_case(engine))
+                })
+            });
+            if !flagged {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+// Local times in HH:MM; a start after the end spans midnight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: String,
+    pub end: String,
+    // Let malicious verdicts through anyway
+    #[serde(default = "default_true")]
+    pub allow_critical: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl QuietHours {
+    fn contains(&self, time: chrono::NaiveTime) -> bool {
+        let (Ok(start), Ok(end)) = (
+            chrono::NaiveTime::parse_from_str(&self.start, TIME_FORMAT),
+            chrono::NaiveTime::parse_from_str(&self.end, TIME_FORMAT),
+        ) else {
+            return false;
+        };
+
+        if start <= end {
+            time >= start && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    // Checked in order, the first match decides; results no rule matches are notified
+    pub rules: Vec<NotificationRule>,
+    pub quiet_hours: Option<QuietHours>,
+    // Normal notifications beyond this in an hour go to the digest instead
+    pub max_per_hour: u32,
+    // Without the digest, results routed to it are notified directly
+    pub digest_enabled: bool,
+    pub digest_interval_minutes: u64,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                NotificationRule {
+                    name: "Threats".to_string(),
+                    verdicts: vec![ScanStatus::Malicious, ScanStatus::Suspicious],
+                    path_pattern: None,
+                    engines: Vec::new(),
+                    action: RuleAction::Notify,
+                },
+                NotificationRule {
+                    name: "Clean files".to_string(),
+                    verdicts: vec![ScanStatus::Clean],
+                    path_pattern: None,
+                    engines: Vec::new(),
+                    action: RuleAction::Digest,
+                },
+            ],
+            quiet_hours: None,
+            max_per_hour: 20,
+            digest_enabled: true,
+            digest_interval_minutes: 60,
+        }
+    }
+}
+
+impl NotificationConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        for rule in &self.rules {
+            if let Some(pattern) = &rule.path_pattern {
+                if glob::Pattern::new(pattern).is_err() {
+                    errors.push(FieldError::new("notifications.rules", format!("Invalid path pattern in rule {}: {}", rule.name, pattern)));
+                }
+            }
+        }
+        if let Some(quiet_hours) = &self.quiet_hours {
+            for time in [&quiet_hours.start, &quiet_hours.end] {
+                if chrono::NaiveTime::parse_from_str(time, TIME_FORMAT).is_err() {
+                    errors.push(FieldError::new("notifications.quiet_hours", format!("Times must be HH:MM, got {}", time)));
+                }
+            }
+        }
+        if self.max_per_hour == 0 {
+            errors.push(FieldError::new("notifications.max_per_hour", "At least one notification an hour must be allowed"));
+        }
+        if self.digest_interval_minutes == 0 {
+            errors.push(FieldError::new("notifications.digest_interval_minutes", "Digest interval must be positive"));
+        }
+    }
+
+    fn in_quiet_hours(&self) -> bool {
+        self.quiet_hours.as_ref()
+            .is_some_and(|quiet_hours| quiet_hours.contains(chrono::Local::now().time()))
+    }
+
+    fn action_for(&self, result: &ScanResult) -> RuleAction {
+        let action = self.rules.iter()
+            .find(|rule| rule.matches(result))
+            .map(|rule| rule.action)
+            .unwrap_or(RuleAction::Notify);
+
+        match action {
+            RuleAction::Digest if !self.digest_enabled => RuleAction::Notify,
+            action => action,
+        }
+    }
+}
+
+// Show a notification unless quiet hours or the rate limit hold it back for the digest
+pub fn notify(host: &Host, title: &str, body: &str, priority: Priority) {
+    let settings = Settings::cached();
+    let config = &settings.notifications;
+    let mut dispatch = DISPATCH.lock().unwrap();
+
+    let critical = priority == Priority::Critical;
+    let quiet = config.in_quiet_hours()
+        && !(critical && config.quiet_hours.as_ref().is_some_and(|quiet_hours| quiet_hours.allow_critical));
+
+    let now = Instant::now();
+    while dispatch.shown.front().is_some_and(|shown| now.duration_since(*shown) > RATE_WINDOW) {
+        dispatch.shown.pop_front();
+    }
+    let limited = !critical && dispatch.shown.len() >= config.max_per_hour as usize;
+
+    if quiet || limited {
+        log::debug!("Holding back notification \"{}\" ({})", title, if quiet { "quiet hours" } else { "rate limit" });
+        dispatch.digest.held_back.push(HeldNotification {
+            title: title.to_string(),
+            body: body.to_string(),
+        });
+        return;
+    }
+
+    dispatch.shown.push_back(now);
+    drop(dispatch);
+    show(host, title, body);
+}
+
+// Route a finished scan through the notification rules
+pub fn scan_completed(host: &Host, result: &ScanResult) {
+    let settings = Settings::cached();
+    if !settings.notify_on_scan_completion {
+        return;
+    }
+
+    match settings.notifications.action_for(result) {
+        RuleAction::Suppress => {}
+        RuleAction::Digest => DISPATCH.lock().unwrap().digest.add_result(&result.status),
+        RuleAction::Notify => {
+            let priority = if result.status == ScanStatus::Malicious { Priority::Critical } else { Priority::Normal };
+            let body = match (result.detection_count, result.total_engines) {
+                (Some(detections), Some(total)) => format!("{} is {:?} ({}/{} engines)", result.file_name, result.status, detections, total),
+                _ => format!("{} is {:?}", result.file_name, result.status),
+            };
+            notify(host, "Scan complete", &body, priority);
+        }
+    }
+}
+
+// Send the pending digest once per interval, waiting out quiet hours
+pub fn start_digest(host: Host) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let interval = Settings::cached().notifications.digest_interval_minutes;
+            sleep(Duration::from_secs(interval.max(1) * 60)).await;
+
+            if Settings::cached().notifications.in_quiet_hours() {
+                continue;
+            }
+            flush_digest(&host);
+        }
+    });
+}
+
+fn flush_digest(host: &Host) {
+    let digest = std::mem::take(&mut DISPATCH.lock().unwrap().digest);
+    if digest.is_empty() {
+        return;
+    }
+
+    if let Err(e) = host.emit("notification-digest", &digest) {
+        log::error!("Failed to emit notification-digest event: {}", e);
+    }
+    show(host, "Scan summary", &digest.summary());
+}
+
+// System notification, falling back to an in-app one when the platform refuses. The daemon
+// has neither, so it logs the notification for whoever follows its events.
+fn show(host: &Host, title: &str, body: &str) {
+    let Host::App(app_handle) = host else {
+        log::info!("Notification: {}: {}", title, body);
+        host.emit("notification", serde_json::json!({ "title": title, "body": body })).ok();
+        return;
+    };
+
+    let result = app_handle.notification()
+        .builder()
+        .title(title)
+        .body(body)
+        .show();
+
+    if let Err(e) = result {
+        log::error!("Failed to show system notification: {}", e);
+        if let Err(e) = host.emit("notification", serde_json::json!({ "title": title, "body": body })) {
+            log::error!("Failed to emit notification event: {}", e);
+        }
+    }
+}
+
+// Tauri commands for notifications
+#[tauri::command]
+pub async fn get_pending_digest() -> Result<Digest, String> {
+    Ok(DISPATCH.lock().unwrap().digest.clone())
+}
+
+#[tauri::command]
+pub async fn send_digest_now(app_handle: AppHandle) -> Result<(), String> {
+    flush_digest(&Host::App(app_handle));
+    Ok(())
+}
@@ -0,0 +1,755 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use base64::Engine;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Digest;
+use crate::audit;
+use crate::host::Host;
+use crate::notifications::{self, Priority};
+use crate::settings::{self, QuarantineSettings, Settings};
+use crate::siem;
+use crate::virus_total::{ScanResult, ScanStatus};
+use crate::AppState;
+use crate::{ErrorCategory, Subsystem};
+
+// Marks a vault payload so a stray file is never mistaken for one
+const PAYLOAD_MAGIC: &[u8; 8] = b"VSQUAR01";
+const KEY_LEN: usize = 32;
+const BUFFER_SIZE: usize = 64 * 1024;
+// Automatic actions kept in the log, oldest dropped first
+const ACTION_LOG_LIMIT: usize = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHashes {
+    pub sha256: String,
+    pub sha1: String,
+    pub md5: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantineItem {
+    pub id: String,
+    pub original_path: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub hashes: FileHashes,
+    pub verdict: Option<ScanStatus>,
+    pub detection_count: Option<u32>,
+    pub quarantined_at: chrono::DateTime<chrono::Utc>,
+    // Unix permission bits of the original file, reapplied on restore
+    pub original_mode: Option<u32>,
+}
+
+// Metadata as written next to the payload. The key stays in the vault and is never
+// part of what the UI or CLI receive.
+#[derive(Serialize, Deserialize)]
+struct StoredItem {
+    #[serde(flatten)]
+    item: QuarantineItem,
+    // Per-item key the payload is XORed with so it can never run from the vault
+    key: String,
+}
+
+// Item ids come from the UI and CLI; only a UUID may ever become part of a vault path
+fn parse_id(id: &str) -> Result<uuid::Uuid, String> {
+    uuid::Uuid::parse_str(id).map_err(|_| format!("Invalid quarantine id: {}", id))
+}
+
+// Directory of neutralized files. Each item is a `<id>.bin` payload next to `<id>.json` metadata.
+pub struct Quarantine {
+    vault_dir: PathBuf,
+}
+
+impl Quarantine {
+    // A configured location gets a vault subdirectory of its own, so the permissions of the
+    // folder the user picked are left as they are
+    pub fn open(settings: &QuarantineSettings) -> Result<Self, String> {
+        let location = settings.quarantine_location.as_ref().map(PathBuf::from);
+        let vault_dir = location.as_deref()
+            .unwrap_or(&settings::data_dir())
+            .join("quarantine");
+
+        if !vault_dir.is_dir() {
+            fs::create_dir_all(&vault_dir)
+                .map_err(|e| format!("Failed to create quarantine directory: {}", e))?;
+            set_mode(&vault_dir, 0o700)?;
+            if let Some(location) = &location {
+                move_legacy_items(location, &vault_dir);
+            }
+        }
+
+        Ok(Self { vault_dir })
+    }
+
+    fn payload_path(&self, id: &uuid::Uuid) -> PathBuf {
+        self.vault_dir.join(format!("{}.bin", id))
+    }
+
+    fn metadata_path(&self, id: &uuid::Uuid) -> PathBuf {
+        self.vault_dir.join(format!("{}.json", id))
+    }
+
+    // Move a file into the vault. With `verify` set, the payload is decoded and checked
+    // against the original hash before the original is removed.
+    pub fn quarantine(&self, path: &Path, scan: Option<&ScanResult>, verify: bool) -> Result<QuarantineItem, String> {
+        self.audited(path, self.move_into_vault(path, scan, None, verify))
+    }
+
+    // Quarantine the file a scan was for, refusing if its content no longer matches the scanned hash
+    pub fn quarantine_scanned(&self, result: &ScanResult, verify: bool) -> Result<QuarantineItem, String> {
+        let path = Path::new(&result.file_path);
+        self.audited(path, self.move_into_vault(path, Some(result), Some(&result.file_hash), verify))
+    }
+
+    fn audited(&self, path: &Path, result: Result<QuarantineItem, String>) -> Result<QuarantineItem, String> {
+        let details = match &result {
+            Ok(item) => json!({ "id": item.id, "sha256": item.hashes.sha256, "verdict": item.verdict }),
+            Err(e) => json!({ "error": e }),
+        };
+        audit::record("quarantine", &path.to_string_lossy(), result.is_ok(), details);
+        if let Ok(item) = &result {
+            siem::quarantine_action("quarantine", item);
+        }
+        result
+    }
+
+    fn move_into_vault(
+        &self,
+        path: &Path,
+        scan: Option<&ScanResult>,
+        expected_sha256: Option<&str>,
+        verify: bool,
+    ) -> Result<QuarantineItem, String> {
+        let metadata = fs::symlink_metadata(path)
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+        if !metadata.is_file() {
+            return Err(format!("Not a regular file: {}", path.display()));
+        }
+
+        let id = uuid::Uuid::new_v4();
+        let mut key = [0u8; KEY_LEN];
+        rand::thread_rng().fill_bytes(&mut key);
+
+        let payload_path = self.payload_path(&id);
+        let hashes = match encode_file(path, &payload_path, &key) {
+            Ok(hashes) => hashes,
+            Err(e) => {
+                let _ = fs::remove_file(&payload_path);
+                return Err(e);
+            }
+        };
+
+        // The hash is taken from the bytes copied into the vault, so a file replaced after
+        // its scan is never removed on the strength of someone else's verdict
+        if expected_sha256.is_some_and(|expected| !expected.eq_ignore_ascii_case(&hashes.sha256)) {
+            let _ = fs::remove_file(&payload_path);
+            return Err(format!("{} has changed since it was scanned", path.display()));
+        }
+
+        if verify {
+            let decoded = decode_to(&payload_path, &key, &mut std::io::sink())?;
+            if decoded != hashes.sha256 {
+                let _ = fs::remove_file(&payload_path);
+                return Err("Quarantine copy failed verification".to_string());
+            }
+        }
+        set_mode(&payload_path, 0o400)?;
+
+        let item = QuarantineItem {
+            id: id.to_string(),
+            original_path: path.to_string_lossy().to_string(),
+            file_name: path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            file_size: metadata.len(),
+            hashes,
+            verdict: scan.map(|result| result.status.clone()),
+            detection_count: scan.and_then(|result| result.detection_count),
+            quarantined_at: chrono::Utc::now(),
+            original_mode: file_mode(&metadata),
+        };
+        let stored = StoredItem {
+            item,
+            key: base64::engine::general_purpose::STANDARD.encode(key),
+        };
+
+        if let Err(e) = self.write_metadata(&id, &stored) {
+            let _ = fs::remove_file(&payload_path);
+            return Err(e);
+        }
+
+        if let Err(e) = fs::remove_file(path) {
+            self.remove_item(&id);
+            return Err(format!("Failed to remove original file: {}", e));
+        }
+
+        log::info!("Quarantined {} as {}", stored.item.original_path, id);
+        Ok(stored.item)
+    }
+
+    pub fn list(&self) -> Result<Vec<QuarantineItem>, String> {
+        let entries = fs::read_dir(&self.vault_dir)
+            .map_err(|e| format!("Failed to read quarantine directory: {}", e))?;
+
+        let mut items: Vec<QuarantineItem> = entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let content = fs::read_to_string(&path).ok()?;
+                serde_json::from_str::<StoredItem>(&content)
+                    .map(|stored| stored.item)
+                    .map_err(|e| log::warn!("Failed to parse quarantine metadata {}: {}", path.display(), e))
+                    .ok()
+            })
+            .collect();
+
+        items.sort_by_key(|item| std::cmp::Reverse(item.quarantined_at));
+        Ok(items)
+    }
+
+    pub fn get(&self, id: &str) -> Result<QuarantineItem, String> {
+        self.load(&parse_id(id)?).map(|stored| stored.item)
+    }
+
+    fn load(&self, id: &uuid::Uuid) -> Result<StoredItem, String> {
+        let content = fs::read_to_string(self.metadata_path(id))
+            .map_err(|_| format!("No quarantined file with id {}", id))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse quarantine metadata: {}", e))
+    }
+
+    // Decode an item back to its original path, or to `destination` when given.
+    // Existing files are never overwritten.
+    pub fn restore(&self, id: &str, destination: Option<&Path>) -> Result<PathBuf, String> {
+        let item = self.get(id).ok();
+        let result = self.restore_from_vault(id, destination);
+        let details = match &result {
+            Ok(path) => json!({ "restored_to": path }),
+            Err(e) => json!({ "error": e }),
+        };
+        audit::record("restore", id, result.is_ok(), details);
+        if let (Ok(_), Some(item)) = (&result, &item) {
+            siem::quarantine_action("restore", item);
+        }
+        result
+    }
+
+    fn restore_from_vault(&self, id: &str, destination: Option<&Path>) -> Result<PathBuf, String> {
+        let id = parse_id(id)?;
+        let StoredItem { item, key } = self.load(&id)?;
+        let target = destination.map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from(&item.original_path));
+
+        if target.exists() {
+            return Err(format!("A file already exists at {}", target.display()));
+        }
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create restore directory: {}", e))?;
+        }
+
+        let key = base64::engine::general_purpose::STANDARD.decode(&key)
+            .map_err(|e| format!("Invalid quarantine key: {}", e))?;
+
+        let restored = File::options().write(true).create_new(true).open(&target)
+            .map_err(|e| format!("Failed to create restored file: {}", e))?;
+        let mut writer = BufWriter::new(restored);
+        let sha256 = decode_to(&self.payload_path(&id), &key, &mut writer)
+            .and_then(|hash| writer.flush().map(|_| hash).map_err(|e| format!("Failed to write restored file: {}", e)));
+
+        match sha256 {
+            Ok(hash) if hash == item.hashes.sha256 => {}
+            Ok(_) => {
+                let _ = fs::remove_file(&target);
+                return Err("Restored file does not match the quarantined hash".to_string());
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&target);
+                return Err(e);
+            }
+        }
+
+        if let Some(mode) = item.original_mode {
+            set_mode(&target, mode)?;
+        }
+
+        self.remove_item(&id);
+        log::info!("Restored quarantined file {} to {}", id, target.display());
+        Ok(target)
+    }
+
+    // Write the original content of an item to `writer`, failing if it no longer matches
+    // the recorded hash
+    pub fn decode_into(&self, id: &str, writer: &mut impl Write) -> Result<QuarantineItem, String> {
+        let id = parse_id(id)?;
+        let StoredItem { item, key } = self.load(&id)?;
+        let key = base64::engine::general_purpose::STANDARD.decode(&key)
+            .map_err(|e| format!("Invalid quarantine key: {}", e))?;
+
+        let sha256 = decode_to(&self.payload_path(&id), &key, writer)?;
+        if sha256 != item.hashes.sha256 {
+            return Err(format!("Quarantined file {} does not match its recorded hash", id));
+        }
+        Ok(item)
+    }
+
+    // Permanently delete an item from the vault
+    pub fn purge(&self, id: &str) -> Result<(), String> {
+        let id = parse_id(id)?;
+        let item = self.load(&id)?.item;
+        self.remove_item(&id);
+        audit::record("quarantine_purge", &id.to_string(), true, json!({ "sha256": item.hashes.sha256, "original_path": item.original_path }));
+        siem::quarantine_action("quarantine_purge", &item);
+        log::info!("Purged quarantined file {}", id);
+        Ok(())
+    }
+
+    // Payloads without metadata and metadata without payloads, left behind by an interrupted
+    // quarantine. Only UUID-named files older than `min_age_secs` count, so neither a quarantine
+    // in progress nor anything else someone keeps in the directory is touched.
+    pub fn orphaned_files(&self, min_age_secs: u64) -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(&self.vault_dir) else {
+            return Vec::new();
+        };
+
+        entries.flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let Some(id) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| parse_id(stem).ok()) else {
+                    return false;
+                };
+                let unpaired = match path.extension().and_then(|ext| ext.to_str()) {
+                    Some("bin") => !self.metadata_path(&id).exists(),
+                    Some("json") => !self.payload_path(&id).exists(),
+                    _ => false,
+                };
+                unpaired && is_older_than(path, min_age_secs)
+            })
+            .collect()
+    }
+
+    fn remove_item(&self, id: &uuid::Uuid) {
+        for path in [self.payload_path(id), self.metadata_path(id)] {
+            if let Err(e) = remove_readonly(&path) {
+                log::error!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    fn write_metadata(&self, id: &uuid::Uuid, stored: &StoredItem) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(stored)
+            .map_err(|e| format!("Failed to serialize quarantine metadata: {}", e))?;
+        let path = self.metadata_path(id);
+        fs::write(&path, content)
+            .map_err(|e| format!("Failed to write quarantine metadata: {}", e))?;
+        set_mode(&path, 0o600)
+    }
+}
+
+// XOR the file into the vault while hashing the plain content
+fn encode_file(source: &Path, destination: &Path, key: &[u8]) -> Result<FileHashes, String> {
+    let mut reader = BufReader::new(File::open(source)
+        .map_err(|e| format!("Failed to open file: {}", e))?);
+    let output = File::options().write(true).create_new(true).open(destination)
+        .map_err(|e| format!("Failed to create quarantine payload: {}", e))?;
+    let mut writer = BufWriter::new(output);
+
+    writer.write_all(PAYLOAD_MAGIC)
+        .map_err(|e| format!("Failed to write quarantine payload: {}", e))?;
+
+    let mut sha256 = sha2::Sha256::new();
+    let mut sha1 = sha1::Sha1::new();
+    let mut md5 = md5::Md5::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut offset = 0usize;
+
+    loop {
+        let read = reader.read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+
+        let chunk = &mut buffer[..read];
+        sha256.update(&*chunk);
+        sha1.update(&*chunk);
+        md5.update(&*chunk);
+        xor_in_place(chunk, key, offset);
+        offset += read;
+
+        writer.write_all(chunk)
+            .map_err(|e| format!("Failed to write quarantine payload: {}", e))?;
+    }
+
+    writer.flush()
+        .map_err(|e| format!("Failed to write quarantine payload: {}", e))?;
+
+    Ok(FileHashes {
+        sha256: format!("{:x}", sha256.finalize()),
+        sha1: format!("{:x}", sha1.finalize()),
+        md5: format!("{:x}", md5.finalize()),
+    })
+}
+
+// SHA-256, SHA-1 and MD5 of a file in one pass
+pub fn hash_file(path: &Path) -> Result<FileHashes, String> {
+    let mut reader = BufReader::new(File::open(path)
+        .map_err(|e| format!("Failed to open file: {}", e))?);
+
+    let mut sha256 = sha2::Sha256::new();
+    let mut sha1 = sha1::Sha1::new();
+    let mut md5 = md5::Md5::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+
+    loop {
+        let read = reader.read(&mut buffer)
+            .map_err(|e| format!("Failed to read file: {}", e))?;
+        if read == 0 {
+            break;
+        }
+        sha256.update(&buffer[..read]);
+        sha1.update(&buffer[..read]);
+        md5.update(&buffer[..read]);
+    }
+
+    Ok(FileHashes {
+        sha256: format!("{:x}", sha256.finalize()),
+        sha1: format!("{:x}", sha1.finalize()),
+        md5: format!("{:x}", md5.finalize()),
+    })
+}
+
+// Decode a payload into `writer` and return the SHA-256 of the decoded content
+fn decode_to(payload: &Path, key: &[u8], writer: &mut impl Write) -> Result<String, String> {
+    let mut reader = BufReader::new(File::open(payload)
+        .map_err(|e| format!("Failed to open quarantine payload: {}", e))?);
+
+    let mut magic = [0u8; 8];
+    reader.read_exact(&mut magic)
+        .map_err(|e| format!("Failed to read quarantine payload: {}", e))?;
+    if &magic != PAYLOAD_MAGIC {
+        return Err("Not a quarantine payload".to_string());
+    }
+
+    let mut sha256 = sha2::Sha256::new();
+    let mut buffer = vec![0u8; BUFFER_SIZE];
+    let mut offset = 0usize;
+
+    loop {
+        let read = reader.read(&mut buffer)
+            .map_err(|e| format!("Failed to read quarantine payload: {}", e))?;
+        if read == 0 {
+            break;
+        }
+
+        let chunk = &mut buffer[..read];
+        xor_in_place(chunk, key, offset);
+        offset += read;
+        sha256.update(&*chunk);
+
+        writer.write_all(chunk)
+            .map_err(|e| format!("Failed to write restored file: {}", e))?;
+    }
+
+    Ok(format!("{:x}", sha256.finalize()))
+}
+
+fn xor_in_place(chunk: &mut [u8], key: &[u8], offset: usize) {
+    for (i, byte) in chunk.iter_mut().enumerate() {
+        *byte ^= key[(offset + i) % key.len()];
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(metadata.permissions().mode() & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_metadata: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> Result<(), String> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode))
+        .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))
+}
+
+#[cfg(not(unix))]
+fn set_mode(path: &Path, mode: u32) -> Result<(), String> {
+    let mut permissions = fs::metadata(path)
+        .map_err(|e| format!("Failed to read permissions on {}: {}", path.display(), e))?
+        .permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    fs::set_permissions(path, permissions)
+        .map_err(|e| format!("Failed to set permissions on {}: {}", path.display(), e))
+}
+
+// Items quarantined before the vault had its own subdirectory sit directly in the configured
+// location. Only UUID-named payloads and metadata are moved.
+fn move_legacy_items(location: &Path, vault_dir: &Path) {
+    let Ok(entries) = fs::read_dir(location) else {
+        return;
+    };
+    for path in entries.flatten().map(|entry| entry.path()) {
+        let is_item = path.file_stem().and_then(|stem| stem.to_str()).is_some_and(|stem| parse_id(stem).is_ok())
+            && matches!(path.extension().and_then(|ext| ext.to_str()), Some("bin" | "json"));
+        if !is_item {
+            continue;
+        }
+        let Some(name) = path.file_name() else { continue };
+        if let Err(e) = fs::rename(&path, vault_dir.join(name)) {
+            log::error!("Failed to move quarantined file {} into the vault: {}", path.display(), e);
+        }
+    }
+}
+
+// Whether a file was last modified at least `secs` ago
+pub(crate) fn is_older_than(path: &Path, secs: u64) -> bool {
+    fs::metadata(path).ok()
+        .and_then(|metadata| metadata.modified().ok())
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age.as_secs() >= secs)
+}
+
+// Read-only payloads cannot be removed on Windows until they are writable again
+pub(crate) fn remove_readonly(path: &Path) -> std::io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+    let mut permissions = fs::metadata(path)?.permissions();
+    if permissions.readonly() {
+        #[allow(clippy::permissions_set_readonly_false)]
+        permissions.set_readonly(false);
+        fs::set_permissions(path, permissions)?;
+    }
+    fs::remove_file(path)
+}
+
+// Most recent scan of a path, used to record the verdict alongside a quarantined file
+pub async fn latest_scan(state: &AppState, path: &str) -> Option<ScanResult> {
+    state.scan_history.lock().await.iter()
+        .rev()
+        .find(|result| result.file_path == path)
+        .cloned()
+}
+
+// An automatic quarantine, kept so it can be reviewed and undone within the window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoQuarantineAction {
+    pub item_id: String,
+    pub file_path: String,
+    pub verdict: ScanStatus,
+    pub detection_count: Option<u32>,
+    pub quarantined_at: chrono::DateTime<chrono::Utc>,
+    pub undo_until: chrono::DateTime<chrono::Utc>,
+    pub undone: bool,
+}
+
+static ACTION_LOG: once_cell::sync::Lazy<std::sync::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(()));
+
+fn action_log_path() -> PathBuf {
+    settings::data_dir().join("auto_quarantine_log.json")
+}
+
+pub fn load_actions() -> Vec<AutoQuarantineAction> {
+    fs::read_to_string(action_log_path()).ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_actions(actions: &[AutoQuarantineAction]) -> Result<(), String> {
+    fs::create_dir_all(settings::data_dir())
+        .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    let content = serde_json::to_string_pretty(actions)
+        .map_err(|e| format!("Failed to serialize quarantine log: {}", e))?;
+    fs::write(action_log_path(), content)
+        .map_err(|e| format!("Failed to write quarantine log: {}", e))
+}
+
+// Read-modify-write the action log under a lock so concurrent scans never drop entries
+fn update_actions<T>(update: impl FnOnce(&mut Vec<AutoQuarantineAction>) -> T) -> Result<T, String> {
+    let _guard = ACTION_LOG.lock().unwrap();
+    let mut actions = load_actions();
+    let output = update(&mut actions);
+    if actions.len() > ACTION_LOG_LIMIT {
+        let excess = actions.len() - ACTION_LOG_LIMIT;
+        actions.drain(..excess);
+    }
+    save_actions(&actions)?;
+    Ok(output)
+}
+
+// Whether a finished scan falls under the auto-quarantine policy
+pub fn should_auto_quarantine(settings: &Settings, result: &ScanResult) -> bool {
+    if !settings.auto_quarantine_malicious {
+        return false;
+    }
+    match result.status {
+        ScanStatus::Malicious => true,
+        ScanStatus::Suspicious => settings.quarantine_settings.suspicious_threshold
+            .is_some_and(|threshold| result.detection_count.unwrap_or(0) >= threshold),
+        _ => false,
+    }
+}
+
+// Apply the auto-quarantine policy to a finished scan. Returns the action taken, if any.
+pub async fn enforce_policy(host: &Host, result: &ScanResult) -> Option<AutoQuarantineAction> {
+    let settings = Settings::cached();
+    if !should_auto_quarantine(&settings, result) || !Path::new(&result.file_path).is_file() {
+        return None;
+    }
+
+    let quarantine_settings = &settings.quarantine_settings;
+    let item = match Quarantine::open(quarantine_settings)
+        .and_then(|vault| vault.quarantine_scanned(result, quarantine_settings.verify_before_quarantine))
+    {
+        Ok(item) => item,
+        Err(e) => {
+            let mut context = HashMap::new();
+            context.insert("file_path".to_string(), result.file_path.clone());
+            host.state().error_handler.lock().await.report_error(
+                Subsystem::Quarantine,
+                ErrorCategory::FileSystemError,
+                "auto_quarantine",
+                &e,
+                context,
+            );
+            return None;
+        }
+    };
+    host.state().error_handler.lock().await.report_success(Subsystem::Quarantine, "auto_quarantine");
+
+    let action = AutoQuarantineAction {
+        item_id: item.id,
+        file_path: result.file_path.clone(),
+        verdict: result.status.clone(),
+        detection_count: result.detection_count,
+        quarantined_at: item.quarantined_at,
+        undo_until: item.quarantined_at + chrono::Duration::seconds(quarantine_settings.undo_window_secs as i64),
+        undone: false,
+    };
+    if let Err(e) = update_actions(|actions| actions.push(action.clone())) {
+        log::error!("Failed to log auto-quarantine: {}", e);
+    }
+    log::info!("Auto-quarantined {} ({:?})", action.file_path, action.verdict);
+
+    if let Err(e) = host.emit("auto-quarantine", &action) {
+        log::error!("Failed to emit auto-quarantine event: {}", e);
+    }
+
+    if quarantine_settings.notify_on_quarantine {
+        notifications::notify(
+            host,
+            "File quarantined",
+            &format!(
+                "{} was {:?} and has been moved to quarantine. You can undo this for {}.",
+                item.file_name,
+                action.verdict,
+                describe_window(quarantine_settings.undo_window_secs),
+            ),
+            Priority::Normal,
+        );
+    }
+
+    Some(action)
+}
+
+// "5 minutes", "90 seconds", "1 hour"
+fn describe_window(secs: u64) -> String {
+    let (value, unit) = if secs >= 3600 && secs.is_multiple_of(3600) {
+        (secs / 3600, "hour")
+    } else if secs >= 60 && secs.is_multiple_of(60) {
+        (secs / 60, "minute")
+    } else {
+        (secs, "second")
+    };
+    format!("{} {}{}", value, unit, if value == 1 { "" } else { "s" })
+}
+
+// Tauri commands for the quarantine vault
+#[tauri::command]
+pub async fn quarantine_file(
+    file_path: String,
+    state: tauri::State<'_, AppState>,
+) -> Result<QuarantineItem, String> {
+    let settings = Settings::load()?.quarantine_settings;
+    let scan = latest_scan(&state, &file_path).await;
+    let result = Quarantine::open(&settings)
+        .and_then(|vault| vault.quarantine(Path::new(&file_path), scan.as_ref(), settings.verify_before_quarantine));
+
+    let mut error_handler = state.error_handler.lock().await;
+    match &result {
+        Ok(_) => error_handler.report_success(Subsystem::Quarantine, "quarantine_file"),
+        Err(e) => {
+            error_handler.report(Subsystem::Quarantine, ErrorCategory::FileSystemError, "quarantine_file", e);
+        }
+    }
+    result
+}
+
+#[tauri::command]
+pub async fn list_quarantine() -> Result<Vec<QuarantineItem>, String> {
+    Quarantine::open(&Settings::load()?.quarantine_settings)?.list()
+}
+
+#[tauri::command]
+pub async fn restore_file(id: String, destination: Option<String>) -> Result<String, String> {
+    let quarantine = Quarantine::open(&Settings::load()?.quarantine_settings)?;
+    let restored = quarantine.restore(&id, destination.as_deref().map(Path::new))?;
+    Ok(restored.to_string_lossy().to_string())
+}
+
+// Purge the given items, or everything in the vault when no ids are passed
+#[tauri::command]
+pub async fn purge_quarantine(ids: Option<Vec<String>>) -> Result<usize, String> {
+    let quarantine = Quarantine::open(&Settings::load()?.quarantine_settings)?;
+    let ids = match ids {
+        Some(ids) => ids,
+        None => quarantine.list()?.into_iter().map(|item| item.id).collect(),
+    };
+
+    for id in &ids {
+        quarantine.purge(id)?;
+    }
+    Ok(ids.len())
+}
+
+#[tauri::command]
+pub async fn get_auto_quarantine_log() -> Result<Vec<AutoQuarantineAction>, String> {
+    Ok(load_actions())
+}
+
+// Restore an automatically quarantined file while its undo window is still open
+#[tauri::command]
+pub async fn undo_auto_quarantine(item_id: String) -> Result<String, String> {
+    let action = load_actions().into_iter()
+        .find(|action| action.item_id == item_id)
+        .ok_or_else(|| format!("No automatic quarantine with id {}", item_id))?;
+    if action.undone {
+        return Err("This quarantine has already been undone".to_string());
+    }
+    if chrono::Utc::now() > action.undo_until {
+        return Err("The undo window for this quarantine has passed".to_string());
+    }
+
+    let quarantine = Quarantine::open(&Settings::load()?.quarantine_settings)?;
+    let restored = quarantine.restore(&item_id, None)?;
+
+    update_actions(|actions| {
+        if let Some(action) = actions.iter_mut().find(|action| action.item_id == item_id) {
+            action.undone = true;
+        }
+    })?;
+    log::info!("Undid auto-quarantine of {}", action.file_path);
+
+    Ok(restored.to_string_lossy().to_string())
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::path::Path;
+use serde::Serialize;
+use tauri::AppHandle;
+use crate::host::Host;
+use crate::notifications::{self, Priority};
+use crate::quarantine;
+use crate::virus_total::{self, ScanResult, ScanStatus, VirusTotal};
+use crate::webhooks;
+use crate::Subsystem;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerdictChange {
+    pub file_path: String,
+    pub file_hash: String,
+    pub previous_status: ScanStatus,
+    pub status: ScanStatus,
+    pub previous_detections: u32,
+    pub detections: u32,
+    pub previous_scan_date: chrono::DateTime<chrono::Utc>,
+    pub result: ScanResult,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RelookupSummary {
+    pub checked: usize,
+    pub changed: usize,
+    pub failed: usize,
+}
+
+// Look up again every file whose latest scan was clean within the last `window_days`
+// days and that is still on disk unchanged. Files now flagged are recorded in the
+// history and reported through a "verdict-changed" event and a notification.
+pub async fn recheck_clean_hashes(host: &Host, window_days: u32) -> Result<RelookupSummary, String> {
+    let state = host.state();
+    let api_key = state.api_key.lock().await.clone()
+        .ok_or_else(|| "API key not initialized".to_string())?;
+    let vt = VirusTotal::new(api_key);
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(window_days as i64);
+    let candidates = recent_clean_results(&state.scan_history.lock().await, cutoff);
+    log::info!("Re-checking {} clean hashes", candidates.len());
+
+    let mut summary = RelookupSummary::default();
+    for previous in candidates {
+        let path = Path::new(&previous.file_path);
+
+        // A file edited since its scan has a different hash and needs a real rescan instead
+        match virus_total::calculate_file_hash(path).await {
+            Ok(hash) if hash == previous.file_hash => {}
+            _ => continue,
+        }
+
+        summary.checked += 1;
+        let result = match vt.lookup_hash(path, &previous.file_hash).await {
+            Ok(Some(result)) => result,
+            Ok(None) => continue,
+            Err(e) => {
+                let mut context = HashMap::new();
+                context.insert("file_path".to_string(), previous.file_path.clone());
+                state.error_handler.lock().await.report_error(
+                    Subsystem::Api,
+                    e.category,
+                    "relookup",
+                    &e.message,
+                    context,
+                );
+                summary.failed += 1;
+                continue;
+            }
+        };
+        state.error_handler.lock().await.report_success(Subsystem::Api, "relookup");
+
+        if !matches!(result.status, ScanStatus::Suspicious | ScanStatus::Malicious) {
+            continue;
+        }
+
+        summary.changed += 1;
+        let change = VerdictChange {
+            file_path: previous.file_path.clone(),
+            file_hash: previous.file_hash.clone(),
+            previous_status: previous.status.clone(),
+            status: result.status.clone(),
+            previous_detections: previous.detection_count.unwrap_or(0),
+            detections: result.detection_count.unwrap_or(0),
+            previous_scan_date: previous.scan_date,
+            result: result.clone(),
+        };
+        virus_total::record_result(state, result).await;
+        report_change(host, &change);
+        quarantine::enforce_policy(host, &change.result).await;
+        webhooks::detection(host, &change.result);
+    }
+
+    Ok(summary)
+}
+
+// Latest result per file, kept only when that result was clean and recent
+fn recent_clean_results(history: &[ScanResult], cutoff: chrono::DateTime<chrono::Utc>) -> Vec<ScanResult> {
+    let mut latest: HashMap<&str, &ScanResult> = HashMap::new();
+    for result in history {
+        let entry = latest.entry(result.file_path.as_str()).or_insert(result);
+        if result.scan_date >= entry.scan_date {
+            *entry = result;
+        }
+    }
+
+    latest.into_values()
+        .filter(|result| result.status == ScanStatus::Clean && result.scan_date >= cutoff)
+        .filter(|result| Path::new(&result.file_path).is_file())
+        .cloned()
+        .collect()
+}
+
+fn report_change(host: &Host, change: &VerdictChange) {
+    log::info!(
+        "Verdict changed for {}: {:?} -> {:?} ({} -> {} detections)",
+        change.file_path, change.previous_status, change.status, change.previous_detections, change.detections,
+    );
+
+    if let Err(e) = host.emit("verdict-changed", change) {
+        log::error!("Failed to emit verdict-changed event: {}", e);
+    }
+
+    let file_name = Path::new(&change.file_path).file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| change.file_path.clone());
+    let priority = if change.status == ScanStatus::Malicious { Priority::Critical } else { Priority::Normal };
+    notifications::notify(
+        host,
+        "Scan verdict changed",
+        &format!("{} was clean but is now {:?} ({} detections)", file_name, change.status, change.detections),
+        priority,
+    );
+}
+
+// Tauri command to run a re-lookup right away
+#[tauri::command]
+pub async fn recheck_clean_files(window_days: Option<u32>, app_handle: AppHandle) -> Result<RelookupSummary, String> {
+    let window_days = match window_days {
+        Some(days) => days,
+        None => crate::settings::Settings::load()?.relookup.window_days,
+    };
+    recheck_clean_hashes(&Host::App(app_handle), window_days).await
+}
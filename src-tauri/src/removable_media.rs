@@ -0,0 +1,330 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use crate::host::Host;
+use crate::notifications::{self, Priority};
+use crate::settings::Settings;
+
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+const UUID_DIR: &str = "/dev/disk/by-uuid";
+pub const MOUNT_ROOTS: [&str; 3] = ["/media", "/run/media", "/mnt"];
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Volumes waiting for the user to choose Scan or Ignore, by prompt id
+static PROMPTS: once_cell::sync::Lazy<std::sync::Mutex<HashMap<String, MediaPrompt>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum MediaPolicy {
+    Ask,
+    AutoScan,
+    Ignore,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemovableMediaSettings {
+    pub enabled: bool,
+    pub default_policy: MediaPolicy,
+    // Policies remembered per device, keyed by filesystem UUID
+    pub device_policies: HashMap<String, MediaPolicy>,
+}
+
+impl Default for RemovableMediaSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            default_policy: MediaPolicy::Ask,
+            device_policies: HashMap::new(),
+        }
+    }
+}
+
+impl RemovableMediaSettings {
+    pub fn policy_for(&self, device_id: &str) -> MediaPolicy {
+        self.device_policies.get(device_id).copied().unwrap_or(self.default_policy)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RemovableMount {
+    pub mount_point: String,
+    pub device: String,
+    pub fs_type: String,
+    pub uuid: Option<String>,
+}
+
+impl RemovableMount {
+    // UUID when the filesystem has one, otherwise the device node
+    pub fn device_id(&self) -> String {
+        self.uuid.clone().unwrap_or_else(|| self.device.clone())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MediaPrompt {
+    pub prompt_id: String,
+    pub mount: RemovableMount,
+    pub mounted_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptAnswer {
+    Scan,
+    Ignore,
+}
+
+// Parse /proc/self/mountinfo and keep only mounts below the removable media roots
+pub fn parse_mountinfo(content: &str) -> Vec<RemovableMount> {
+    content.lines()
+        .filter_map(|line| {
+            // Fields: id parent major:minor root mount_point options [optional...] - fs_type source super_options
+            let (left, right) = line.split_once(" - ")?;
+            let mount_point = unescape_mount_field(left.split(' ').nth(4)?);
+            let mut right = right.split(' ');
+            let fs_type = right.next()?.to_string();
+            let device = unescape_mount_field(right.next()?);
+
+            if !is_removable_mount_point(Path::new(&mount_point)) {
+                return None;
+            }
+
+            Some(RemovableMount {
+                mount_point,
+                device,
+                fs_type,
+                uuid: None,
+            })
+        })
+        .collect()
+}
+
+fn is_removable_mount_point(path: &Path) -> bool {
+    MOUNT_ROOTS.iter().any(|root| path.starts_with(root) && path != Path::new(root))
+}
+
+// The kernel escapes spaces, tabs, newlines and backslashes as three-digit octal sequences
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let code = (bytes[i + 1] - b'0') * 64 + (bytes[i + 2] - b'0') * 8 + (bytes[i + 3] - b'0');
+            out.push(code);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+// Resolve the filesystem UUID of a device through the /dev/disk/by-uuid symlinks
+fn lookup_uuid(device: &str) -> Option<String> {
+    let device = std::fs::canonicalize(device).ok()?;
+
+    std::fs::read_dir(UUID_DIR).ok()?
+        .flatten()
+        .find(|entry| std::fs::canonicalize(entry.path()).map(|target| target == device).unwrap_or(false))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+}
+
+fn read_removable_mounts() -> Result<Vec<RemovableMount>, String> {
+    let content = std::fs::read_to_string(MOUNTINFO_PATH)
+        .map_err(|e| format!("Failed to read {}: {}", MOUNTINFO_PATH, e))?;
+
+    Ok(parse_mountinfo(&content)
+        .into_iter()
+        .map(|mut mount| {
+            mount.uuid = lookup_uuid(&mount.device);
+            mount
+        })
+        .collect())
+}
+
+// Watch the mount table and react to newly mounted removable volumes
+pub fn start_watcher(host: Host) {
+    if !Path::new(MOUNTINFO_PATH).exists() {
+        log::warn!("Mount table not available, removable media watcher disabled");
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        // Volumes that were already mounted at startup are not treated as new
+        let mut known: HashMap<String, RemovableMount> = read_removable_mounts()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mount| (mount.mount_point.clone(), mount))
+            .collect();
+        let mut last_content = std::fs::read_to_string(MOUNTINFO_PATH).unwrap_or_default();
+
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let content = match std::fs::read_to_string(MOUNTINFO_PATH) {
+                Ok(content) => content,
+                Err(e) => {
+                    log::error!("Failed to read {}: {}", MOUNTINFO_PATH, e);
+                    continue;
+                }
+            };
+            if content == last_content {
+                continue;
+            }
+            last_content = content;
+
+            let current = match read_removable_mounts() {
+                Ok(mounts) => mounts,
+                Err(e) => {
+                    log::error!("{}", e);
+                    continue;
+                }
+            };
+
+            known.retain(|mount_point, mount| {
+                let still_mounted = current.iter().any(|m| &m.mount_point == mount_point);
+                if !still_mounted {
+                    PROMPTS.lock().unwrap().retain(|_, prompt| &prompt.mount.mount_point != mount_point);
+                    host.emit("removable-media-removed", &*mount).ok();
+                }
+                still_mounted
+            });
+
+            for mount in current {
+                if known.contains_key(&mount.mount_point) {
+                    continue;
+                }
+                known.insert(mount.mount_point.clone(), mount.clone());
+                handle_new_mount(&host, mount).await;
+            }
+        }
+    });
+}
+
+async fn handle_new_mount(host: &Host, mount: RemovableMount) {
+    let settings = Settings::load().unwrap_or_default();
+    if !settings.removable_media.enabled {
+        return;
+    }
+
+    let policy = settings.removable_media.policy_for(&mount.device_id());
+    log::info!("Removable media mounted at {} ({:?})", mount.mount_point, policy);
+
+    if let Err(e) = host.emit("removable-media-mounted", serde_json::json!({
+        "mount": mount,
+        "policy": policy,
+    })) {
+        log::error!("Failed to emit removable-media-mounted event: {}", e);
+    }
+
+    match policy {
+        MediaPolicy::AutoScan => {
+            match crate::folder_scan::start_folder_scan(host.clone(), PathBuf::from(&mount.mount_point), true).await {
+                Ok(job_id) => {
+                    host.emit("removable-media-scan-started", serde_json::json!({
+                        "mount": mount,
+                        "job_id": job_id,
+                    })).ok();
+                }
+                Err(e) => log::error!("Failed to start scan of {}: {}", mount.mount_point, e),
+            }
+        }
+        MediaPolicy::Ask => {
+            // Desktop notifications cannot carry buttons, so the app shows the Scan/Ignore prompt
+            let prompt = MediaPrompt {
+                prompt_id: uuid::Uuid::new_v4().to_string(),
+                mount: mount.clone(),
+                mounted_at: chrono::Utc::now(),
+            };
+            PROMPTS.lock().unwrap().insert(prompt.prompt_id.clone(), prompt.clone());
+            if let Err(e) = host.emit("removable-media-prompt", &prompt) {
+                log::error!("Failed to emit removable-media-prompt event: {}", e);
+            }
+            notifications::notify(
+                host,
+                "Removable media detected",
+                &format!("{} was mounted. Choose Scan or Ignore in VirusTotal Scanner.", mount.mount_point),
+                Priority::Normal,
+            );
+        }
+        MediaPolicy::Ignore => {}
+    }
+}
+
+// Tauri commands for removable media
+#[tauri::command]
+pub async fn get_removable_media() -> Result<Vec<RemovableMount>, String> {
+    read_removable_mounts()
+}
+
+#[tauri::command]
+pub async fn scan_removable_media(
+    mount_point: String,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    let mounted = read_removable_mounts()?
+        .into_iter()
+        .any(|mount| mount.mount_point == mount_point);
+    if !mounted {
+        return Err(format!("{} is not a mounted removable volume", mount_point));
+    }
+
+    crate::folder_scan::start_folder_scan(Host::App(app_handle), PathBuf::from(mount_point), true).await
+}
+
+#[tauri::command]
+pub async fn set_removable_media_policy(
+    device_id: String,
+    policy: MediaPolicy,
+) -> Result<(), String> {
+    let mut settings = Settings::load()?;
+    settings.removable_media.device_policies.insert(device_id, policy);
+    settings.save_validated().map_err(|e| e.to_string())
+}
+
+// Prompts still unanswered, for a window opened after the event was sent
+#[tauri::command]
+pub async fn get_removable_media_prompts() -> Result<Vec<MediaPrompt>, String> {
+    let mut prompts: Vec<MediaPrompt> = PROMPTS.lock().unwrap().values().cloned().collect();
+    prompts.sort_by_key(|prompt| prompt.mounted_at);
+    Ok(prompts)
+}
+
+// Scan or ignore a volume the user was asked about, optionally remembering the choice
+// for the device. Returns the folder scan's job id when a scan was started.
+#[tauri::command]
+pub async fn answer_removable_media_prompt(
+    prompt_id: String,
+    answer: PromptAnswer,
+    remember: bool,
+    app_handle: AppHandle,
+) -> Result<Option<String>, String> {
+    let prompt = PROMPTS.lock().unwrap().remove(&prompt_id)
+        .ok_or_else(|| "The volume is no longer waiting for an answer".to_string())?;
+
+    if remember {
+        let policy = match answer {
+            PromptAnswer::Scan => MediaPolicy::AutoScan,
+            PromptAnswer::Ignore => MediaPolicy::Ignore,
+        };
+        set_removable_media_policy(prompt.mount.device_id(), policy).await?;
+    }
+
+    match answer {
+        PromptAnswer::Scan => {
+            let job_id = crate::folder_scan::start_folder_scan(
+                Host::App(app_handle),
+                PathBuf::from(&prompt.mount.mount_point),
+                true,
+            ).await?;
+            Ok(Some(job_id))
+        }
+        PromptAnswer::Ignore => Ok(None),
+    }
+}
@@ -0,0 +1,600 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use hyper::body::HttpBody;
+use hyper::server::conn::Http;
+use hyper::service::service_fn;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::async_runtime::JoinHandle;
+use tauri::AppHandle;
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
+use crate::host::Host;
+use crate::settings::{self, FieldError, Settings};
+use crate::virus_total::{self, ScanResult};
+use crate::AppState;
+
+const UNIX_PREFIX: &str = "unix:";
+const MIN_TOKEN_LEN: usize = 32;
+// Finished jobs kept for GET /scan/{id}, oldest dropped first
+const JOB_LIMIT: usize = 500;
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+static JOBS: once_cell::sync::Lazy<std::sync::Mutex<Vec<ScanJob>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(Vec::new()));
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RestApiConfig {
+    pub enabled: bool,
+    // A loopback address such as 127.0.0.1:8745, or unix:/path/to/socket
+    pub listen: String,
+    pub token: Option<String>,
+    pub max_upload_bytes: u64,
+}
+
+impl Default for RestApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: "127.0.0.1:8745".to_string(),
+            token: None,
+            max_upload_bytes: 32 * 1024 * 1024, // Largest file POST /scan accepts as an upload
+        }
+    }
+}
+
+pub(crate) enum Listen {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl RestApiConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if let Err(e) = parse_listen(&self.listen) {
+            errors.push(FieldError::new("rest_api.listen", e));
+        }
+        if self.enabled && self.token.as_ref().is_none_or(|token| token.len() < MIN_TOKEN_LEN) {
+            errors.push(FieldError::new("rest_api.token", format!("A token of at least {} characters is required", MIN_TOKEN_LEN)));
+        }
+        if self.max_upload_bytes == 0 {
+            errors.push(FieldError::new("rest_api.max_upload_bytes", "Uploads must be allowed at least one byte"));
+        }
+    }
+}
+
+// A loopback address such as 127.0.0.1:8745, or unix:/path/to/socket
+pub(crate) fn parse_listen(listen: &str) -> Result<Listen, String> {
+    if let Some(path) = listen.strip_prefix(UNIX_PREFIX) {
+        #[cfg(unix)]
+        {
+            return if Path::new(path).is_absolute() {
+                Ok(Listen::Unix(PathBuf::from(path)))
+            } else {
+                Err(format!("Socket path must be absolute: {}", path))
+            };
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = path;
+            return Err("Unix sockets are not supported on this platform".to_string());
+        }
+    }
+
+    let address: SocketAddr = listen.parse()
+        .map_err(|_| format!("Invalid listen address: {}", listen))?;
+    // Other machines must never reach the API key through these servers
+    if !address.ip().is_loopback() {
+        return Err(format!("Only loopback addresses may be used: {}", listen));
+    }
+    Ok(Listen::Tcp(address))
+}
+
+// The task serving one configuration. It is stopped and awaited before a new
+// configuration binds, so the old listener is closed by then.
+pub(crate) struct RunningServer {
+    shutdown: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl RunningServer {
+    pub(crate) fn spawn<F>(shutdown: CancellationToken, serve: F) -> Self
+    where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            shutdown,
+            task: tauri::async_runtime::spawn(serve),
+        }
+    }
+
+    pub(crate) async fn stop(self) {
+        self.shutdown.cancel();
+        if let Err(e) = self.task.await {
+            log::warn!("Server task ended abnormally: {}", e);
+        }
+    }
+}
+
+// The socket file a server bound, identified by its inode
+#[cfg(unix)]
+pub(crate) struct SocketFile {
+    path: PathBuf,
+    dev: u64,
+    ino: u64,
+}
+
+#[cfg(unix)]
+impl SocketFile {
+    // Unlink the socket unless something else has been bound at the path since
+    pub(crate) fn remove(&self) {
+        use std::os::unix::fs::MetadataExt;
+
+        match std::fs::symlink_metadata(&self.path) {
+            Ok(metadata) if metadata.dev() == self.dev && metadata.ino() == self.ino => {
+                if let Err(e) = std::fs::remove_file(&self.path) {
+                    log::warn!("Failed to remove socket {}: {}", self.path.display(), e);
+                }
+            }
+            _ => log::debug!("Socket {} was replaced, leaving it in place", self.path.display()),
+        }
+    }
+}
+
+// Replace a socket left by a previous run and make it private to this user. A socket
+// another server still answers on, or a file that is not a socket, is left alone.
+#[cfg(unix)]
+pub(crate) fn bind_unix(path: &Path) -> Result<(tokio::net::UnixListener, SocketFile), String> {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+
+    if let Ok(metadata) = std::fs::symlink_metadata(path) {
+        if !metadata.file_type().is_socket() {
+            return Err(format!("Refusing to replace {}: not a socket", path.display()));
+        }
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            return Err(format!("Another server is already listening on {}", path.display()));
+        }
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove stale socket {}: {}", path.display(), e))?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .map_err(|e| format!("Failed to listen on {}: {}", path.display(), e))?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))
+        .map_err(|e| format!("Failed to restrict socket permissions: {}", e))?;
+    let metadata = std::fs::symlink_metadata(path)
+        .map_err(|e| format!("Failed to read socket metadata: {}", e))?;
+
+    let socket = SocketFile {
+        path: path.to_path_buf(),
+        dev: metadata.dev(),
+        ino: metadata.ino(),
+    };
+    Ok((listener, socket))
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanJob {
+    pub id: String,
+    pub status: JobStatus,
+    pub file_path: String,
+    pub submitted_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub result: Option<ScanResult>,
+    pub error: Option<String>,
+    // Uploaded files live in a per-job directory that is removed after the scan
+    #[serde(skip)]
+    upload_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct ScanRequest {
+    path: String,
+}
+
+struct Context {
+    host: Host,
+    token: String,
+    max_upload_bytes: u64,
+}
+
+// Runs the HTTP server while it is enabled in the settings
+#[derive(Clone, Default)]
+pub struct RestApi {
+    server: Arc<tokio::sync::Mutex<Option<RunningServer>>>,
+}
+
+impl RestApi {
+    // Stop any running server and start a new one for the given config
+    pub async fn apply(&self, host: &Host, config: &RestApiConfig) {
+        let mut server = self.server.lock().await;
+        if let Some(previous) = server.take() {
+            previous.stop().await;
+        }
+        if !config.enabled {
+            return;
+        }
+
+        let listen = match parse_listen(&config.listen) {
+            Ok(listen) => listen,
+            Err(e) => {
+                log::error!("Not starting REST API: {}", e);
+                return;
+            }
+        };
+        let Some(token) = config.token.clone() else {
+            log::error!("Not starting REST API: no token configured");
+            return;
+        };
+
+        let shutdown = CancellationToken::new();
+        let context = Arc::new(Context {
+            host: host.clone(),
+            token,
+            max_upload_bytes: config.max_upload_bytes,
+        });
+
+        *server = Some(RunningServer::spawn(shutdown.clone(), async move {
+            if let Err(e) = serve(listen, context, shutdown).await {
+                log::error!("REST API stopped: {}", e);
+            }
+        }));
+    }
+}
+
+async fn serve(listen: Listen, context: Arc<Context>, shutdown: CancellationToken) -> Result<(), String> {
+    match listen {
+        Listen::Tcp(address) => {
+            let listener = tokio::net::TcpListener::bind(address).await
+                .map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+            log::info!("REST API listening on {}", address);
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => serve_connection(stream, context.clone()),
+                        Err(e) => log::warn!("Failed to accept REST API connection: {}", e),
+                    },
+                }
+            }
+        }
+        #[cfg(unix)]
+        Listen::Unix(path) => {
+            let (listener, socket) = bind_unix(&path)?;
+            log::info!("REST API listening on {}", path.display());
+
+            loop {
+                tokio::select! {
+                    _ = shutdown.cancelled() => break,
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => serve_connection(stream, context.clone()),
+                        Err(e) => log::warn!("Failed to accept REST API connection: {}", e),
+                    },
+                }
+            }
+            socket.remove();
+        }
+    }
+
+    log::info!("REST API stopped");
+    Ok(())
+}
+
+fn serve_connection<S>(stream: S, context: Arc<Context>)
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    tauri::async_runtime::spawn(async move {
+        let service = service_fn(move |request| {
+            let context = context.clone();
+            async move { Ok::<_, Infallible>(handle(&context, request).await) }
+        });
+        if let Err(e) = Http::new().http1_only(true).serve_connection(stream, service).await {
+            log::debug!("REST API connection closed: {}", e);
+        }
+    });
+}
+
+async fn handle(context: &Context, request: Request<Body>) -> Response<Body> {
+    if !authorized(&request, &context.token) {
+        return error(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token");
+    }
+
+    let path = request.uri().path().trim_end_matches('/').to_string();
+    let segments: Vec<&str> = path.split('/').filter(|segment| !segment.is_empty()).collect();
+
+    match (request.method(), segments.as_slice()) {
+        (&Method::POST, ["scan"]) => submit_scan(context, request).await,
+        (&Method::GET, ["scan", id]) => match find_job(id) {
+            Some(job) => respond(StatusCode::OK, &job),
+            None => error(StatusCode::NOT_FOUND, "No scan with that id"),
+        },
+        (&Method::GET, ["history"]) => history(context, &request).await,
+        (&Method::GET, ["hash", sha256]) => lookup_hash(context, sha256).await,
+        (_, ["scan"]) | (_, ["scan", _]) | (_, ["history"]) | (_, ["hash", _]) => {
+            error(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+        }
+        _ => error(StatusCode::NOT_FOUND, "Not found"),
+    }
+}
+
+fn authorized(request: &Request<Body>, token: &str) -> bool {
+    request.headers().get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes()))
+}
+
+// Compare without returning early, so response timing does not reveal the token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+// Start a scan of a local path (JSON body) or of the uploaded request body
+async fn submit_scan(context: &Context, request: Request<Body>) -> Response<Body> {
+    let id = uuid::Uuid::new_v4().to_string();
+    let is_json = request.headers().get(hyper::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    let (file_path, upload_dir) = if is_json {
+        let body = match read_body(request.into_body(), 64 * 1024).await {
+            Ok(body) => body,
+            Err(response) => return response,
+        };
+        let scan_request: ScanRequest = match serde_json::from_slice(&body) {
+            Ok(scan_request) => scan_request,
+            Err(e) => return error(StatusCode::BAD_REQUEST, &format!("Invalid scan request: {}", e)),
+        };
+        if !Path::new(&scan_request.path).is_file() {
+            return error(StatusCode::BAD_REQUEST, &format!("Not a file: {}", scan_request.path));
+        }
+        (scan_request.path, None)
+    } else {
+        match save_upload(context, &id, request).await {
+            Ok((path, dir)) => (path.to_string_lossy().to_string(), Some(dir)),
+            Err(response) => return response,
+        }
+    };
+
+    let job = ScanJob {
+        id: id.clone(),
+        status: JobStatus::Pending,
+        file_path: file_path.clone(),
+        submitted_at: chrono::Utc::now(),
+        finished_at: None,
+        result: None,
+        error: None,
+        upload_dir,
+    };
+    store_job(job.clone());
+
+    let host = context.host.clone();
+    let uploaded = job.upload_dir.is_some();
+    tauri::async_runtime::spawn(async move {
+        update_job(&id, |job| job.status = JobStatus::Running);
+        // An upload is deleted once scanned, so it stays out of the history and quarantine
+        let outcome = if uploaded {
+            virus_total::scan_transient(&file_path, host.state()).await.map_err(String::from)
+        } else {
+            virus_total::scan_and_respond(&host, &file_path).await
+        };
+
+        let mut upload_dir = None;
+        update_job(&id, |job| {
+            job.finished_at = Some(chrono::Utc::now());
+            match outcome {
+                Ok(result) => {
+                    job.status = JobStatus::Completed;
+                    job.result = Some(result);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e);
+                }
+            }
+            upload_dir = job.upload_dir.take();
+        });
+        if let Some(dir) = upload_dir {
+            if let Err(e) = tokio::fs::remove_dir_all(&dir).await {
+                log::warn!("Failed to remove uploaded file {}: {}", dir.display(), e);
+            }
+        }
+    });
+
+    respond(StatusCode::ACCEPTED, &job)
+}
+
+// Write the request body to a private directory, named after the filename query parameter
+async fn save_upload(context: &Context, id: &str, request: Request<Body>) -> Result<(PathBuf, PathBuf), Response<Body>> {
+    let file_name = query_param(&request, "filename")
+        .and_then(|name| Path::new(&name).file_name().map(|name| name.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "upload.bin".to_string());
+
+    let dir = settings::data_dir().join("api_uploads").join(id);
+    tokio::fs::create_dir_all(&dir).await
+        .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to create upload directory: {}", e)))?;
+    let path = dir.join(file_name);
+
+    let result = async {
+        let mut file = tokio::fs::File::create(&path).await
+            .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to create upload: {}", e)))?;
+        let mut body = request.into_body();
+        let mut written = 0u64;
+        while let Some(chunk) = body.data().await {
+            let chunk = chunk.map_err(|e| error(StatusCode::BAD_REQUEST, &format!("Failed to read upload: {}", e)))?;
+            written += chunk.len() as u64;
+            if written > context.max_upload_bytes {
+                return Err(error(StatusCode::PAYLOAD_TOO_LARGE, "Upload is too large"));
+            }
+            file.write_all(&chunk).await
+                .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to write upload: {}", e)))?;
+        }
+        if written == 0 {
+            return Err(error(StatusCode::BAD_REQUEST, "Empty upload"));
+        }
+        file.flush().await
+            .map_err(|e| error(StatusCode::INTERNAL_SERVER_ERROR, &format!("Failed to write upload: {}", e)))
+    }.await;
+
+    match result {
+        Ok(()) => Ok((path, dir)),
+        Err(response) => {
+            let _ = tokio::fs::remove_dir_all(&dir).await;
+            Err(response)
+        }
+    }
+}
+
+async fn read_body(mut body: Body, limit: usize) -> Result<Vec<u8>, Response<Body>> {
+    let mut content = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| error(StatusCode::BAD_REQUEST, &format!("Failed to read request: {}", e)))?;
+        if content.len() + chunk.len() > limit {
+            return Err(error(StatusCode::PAYLOAD_TOO_LARGE, "Request is too large"));
+        }
+        content.extend_from_slice(&chunk);
+    }
+    Ok(content)
+}
+
+// Newest results first
+async fn history(context: &Context, request: &Request<Body>) -> Response<Body> {
+    let limit = query_param(request, "limit")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_LIMIT);
+
+    let state = context.host.state();
+    let mut history = state.scan_history.lock().await.clone();
+    history.sort_by_key(|result| std::cmp::Reverse(result.scan_date));
+    history.truncate(limit);
+    respond(StatusCode::OK, &history)
+}
+
+// Answer from the history and the result cache, without spending API quota
+async fn lookup_hash(context: &Context, sha256: &str) -> Response<Body> {
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return error(StatusCode::BAD_REQUEST, "Expected a SHA-256 hash");
+    }
+    let sha256 = sha256.to_lowercase();
+
+    let state = context.host.state();
+    let known = state.scan_history.lock().await.iter()
+        .filter(|result| result.file_hash == sha256)
+        .max_by_key(|result| result.scan_date)
+        .cloned()
+        .or_else(|| virus_total::cached_result(&sha256));
+
+    match known {
+        Some(result) => respond(StatusCode::OK, &result),
+        None => error(StatusCode::NOT_FOUND, "No scan result for that hash"),
+    }
+}
+
+fn query_param(request: &Request<Body>, name: &str) -> Option<String> {
+    let query = request.uri().query()?;
+    let params: HashMap<&str, &str> = query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .collect();
+    params.get(name).map(|value| percent_decode(value))
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = bytes.get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn store_job(job: ScanJob) {
+    let mut jobs = JOBS.lock().unwrap();
+    jobs.push(job);
+    while jobs.len() > JOB_LIMIT {
+        match jobs.iter().position(|job| job.finished_at.is_some()) {
+            Some(index) => {
+                jobs.remove(index);
+            }
+            None => break,
+        }
+    }
+}
+
+fn update_job(id: &str, update: impl FnOnce(&mut ScanJob)) {
+    if let Some(job) = JOBS.lock().unwrap().iter_mut().find(|job| job.id == id) {
+        update(job);
+    }
+}
+
+fn find_job(id: &str) -> Option<ScanJob> {
+    JOBS.lock().unwrap().iter().find(|job| job.id == id).cloned()
+}
+
+fn respond<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap_or_default()
+}
+
+fn error(status: StatusCode, message: &str) -> Response<Body> {
+    respond(status, &json!({ "error": message }))
+}
+
+// Tauri commands for the REST API
+#[tauri::command]
+pub async fn generate_rest_api_token(
+    state: tauri::State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<String, String> {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let token: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    let previous = Settings::load()?;
+    let mut settings = previous.clone();
+    settings.rest_api.token = Some(token.clone());
+    settings.save_validated().map_err(|e| e.to_string())?;
+    crate::audit::record_settings_change(&previous, &settings);
+
+    state.rest_api.apply(&Host::App(app_handle), &settings.rest_api).await;
+    Ok(token)
+}
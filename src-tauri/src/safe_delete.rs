@@ -0,0 +1,311 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use rand::RngCore;
+use serde::Serialize;
+use serde_json::json;
+use crate::audit;
+use crate::config::DeleteMode;
+use crate::quarantine::Quarantine;
+use crate::removable_media::MOUNT_ROOTS;
+use crate::settings::{self, Settings};
+use crate::virus_total;
+use crate::AppState;
+
+const OVERWRITE_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeleteOutcome {
+    pub path: String,
+    pub mode: DeleteMode,
+    // Where the file ended up when it was moved to the trash
+    pub trash_path: Option<String>,
+}
+
+// Delete a file the app has scanned or quarantined, provided it lies under an allowed
+// root. Every attempt, refused or not, is recorded in the audit trail.
+pub async fn safe_delete(state: &AppState, file_path: &str, mode: DeleteMode) -> Result<DeleteOutcome, String> {
+    let outcome = delete_checked(state, file_path, mode).await;
+
+    let details = match &outcome {
+        Ok(outcome) => json!({ "mode": mode, "trash_path": outcome.trash_path }),
+        Err(e) => json!({ "mode": mode, "error": e }),
+    };
+    audit::record("delete_file", file_path, outcome.is_ok(), details);
+
+    outcome
+}
+
+async fn delete_checked(state: &AppState, file_path: &str, mode: DeleteMode) -> Result<DeleteOutcome, String> {
+    let settings = Settings::load()?;
+    let path = Path::new(file_path);
+
+    let metadata = fs::symlink_metadata(path)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+    if !metadata.is_file() {
+        return Err(format!("Only regular files can be deleted: {}", file_path));
+    }
+
+    let canonical = path.canonicalize()
+        .map_err(|e| format!("Failed to resolve path: {}", e))?;
+    if !is_under_allowed_root(&canonical, &settings) {
+        return Err(format!("Refusing to delete outside the allowed locations: {}", canonical.display()));
+    }
+    if !is_known_file(state, &settings, &canonical).await {
+        return Err(format!("Refusing to delete a file that was never scanned or quarantined: {}", file_path));
+    }
+
+    let trash_path = match mode {
+        DeleteMode::Trash => Some(move_to_trash(&canonical)?),
+        DeleteMode::Permanent => {
+            fs::remove_file(&canonical)
+                .map_err(|e| format!("Failed to delete file: {}", e))?;
+            None
+        }
+        DeleteMode::SecureOverwrite => {
+            overwrite_and_remove(&canonical, settings.delete.overwrite_passes)?;
+            None
+        }
+    };
+
+    log::info!("Deleted {} ({:?})", canonical.display(), mode);
+    Ok(DeleteOutcome {
+        path: canonical.to_string_lossy().to_string(),
+        mode,
+        trash_path: trash_path.map(|path| path.to_string_lossy().to_string()),
+    })
+}
+
+// The user's download, desktop and document folders rather than the whole home directory,
+// so shell profiles, SSH keys and other dotfiles are never candidates
+fn allowed_roots(settings: &Settings) -> Vec<PathBuf> {
+    [dirs::download_dir(), dirs::desktop_dir(), dirs::document_dir()].into_iter()
+        .flatten()
+        .chain(settings.monitor.monitored_paths.iter().cloned())
+        .chain(MOUNT_ROOTS.iter().map(PathBuf::from))
+        .chain(settings.delete.allowed_roots.iter().cloned())
+        .filter_map(|root| root.canonicalize().ok())
+        .collect()
+}
+
+fn is_under_allowed_root(canonical: &Path, settings: &Settings) -> bool {
+    // The app's own state is never a valid target, even though it lives under the home directory
+    let protected = [settings::data_dir(), settings::config_dir()];
+    if protected.iter()
+        .filter_map(|dir| dir.canonicalize().ok())
+        .any(|dir| canonical.starts_with(dir))
+    {
+        return false;
+    }
+
+    allowed_roots(settings).iter()
+        .any(|root| canonical.starts_with(root) && canonical != root)
+}
+
+// Known means the file's current content was scanned or quarantined, so a file swapped in
+// under a scanned path does not qualify
+async fn is_known_file(state: &AppState, settings: &Settings, canonical: &Path) -> bool {
+    let hash = match virus_total::calculate_file_hash(canonical).await {
+        Ok(hash) => hash,
+        Err(e) => {
+            log::warn!("Failed to hash {} before deleting: {}", canonical.display(), e);
+            return false;
+        }
+    };
+    let matches = |recorded: &str| recorded.eq_ignore_ascii_case(&hash);
+
+    if state.scan_history.lock().await.iter().any(|result| matches(&result.file_hash)) {
+        return true;
+    }
+
+    Quarantine::open(&settings.quarantine_settings)
+        .and_then(|vault| vault.list())
+        .map(|items| items.iter().any(|item| matches(&item.hashes.sha256)))
+        .unwrap_or(false)
+}
+
+// Overwrite the file contents with random data before unlinking. This does not reach
+// old copies kept by copy-on-write filesystems or SSD wear levelling.
+fn overwrite_and_remove(path: &Path, passes: u32) -> Result<(), String> {
+    let length = fs::metadata(path)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+    let mut file = OpenOptions::new().write(true).open(path)
+        .map_err(|e| format!("Failed to open file for overwrite: {}", e))?;
+
+    let mut buffer = vec![0u8; OVERWRITE_CHUNK];
+    for _ in 0..passes {
+        overwrite_pass(&mut file, length, &mut buffer)?;
+    }
+    drop(file);
+
+    fs::remove_file(path)
+        .map_err(|e| format!("Failed to delete file: {}", e))
+}
+
+fn overwrite_pass(file: &mut File, length: u64, buffer: &mut [u8]) -> Result<(), String> {
+    use std::io::{Seek, SeekFrom};
+
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to overwrite file: {}", e))?;
+
+    let mut remaining = length;
+    while remaining > 0 {
+        let chunk = remaining.min(buffer.len() as u64) as usize;
+        rand::thread_rng().fill_bytes(&mut buffer[..chunk]);
+        file.write_all(&buffer[..chunk])
+            .map_err(|e| format!("Failed to overwrite file: {}", e))?;
+        remaining -= chunk as u64;
+    }
+
+    file.sync_all()
+        .map_err(|e| format!("Failed to flush overwritten file: {}", e))
+}
+
+// Move a file to the trash as described by the freedesktop.org Trash specification:
+// the home trash for files on the same filesystem, otherwise a per-user trash at the
+// top of the file's mount.
+#[cfg(unix)]
+fn move_to_trash(path: &Path) -> Result<PathBuf, String> {
+    use std::os::unix::fs::MetadataExt;
+
+    let file_dev = fs::metadata(path)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .dev();
+    let data_home = dirs::data_dir().ok_or_else(|| "No data directory for the trash".to_string())?;
+    fs::create_dir_all(&data_home)
+        .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    let home_dev = fs::metadata(&data_home)
+        .map_err(|e| format!("Failed to read data directory: {}", e))?
+        .dev();
+
+    let (trash_dir, top_dir) = if file_dev == home_dev {
+        (data_home.join("Trash"), None)
+    } else {
+        let top_dir = mount_point(path, file_dev);
+        (topdir_trash(&top_dir)?, Some(top_dir))
+    };
+
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    for dir in [&files_dir, &info_dir] {
+        create_private_dir(dir)?;
+    }
+
+    // The spec records paths relative to the mount for top directory trashes
+    let recorded_path = match &top_dir {
+        Some(top_dir) => path.strip_prefix(top_dir).unwrap_or(path),
+        None => path,
+    };
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        percent_encode(recorded_path),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S"),
+    );
+
+    // Claim a name by creating its .trashinfo exclusively, as the spec requires
+    let file_name = path.file_name()
+        .ok_or_else(|| "File has no name".to_string())?
+        .to_string_lossy()
+        .to_string();
+    let (name, info_path) = claim_trash_name(&info_dir, &file_name, &info)?;
+
+    let destination = files_dir.join(&name);
+    if let Err(e) = fs::rename(path, &destination) {
+        let _ = fs::remove_file(&info_path);
+        return Err(format!("Failed to move file to the trash: {}", e));
+    }
+
+    Ok(destination)
+}
+
+#[cfg(not(unix))]
+fn move_to_trash(_path: &Path) -> Result<PathBuf, String> {
+    Err("Moving files to the trash is not supported on this platform".to_string())
+}
+
+#[cfg(unix)]
+fn claim_trash_name(info_dir: &Path, file_name: &str, info: &str) -> Result<(String, PathBuf), String> {
+    for attempt in 1..=1000 {
+        let name = if attempt == 1 {
+            file_name.to_string()
+        } else {
+            format!("{}.{}", file_name, attempt)
+        };
+        let info_path = info_dir.join(format!("{}.trashinfo", name));
+
+        match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(mut file) => {
+                file.write_all(info.as_bytes())
+                    .map_err(|e| format!("Failed to write trash info: {}", e))?;
+                return Ok((name, info_path));
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create trash info: {}", e)),
+        }
+    }
+    Err("Failed to find a free name in the trash".to_string())
+}
+
+// Walk up from the file while the parent is still on the same device
+#[cfg(unix)]
+fn mount_point(path: &Path, dev: u64) -> PathBuf {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut top = path.to_path_buf();
+    for ancestor in path.ancestors().skip(1) {
+        match fs::metadata(ancestor) {
+            Ok(metadata) if metadata.dev() == dev => top = ancestor.to_path_buf(),
+            _ => break,
+        }
+    }
+    top
+}
+
+// $topdir/.Trash/$uid when an administrator set up a valid shared trash, else $topdir/.Trash-$uid
+#[cfg(unix)]
+fn topdir_trash(top_dir: &Path) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    // SAFETY: getuid takes no arguments, cannot fail and touches no memory we own
+    let uid = unsafe { libc::getuid() };
+
+    let shared = top_dir.join(".Trash");
+    if let Ok(metadata) = fs::symlink_metadata(&shared) {
+        let sticky = metadata.permissions().mode() & 0o1000 != 0;
+        if metadata.is_dir() && sticky {
+            let user_trash = shared.join(uid.to_string());
+            if create_private_dir(&user_trash).is_ok() {
+                return Ok(user_trash);
+            }
+        }
+    }
+
+    let user_trash = top_dir.join(format!(".Trash-{}", uid));
+    create_private_dir(&user_trash)?;
+    Ok(user_trash)
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    if dir.is_dir() {
+        return Ok(());
+    }
+    fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)
+        .map_err(|e| format!("Failed to create trash directory {}: {}", dir.display(), e))
+}
+
+// Percent-encode a path for the Path= key, leaving unreserved characters and separators alone
+#[cfg(unix)]
+fn percent_encode(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    path.as_os_str().as_bytes().iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
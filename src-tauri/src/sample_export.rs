@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use serde_json::json;
+use zip::unstable::write::FileOptionsExt;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+use crate::audit;
+use crate::quarantine::{FileHashes, Quarantine, QuarantineItem};
+use crate::settings::Settings;
+use crate::virus_total::{self, ScanEntry, ScanStatus};
+use crate::AppState;
+
+// The conventional password for malware sample archives
+const SAMPLE_PASSWORD: &[u8] = b"infected";
+const MANIFEST_NAME: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleManifest {
+    pub generated_at: chrono::DateTime<chrono::Utc>,
+    pub sample_count: usize,
+    pub samples: Vec<SampleEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleEntry {
+    // Location of the sample inside the archive
+    pub archive_path: String,
+    pub quarantine_id: String,
+    pub file_name: String,
+    pub original_path: String,
+    pub file_size: u64,
+    pub hashes: FileHashes,
+    pub verdict: Option<ScanStatus>,
+    pub detection_count: Option<u32>,
+    pub quarantined_at: chrono::DateTime<chrono::Utc>,
+    pub vendor_results: Option<HashMap<String, ScanEntry>>,
+}
+
+// Package quarantine items into a ZipCrypto archive protected with the password
+// "infected". Samples are stored under their SHA-256 with no extension so they
+// cannot be opened by accident, next to a manifest describing each one.
+pub async fn export_samples(state: &AppState, ids: &[String], destination: &Path) -> Result<SampleManifest, String> {
+    if ids.is_empty() {
+        return Err("No quarantine items selected".to_string());
+    }
+    if destination.exists() {
+        return Err(format!("A file already exists at {}", destination.display()));
+    }
+
+    let vault = Quarantine::open(&Settings::load()?.quarantine_settings)?;
+    let history = state.scan_history.lock().await.clone();
+
+    // Build in a partial file so an interrupted export never looks complete
+    let partial = destination.with_extension("zip.part");
+    let result = write_archive(&vault, ids, &partial, |sha256| {
+        history.iter()
+            .rev()
+            .find(|result| result.file_hash == sha256)
+            .cloned()
+            .or_else(|| virus_total::cached_result(sha256))
+            .and_then(|result| result.vendor_results)
+    })
+    .and_then(|manifest| {
+        fs::rename(&partial, destination)
+            .map_err(|e| format!("Failed to finish sample archive: {}", e))?;
+        Ok(manifest)
+    });
+
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+
+    let details = match &result {
+        Ok(manifest) => json!({
+            "items": ids,
+            "sha256": manifest.samples.iter().map(|sample| &sample.hashes.sha256).collect::<Vec<_>>(),
+        }),
+        Err(e) => json!({ "items": ids, "error": e }),
+    };
+    audit::record("export_samples", &destination.to_string_lossy(), result.is_ok(), details);
+
+    result
+}
+
+fn write_archive(
+    vault: &Quarantine,
+    ids: &[String],
+    path: &Path,
+    vendor_results: impl Fn(&str) -> Option<HashMap<String, ScanEntry>>,
+) -> Result<SampleManifest, String> {
+    let file = File::create(path)
+        .map_err(|e| format!("Failed to create sample archive: {}", e))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let options = FileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .with_deprecated_encryption(SAMPLE_PASSWORD);
+
+    let mut samples = Vec::new();
+    for id in ids {
+        let item = vault.get(id)?;
+        let archive_path = format!("samples/{}", item.hashes.sha256);
+
+        // The same content quarantined twice is only packed once
+        if samples.iter().any(|sample: &SampleEntry| sample.archive_path == archive_path) {
+            continue;
+        }
+
+        zip.start_file(archive_path.as_str(), options)
+            .map_err(|e| format!("Failed to add sample to archive: {}", e))?;
+        vault.decode_into(id, &mut zip)?;
+
+        samples.push(sample_entry(item, archive_path, &vendor_results));
+    }
+
+    let manifest = SampleManifest {
+        generated_at: chrono::Utc::now(),
+        sample_count: samples.len(),
+        samples,
+    };
+    let manifest_json = serde_json::to_vec_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize sample manifest: {}", e))?;
+    zip.start_file(MANIFEST_NAME, options)
+        .map_err(|e| format!("Failed to add manifest to archive: {}", e))?;
+    zip.write_all(&manifest_json)
+        .map_err(|e| format!("Failed to write manifest: {}", e))?;
+
+    let mut writer = zip.finish()
+        .map_err(|e| format!("Failed to finish sample archive: {}", e))?;
+    writer.flush()
+        .map_err(|e| format!("Failed to write sample archive: {}", e))?;
+
+    Ok(manifest)
+}
+
+fn sample_entry(
+    item: QuarantineItem,
+    archive_path: String,
+    vendor_results: &impl Fn(&str) -> Option<HashMap<String, ScanEntry>>,
+) -> SampleEntry {
+    SampleEntry {
+        vendor_results: vendor_results(&item.hashes.sha256),
+        archive_path,
+        quarantine_id: item.id,
+        file_name: item.file_name,
+        original_path: item.original_path,
+        file_size: item.file_size,
+        hashes: item.hashes,
+        verdict: item.verdict,
+        detection_count: item.detection_count,
+        quarantined_at: item.quarantined_at,
+    }
+}
+
+fn default_destination(settings: &Settings) -> PathBuf {
+    let dir = settings.export_path.as_ref()
+        .map(PathBuf::from)
+        .or_else(dirs::download_dir)
+        .unwrap_or_else(|| PathBuf::from("."));
+    dir.join(format!("samples-{}.zip", chrono::Local::now().format("%Y%m%d-%H%M%S")))
+}
+
+// Tauri command to export quarantine items for analysts; returns the archive path
+#[tauri::command]
+pub async fn export_quarantine_samples(
+    ids: Vec<String>,
+    destination: Option<String>,
+    state: tauri::State<'_, AppState>,
+) -> Result<String, String> {
+    let destination = match destination {
+        Some(path) => PathBuf::from(path),
+        None => default_destination(&Settings::load()?),
+    };
+
+    export_samples(&state, &ids, &destination).await?;
+    Ok(destination.to_string_lossy().to_string())
+}
@@ -0,0 +1,332 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+// Lower values are scanned first
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ScanPriority {
+    UserInitiated,
+    NewDownload,
+    ScheduledRescan,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedScan {
+    pub id: String,
+    pub path: PathBuf,
+    pub file_hash: Option<String>,
+    pub priority: ScanPriority,
+    pub queued_at: chrono::DateTime<chrono::Utc>,
+    // Taken by the scanner but not finished; scanned again if the app exits first
+    #[serde(default)]
+    pub in_flight: bool,
+}
+
+// Scan queue ordered by priority, then by insertion order within a priority.
+// Paths and hashes are indexed so duplicate checks stay O(1).
+#[derive(Debug, Default)]
+pub struct ScanQueue {
+    entries: VecDeque<QueuedScan>,
+    in_flight: Vec<QueuedScan>,
+    paths: HashMap<PathBuf, String>,
+    hashes: HashMap<String, String>,
+    storage_path: Option<PathBuf>,
+}
+
+impl ScanQueue {
+    // Load a queue persisted at `storage_path`, starting empty if there is none
+    pub fn load(storage_path: PathBuf) -> Self {
+        let mut queue = ScanQueue {
+            storage_path: Some(storage_path.clone()),
+            ..Default::default()
+        };
+
+        if let Ok(content) = fs::read_to_string(&storage_path) {
+            match serde_json::from_str::<Vec<QueuedScan>>(&content) {
+                Ok(entries) => {
+                    // Interrupted scans go first so they keep their place within their priority
+                    let (interrupted, waiting): (Vec<_>, Vec<_>) =
+                        entries.into_iter().partition(|entry| entry.in_flight);
+                    for mut entry in interrupted.into_iter().chain(waiting) {
+                        if entry.path.is_file() {
+                            entry.in_flight = false;
+                            queue.insert_sorted(entry);
+                        }
+                    }
+                    log::info!("Restored {} queued scans", queue.len());
+                }
+                Err(e) => log::warn!("Failed to parse scan queue: {}", e),
+            }
+        }
+
+        queue
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn list(&self) -> Vec<QueuedScan> {
+        self.entries.iter().cloned().collect()
+    }
+
+    // Queue a file unless the same path or content is already waiting. A duplicate
+    // requested at a higher priority is moved up instead. Returns whether anything changed.
+    pub fn push(&mut self, path: PathBuf, file_hash: Option<String>, priority: ScanPriority) -> bool {
+        let existing = self.paths.get(&path)
+            .or_else(|| file_hash.as_ref().and_then(|hash| self.hashes.get(hash)))
+            .cloned();
+
+        if let Some(id) = existing {
+            let Some(index) = self.position(&id) else { return false };
+            if self.entries[index].priority <= priority {
+                return false;
+            }
+            let mut entry = self.take(index);
+            entry.priority = priority;
+            self.insert_sorted(entry);
+        } else {
+            self.insert_sorted(QueuedScan {
+                id: uuid::Uuid::new_v4().to_string(),
+                path,
+                file_hash,
+                priority,
+                queued_at: chrono::Utc::now(),
+                in_flight: false,
+            });
+        }
+
+        self.persist();
+        true
+    }
+
+    // Whether the path, or a file with the same content, is waiting or being scanned
+    pub fn contains(&self, path: &Path, file_hash: Option<&str>) -> bool {
+        self.paths.contains_key(path) || file_hash.is_some_and(|hash| self.hashes.contains_key(hash))
+    }
+
+    // Drop a waiting entry by path; scans already started are left to finish
+    pub fn remove_path(&mut self, path: &Path) -> Option<QueuedScan> {
+        let index = self.entries.iter().position(|entry| entry.path == path)?;
+        let entry = self.take(index);
+        self.persist();
+        Some(entry)
+    }
+
+    // Hand out the next scan. It stays persisted, and blocks duplicates, until `complete`.
+    pub fn pop(&mut self) -> Option<QueuedScan> {
+        let mut entry = self.entries.pop_front()?;
+        entry.in_flight = true;
+        self.in_flight.push(entry.clone());
+        self.persist();
+        Some(entry)
+    }
+
+    pub fn complete(&mut self, id: &str) {
+        let Some(index) = self.in_flight.iter().position(|entry| entry.id == id) else { return };
+        let entry = self.in_flight.remove(index);
+        self.unindex(&entry);
+        self.persist();
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<QueuedScan> {
+        let index = self.position(id)?;
+        let entry = self.take(index);
+        self.persist();
+        Some(entry)
+    }
+
+    // Move an entry to a new position. It adopts the priority of the entry it lands
+    // next to, so the queue stays grouped by priority.
+    pub fn move_to(&mut self, id: &str, new_index: usize) -> Result<(), String> {
+        let index = self.position(id).ok_or_else(|| format!("No queued scan with id {}", id))?;
+        let mut entry = self.take(index);
+
+        let new_index = new_index.min(self.entries.len());
+        if let Some(neighbour) = self.entries.get(new_index).or_else(|| self.entries.back()) {
+            entry.priority = neighbour.priority;
+        }
+
+        self.index(&entry);
+        self.entries.insert(new_index, entry);
+        self.persist();
+        Ok(())
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.id == id)
+    }
+
+    fn insert_sorted(&mut self, entry: QueuedScan) {
+        let index = self.entries.partition_point(|queued| queued.priority <= entry.priority);
+        self.index(&entry);
+        self.entries.insert(index, entry);
+    }
+
+    fn index(&mut self, entry: &QueuedScan) {
+        self.paths.insert(entry.path.clone(), entry.id.clone());
+        if let Some(hash) = &entry.file_hash {
+            self.hashes.insert(hash.clone(), entry.id.clone());
+        }
+    }
+
+    fn take(&mut self, index: usize) -> QueuedScan {
+        let entry = self.entries.remove(index).expect("queue index in bounds");
+        self.unindex(&entry);
+        entry
+    }
+
+    fn unindex(&mut self, entry: &QueuedScan) {
+        self.paths.remove(&entry.path);
+        if let Some(hash) = &entry.file_hash {
+            self.hashes.remove(hash);
+        }
+    }
+
+    fn persist(&self) {
+        let Some(storage_path) = &self.storage_path else { return };
+        let entries: Vec<QueuedScan> = self.entries.iter().chain(&self.in_flight).cloned().collect();
+        if let Err(e) = write_queue(storage_path, &entries) {
+            log::error!("Failed to persist scan queue: {}", e);
+        }
+    }
+}
+
+// Write through a temporary file so a crash never leaves a truncated queue behind
+fn write_queue(storage_path: &Path, entries: &[QueuedScan]) -> Result<(), String> {
+    if let Some(parent) = storage_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(entries)
+        .map_err(|e| format!("Failed to serialize scan queue: {}", e))?;
+    let tmp_path = storage_path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write scan queue: {}", e))?;
+    fs::rename(&tmp_path, storage_path)
+        .map_err(|e| format!("Failed to replace scan queue: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    struct Fixture {
+        dir: TempDir,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self { dir: TempDir::new().unwrap() }
+        }
+
+        fn storage_path(&self) -> PathBuf {
+            self.dir.path().join("scan_queue.json")
+        }
+
+        // Queued files must exist for `load` to restore them
+        fn file(&self, name: &str) -> PathBuf {
+            let path = self.dir.path().join(name);
+            fs::write(&path, name).unwrap();
+            path
+        }
+    }
+
+    fn paths(queue: &ScanQueue) -> Vec<PathBuf> {
+        queue.list().into_iter().map(|entry| entry.path).collect()
+    }
+
+    #[test]
+    fn pops_by_priority_then_insertion_order() {
+        let mut queue = ScanQueue::default();
+        queue.push(PathBuf::from("/rescan"), None, ScanPriority::ScheduledRescan);
+        queue.push(PathBuf::from("/download-1"), None, ScanPriority::NewDownload);
+        queue.push(PathBuf::from("/user"), None, ScanPriority::UserInitiated);
+        queue.push(PathBuf::from("/download-2"), None, ScanPriority::NewDownload);
+
+        let order: Vec<PathBuf> = std::iter::from_fn(|| queue.pop()).map(|entry| entry.path).collect();
+        assert_eq!(order, ["/user", "/download-1", "/download-2", "/rescan"].map(PathBuf::from));
+    }
+
+    #[test]
+    fn ignores_a_path_that_is_already_queued() {
+        let mut queue = ScanQueue::default();
+        assert!(queue.push(PathBuf::from("/a"), None, ScanPriority::NewDownload));
+        assert!(!queue.push(PathBuf::from("/a"), None, ScanPriority::ScheduledRescan));
+        assert_eq!(queue.len(), 1);
+
+        // Still blocked while it is being scanned, and free again once complete
+        let entry = queue.pop().unwrap();
+        assert!(!queue.push(PathBuf::from("/a"), None, ScanPriority::NewDownload));
+        queue.complete(&entry.id);
+        assert!(queue.push(PathBuf::from("/a"), None, ScanPriority::NewDownload));
+    }
+
+    #[test]
+    fn ignores_a_file_with_the_same_hash() {
+        let mut queue = ScanQueue::default();
+        queue.push(PathBuf::from("/a"), Some("abc".to_string()), ScanPriority::NewDownload);
+        assert!(!queue.push(PathBuf::from("/copy-of-a"), Some("abc".to_string()), ScanPriority::NewDownload));
+        assert!(queue.contains(Path::new("/elsewhere"), Some("abc")));
+        assert_eq!(paths(&queue), [PathBuf::from("/a")]);
+    }
+
+    #[test]
+    fn raises_the_priority_of_a_duplicate() {
+        let mut queue = ScanQueue::default();
+        queue.push(PathBuf::from("/a"), None, ScanPriority::NewDownload);
+        queue.push(PathBuf::from("/b"), Some("abc".to_string()), ScanPriority::ScheduledRescan);
+
+        assert!(queue.push(PathBuf::from("/b-copy"), Some("abc".to_string()), ScanPriority::UserInitiated));
+        let first = queue.list().remove(0);
+        assert_eq!(first.path, PathBuf::from("/b"));
+        assert_eq!(first.priority, ScanPriority::UserInitiated);
+        assert_eq!(queue.len(), 2);
+    }
+
+    #[test]
+    fn load_restores_interrupted_scans_first() {
+        let fixture = Fixture::new();
+        let interrupted = fixture.file("interrupted");
+        let waiting = fixture.file("waiting");
+        let finished = fixture.file("finished");
+
+        {
+            let mut queue = ScanQueue::load(fixture.storage_path());
+            queue.push(finished.clone(), None, ScanPriority::NewDownload);
+            queue.push(interrupted.clone(), None, ScanPriority::NewDownload);
+            queue.push(waiting.clone(), None, ScanPriority::NewDownload);
+            let done = queue.pop().unwrap();
+            queue.complete(&done.id);
+            queue.pop().unwrap();
+        }
+
+        let mut queue = ScanQueue::load(fixture.storage_path());
+        assert_eq!(paths(&queue), [interrupted.clone(), waiting]);
+        assert!(queue.list().iter().all(|entry| !entry.in_flight));
+        assert!(!queue.push(interrupted, None, ScanPriority::NewDownload));
+    }
+
+    #[test]
+    fn load_drops_files_that_no_longer_exist() {
+        let fixture = Fixture::new();
+        let kept = fixture.file("kept");
+        let deleted = fixture.file("deleted");
+
+        {
+            let mut queue = ScanQueue::load(fixture.storage_path());
+            queue.push(deleted.clone(), None, ScanPriority::NewDownload);
+            queue.push(kept.clone(), None, ScanPriority::NewDownload);
+        }
+        fs::remove_file(&deleted).unwrap();
+
+        assert_eq!(paths(&ScanQueue::load(fixture.storage_path())), [kept]);
+    }
+}
@@ -1,118 +1,119 @@
-use std::sync::Arc;
-use tokio::sync::Mutex;
-use serde_json::json;
-use tauri::Window;
-use crate::config::ScannerConfig;
-
-pub struct BackgroundScanner {
-    config: Arc<Mutex<ScannerConfig>>,
-    window: Arc<Window>,
-    scanning: Arc<Mutex<bool>>,
-    scan_queue: Arc<Mutex<Vec<std::path::PathBuf>>>,
+use std::path::Path;
+use async_trait::async_trait;
+use crate::virus_total::{self, ScanError, ScanResult, VirusTotal};
+use crate::ErrorCategory;
+
+// A single verdict source. Engines run in order until one of them reaches a verdict.
+#[async_trait]
+pub trait ScanEngine: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // Ok(None) hands the file on to the next engine
+    async fn scan(&self, path: &Path, file_hash: &str) -> Result<Option<ScanResult>, ScanError>;
 }
 
-impl BackgroundScanner {
-    pub fn new(window: Window) -> Self {
-        Self {
-            config: Arc::new(Mutex::new(ScannerConfig::default())),
-            window: Arc::new(window),
-            scanning: Arc::new(Mutex::new(false)),
-            scan_queue: Arc::new(Mutex::new(Vec::new())),
-        }
+// Results cached from earlier scans of the same content
+pub struct CacheEngine;
+
+#[async_trait]
+impl ScanEngine for CacheEngine {
+    fn name(&self) -> &'static str {
+        "cache"
     }
 
-    pub async fn set_config(&self, config: ScannerConfig) {
-        let mut current_config = self.config.lock().await;
-        *current_config = config;
+    async fn scan(&self, path: &Path, file_hash: &str) -> Result<Option<ScanResult>, ScanError> {
+        Ok(virus_total::cached_result(file_hash).map(|mut result| {
+            // The same content may have been scanned under another name
+            result.file_path = path.to_string_lossy().to_string();
+            result.file_name = path.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or(result.file_name);
+            result
+        }))
     }
+}
 
-    pub async fn start_scanning(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let mut scanning = self.scanning.lock().await;
-        if *scanning {
-            return Ok(());
-        }
-        *scanning = true;
+// Existing VirusTotal report for the hash, which costs one lookup and no upload
+pub struct HashLookupEngine {
+    vt: VirusTotal,
+}
 
-        let scanner = self.clone();
-        tokio::spawn(async move {
-            scanner.scanning_loop().await;
-        });
+#[async_trait]
+impl ScanEngine for HashLookupEngine {
+    fn name(&self) -> &'static str {
+        "virustotal-lookup"
+    }
 
-        Ok(())
+    async fn scan(&self, path: &Path, file_hash: &str) -> Result<Option<ScanResult>, ScanError> {
+        self.vt.lookup_hash(path, file_hash).await
     }
+}
 
-    async fn scanning_loop(&self) {
-        while *self.scanning.lock().await {
-            let config = self.config.lock().await;
-            
-            // Process batch of files
-            self.process_batch(&config).await;
-            
-            // Wait for next scan interval
-            tokio::time::sleep(config.scan_interval).await;
-        }
+// Full upload and analysis for files VirusTotal has never seen
+pub struct UploadEngine {
+    vt: VirusTotal,
+}
+
+#[async_trait]
+impl ScanEngine for UploadEngine {
+    fn name(&self) -> &'static str {
+        "virustotal-upload"
     }
 
-    async fn process_batch(&self, config: &ScannerConfig) {
-        let mut queue = self.scan_queue.lock().await;
-        let batch: Vec<_> = queue.drain(..config.batch_size.min(queue.len())).collect();
-        
-        for path in batch {
-            let result = self.scan_file(&path, config).await;
-            match result {
-                Ok(scan_result) => {
-                    self.window.emit("scan-complete", json!({
-                        "path": path.to_string_lossy(),
-                        "result": scan_result
-                    })).ok();
-                }
-                Err(e) => {
-                    self.window.emit("scan-error", json!({
-                        "path": path.to_string_lossy(),
-                        "error": e.to_string()
-                    })).ok();
-                }
-            }
-        }
+    async fn scan(&self, path: &Path, file_hash: &str) -> Result<Option<ScanResult>, ScanError> {
+        self.vt.upload_and_analyze(path, file_hash).await.map(Some)
     }
+}
 
-    async fn scan_file(&self, path: &std::path::PathBuf, config: &ScannerConfig) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-        let mut attempts = 0;
-        while attempts < config.retry_attempts {
-            match self.perform_single_scan(path).await {
-                Ok(result) => return Ok(result),
-                Err(e) => {
-                    attempts += 1;
-                    if attempts < config.retry_attempts {
-                        tokio::time::sleep(config.retry_delay).await;
-                    } else {
-                        return Err(e);
-                    }
-                }
-            }
+pub struct ScanPipeline {
+    engines: Vec<Box<dyn ScanEngine>>,
+}
+
+impl ScanPipeline {
+    // Cache first, then a hash lookup, and only upload when both miss
+    pub fn new(vt: VirusTotal) -> Self {
+        Self {
+            engines: vec![
+                Box::new(CacheEngine),
+                Box::new(HashLookupEngine { vt: vt.clone() }),
+                Box::new(UploadEngine { vt }),
+            ],
         }
-        Err("Max retry attempts exceeded".into())
     }
 
-    async fn perform_single_scan(&self, path: &std::path::PathBuf) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
-        // TODO: Implement actual virus scanning logic here
-        // For now, return a mock result
-        Ok(json!({
-            "status": "clean",
-            "scanned_at": chrono::Utc::now().to_rfc3339(),
-            "threats_found": 0
-        }))
+    // Known verdicts only, for callers that must never upload what they are given
+    pub fn lookup_only(vt: VirusTotal) -> Self {
+        Self {
+            engines: vec![
+                Box::new(CacheEngine),
+                Box::new(HashLookupEngine { vt }),
+            ],
+        }
     }
 
-    pub async fn stop_scanning(&self) {
-        let mut scanning = self.scanning.lock().await;
-        *scanning = false;
+    pub async fn scan(&self, path: &Path) -> Result<ScanResult, ScanError> {
+        self.try_scan(path).await?
+            .ok_or_else(|| ScanError::new(ErrorCategory::UnknownError, "No scan engine produced a verdict"))
     }
 
-    pub async fn add_to_queue(&self, path: std::path::PathBuf) {
-        let mut queue = self.scan_queue.lock().await;
-        if !queue.contains(&path) {
-            queue.push(path);
+    // Ok(None) when every engine passed on the file
+    pub async fn try_scan(&self, path: &Path) -> Result<Option<ScanResult>, ScanError> {
+        log::info!("Scanning file: {}", path.display());
+
+        if !path.is_file() {
+            return Err(ScanError::new(ErrorCategory::FileSystemError, "File does not exist"));
         }
+
+        let file_hash = virus_total::calculate_file_hash(path).await
+            .map_err(|e| ScanError::new(ErrorCategory::FileSystemError, e))?;
+
+        for engine in &self.engines {
+            if let Some(result) = engine.scan(path, &file_hash).await? {
+                log::info!("Verdict for {} from {}: {:?}", path.display(), engine.name(), result.status);
+                return Ok(Some(result));
+            }
+        }
+
+        Ok(None)
     }
 }
@@ -0,0 +1,652 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use tokio::sync::{Mutex, Notify};
+use tokio::time::{sleep, Duration};
+use crate::{cleanup, folder_scan, relookup};
+use crate::host::Host;
+use crate::scan_queue::ScanPriority;
+use crate::settings::{self, FieldError, Settings};
+use crate::AppState;
+
+// Built-in jobs kept in sync with Settings::auto_rescan_interval and Settings::relookup,
+// plus the daily cleanup that always runs
+pub const AUTO_RESCAN_JOB_ID: &str = "auto-rescan";
+pub const RELOOKUP_JOB_ID: &str = "relookup-clean-hashes";
+pub const CLEANUP_JOB_ID: &str = "cleanup";
+const CLEANUP_INTERVAL_SECS: u64 = 24 * 3600;
+
+// Runs that are late by less than this are on time rather than missed
+const MISSED_RUN_GRACE_SECS: i64 = 60;
+
+// Upper bound on a single sleep, so a suspended machine notices missed runs soon after waking
+const MAX_SLEEP_SECS: u64 = 60;
+
+const MIN_INTERVAL_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobSchedule {
+    // Five fields (minute hour day month weekday) or six with seconds, in local time
+    Cron { expression: String },
+    Interval { every_secs: u64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum JobAction {
+    FolderScan { path: String, recursive: bool },
+    // Queue every file in the scan history that still exists for another scan
+    RescanHistory,
+    // Look up hashes scanned clean within the window again and report new detections
+    RecheckHashes { window_days: u32 },
+    // Apply the retention settings to quarantine, history, cache and temp files
+    Cleanup,
+}
+
+// Fields the UI supplies when creating or editing a job
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobDefinition {
+    pub name: String,
+    pub schedule: JobSchedule,
+    pub action: JobAction,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_true")]
+    pub catch_up: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: String,
+    pub name: String,
+    pub schedule: JobSchedule,
+    pub action: JobAction,
+    pub enabled: bool,
+    // Run once on wake or restart when a run was missed, instead of waiting for the next slot
+    pub catch_up: bool,
+    pub last_run: Option<DateTime<Utc>>,
+    pub next_run: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JobRunEvent {
+    pub job_id: String,
+    pub name: String,
+    pub started_at: DateTime<Utc>,
+    pub missed: bool,
+    pub error: Option<String>,
+}
+
+impl JobSchedule {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        match self {
+            JobSchedule::Cron { expression } => {
+                if let Err(e) = parse_cron(expression) {
+                    errors.push(FieldError::new("schedule.expression", e));
+                }
+            }
+            JobSchedule::Interval { every_secs } => {
+                if *every_secs < MIN_INTERVAL_SECS {
+                    errors.push(FieldError::new("schedule.every_secs", format!("Interval must be at least {} seconds", MIN_INTERVAL_SECS)));
+                }
+            }
+        }
+    }
+
+    // Next run strictly after `after`, given when the job last ran
+    pub fn next_after(&self, after: DateTime<Utc>, last_run: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        match self {
+            JobSchedule::Cron { expression } => {
+                let schedule = parse_cron(expression).ok()?;
+                schedule.after(&after.with_timezone(&Local)).next()
+                    .map(|next| next.with_timezone(&Utc))
+            }
+            JobSchedule::Interval { every_secs } => {
+                let every = chrono::Duration::seconds(*every_secs as i64);
+                let next = last_run.map(|last| last + every).unwrap_or(after + every);
+                Some(if next > after { next } else { after + every })
+            }
+        }
+    }
+}
+
+// Five fields are read as a standard crontab line; six or seven use the cron crate's
+// own format, which starts with seconds and numbers weekdays 1-7 from Sunday
+fn parse_cron(expression: &str) -> Result<cron::Schedule, String> {
+    let fields: Vec<&str> = expression.split_whitespace().collect();
+    let expression = match fields.as_slice() {
+        [minute, hour, day, month, weekday] => {
+            format!("0 {} {} {} {} {}", minute, hour, day, month, crontab_weekdays(weekday)?)
+        }
+        _ if fields.len() == 6 || fields.len() == 7 => expression.to_string(),
+        _ => return Err("Cron expression must have five or six fields".to_string()),
+    };
+    cron::Schedule::from_str(&expression)
+        .map_err(|e| format!("Invalid cron expression: {}", e))
+}
+
+// Crontab numbers weekdays 0-7 with Sunday as both 0 and 7; the cron crate uses 1-7 from
+// Sunday. Names, `*` and steps over `*` mean the same in both.
+fn crontab_weekdays(field: &str) -> Result<String, String> {
+    let day = |value: &str| -> Result<u32, String> {
+        match value.parse::<u32>() {
+            Ok(day @ 0..=7) => Ok(day % 7 + 1),
+            _ => Err(format!("Invalid day of week: {}", value)),
+        }
+    };
+
+    let mut items = Vec::new();
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, Some(step)),
+            None => (item, None),
+        };
+        let numeric = |value: &str| value.chars().all(|c| c.is_ascii_digit());
+        let range = match range.split_once('-') {
+            Some((start, end)) if numeric(start) && numeric(end) => {
+                let (start, end) = (day(start)?, end.parse::<u32>().map_err(|_| format!("Invalid day of week: {}", end))?);
+                match end {
+                    // Ending on Sunday wraps around to the crate's first day
+                    7 if step.is_some() => return Err("Weekday ranges ending at 7 cannot have a step; use 0 or names".to_string()),
+                    7 if start == 1 => "1-7".to_string(),
+                    7 => {
+                        items.push("1".to_string());
+                        format!("{}-7", start)
+                    }
+                    _ => format!("{}-{}", start, day(&end.to_string())?),
+                }
+            }
+            None if numeric(range) && !range.is_empty() => day(range)?.to_string(),
+            _ => range.to_string(),
+        };
+        items.push(match step {
+            Some(step) => format!("{}/{}", range, step),
+            None => range,
+        });
+    }
+    Ok(items.join(","))
+}
+
+impl JobDefinition {
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new("name", "Job name cannot be empty"));
+        }
+        self.schedule.validate(&mut errors);
+        if let JobAction::FolderScan { path, .. } = &self.action {
+            if !expand_home(path).is_dir() {
+                errors.push(FieldError::new("action.path", format!("Directory does not exist: {}", path)));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+// Allow paths such as ~/Downloads in job definitions
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Scheduler {
+    jobs: Arc<Mutex<Vec<ScheduledJob>>>,
+    running: Arc<AtomicBool>,
+    wake: Arc<Notify>,
+    // Ids of jobs whose action is in progress, so a slow job never overlaps itself
+    active: Arc<std::sync::Mutex<HashSet<String>>>,
+}
+
+impl Scheduler {
+    fn storage_path() -> PathBuf {
+        settings::data_dir().join("scheduled_jobs.json")
+    }
+
+    // Restore persisted jobs. Run times are kept as they were so missed runs can be caught up.
+    pub async fn load(&self) {
+        let jobs = match fs::read_to_string(Self::storage_path()) {
+            Ok(content) => serde_json::from_str::<Vec<ScheduledJob>>(&content).unwrap_or_else(|e| {
+                log::warn!("Failed to parse scheduled jobs: {}", e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        log::info!("Loaded {} scheduled jobs", jobs.len());
+        *self.jobs.lock().await = jobs;
+    }
+
+    pub fn start(&self, host: Host) -> bool {
+        if self.running.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+
+        let scheduler = self.clone();
+        tauri::async_runtime::spawn(async move {
+            scheduler.run_loop(host).await;
+        });
+        true
+    }
+
+    pub async fn list(&self) -> Vec<ScheduledJob> {
+        self.jobs.lock().await.clone()
+    }
+
+    pub async fn create(&self, definition: JobDefinition) -> Result<ScheduledJob, String> {
+        let now = Utc::now();
+        let job = ScheduledJob {
+            id: uuid::Uuid::new_v4().to_string(),
+            next_run: definition.schedule.next_after(now, None),
+            name: definition.name,
+            schedule: definition.schedule,
+            action: definition.action,
+            enabled: definition.enabled,
+            catch_up: definition.catch_up,
+            last_run: None,
+            last_error: None,
+        };
+
+        let mut jobs = self.jobs.lock().await;
+        jobs.push(job.clone());
+        persist(&jobs);
+        drop(jobs);
+
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    pub async fn update(&self, id: &str, definition: JobDefinition) -> Result<ScheduledJob, String> {
+        let mut jobs = self.jobs.lock().await;
+        let job = jobs.iter_mut()
+            .find(|job| job.id == id)
+            .ok_or_else(|| format!("No scheduled job with id {}", id))?;
+
+        if job.schedule != definition.schedule {
+            job.next_run = definition.schedule.next_after(Utc::now(), job.last_run);
+        }
+        job.name = definition.name;
+        job.schedule = definition.schedule;
+        job.action = definition.action;
+        job.enabled = definition.enabled;
+        job.catch_up = definition.catch_up;
+
+        let job = job.clone();
+        persist(&jobs);
+        drop(jobs);
+
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<bool, String> {
+        if [AUTO_RESCAN_JOB_ID, RELOOKUP_JOB_ID, CLEANUP_JOB_ID].contains(&id) {
+            return Err("Built-in jobs are controlled from the settings".to_string());
+        }
+
+        let mut jobs = self.jobs.lock().await;
+        let before = jobs.len();
+        jobs.retain(|job| job.id != id);
+        let removed = jobs.len() != before;
+        if removed {
+            persist(&jobs);
+        }
+        Ok(removed)
+    }
+
+    // Create, update or remove the built-in jobs to match the settings
+    pub async fn sync_builtin_jobs(&self, settings: &Settings) {
+        let rescan = settings.auto_rescan_interval.map(|hours| (
+            JobSchedule::Interval { every_secs: hours * 3600 },
+            JobAction::RescanHistory,
+        ));
+        let relookup = settings.relookup.enabled.then(|| (
+            JobSchedule::Interval { every_secs: settings.relookup.interval_hours * 3600 },
+            JobAction::RecheckHashes { window_days: settings.relookup.window_days },
+        ));
+
+        let mut jobs = self.jobs.lock().await;
+        let mut changed = sync_builtin(&mut jobs, AUTO_RESCAN_JOB_ID, "Rescan scan history", rescan);
+        changed |= sync_builtin(&mut jobs, RELOOKUP_JOB_ID, "Re-check clean hashes", relookup);
+        changed |= sync_builtin(&mut jobs, CLEANUP_JOB_ID, "Clean up quarantine, history and temp files", Some((
+            JobSchedule::Interval { every_secs: CLEANUP_INTERVAL_SECS },
+            JobAction::Cleanup,
+        )));
+
+        if changed {
+            persist(&jobs);
+            drop(jobs);
+            self.wake.notify_one();
+        }
+    }
+
+    // Run a job immediately without disturbing its schedule
+    pub async fn run_now(&self, host: &Host, id: &str) -> Result<(), String> {
+        let job = self.jobs.lock().await.iter()
+            .find(|job| job.id == id)
+            .cloned()
+            .ok_or_else(|| format!("No scheduled job with id {}", id))?;
+        if !self.claim(&job.id) {
+            return Err(format!("Scheduled job {} is already running", job.name));
+        }
+        let result = run_action(host, &job.action).await;
+        self.release(&job.id);
+        result
+    }
+
+    fn claim(&self, id: &str) -> bool {
+        self.active.lock().unwrap().insert(id.to_string())
+    }
+
+    fn release(&self, id: &str) {
+        self.active.lock().unwrap().remove(id);
+    }
+
+    async fn run_loop(&self, host: Host) {
+        loop {
+            self.run_due_jobs(&host).await;
+
+            let now = Utc::now();
+            let next_due = self.jobs.lock().await.iter()
+                .filter(|job| job.enabled)
+                .filter_map(|job| job.next_run)
+                .min();
+            let wait_secs = next_due
+                .map(|next| (next - now).num_seconds().max(1) as u64)
+                .unwrap_or(MAX_SLEEP_SECS)
+                .min(MAX_SLEEP_SECS);
+
+            // Wall-clock deadlines are re-checked after every sleep, so time spent
+            // suspended shows up as due or missed runs on the next pass
+            tokio::select! {
+                _ = sleep(Duration::from_secs(wait_secs)) => {}
+                _ = self.wake.notified() => {}
+            }
+        }
+    }
+
+    async fn run_due_jobs(&self, host: &Host) {
+        let now = Utc::now();
+        let due: Vec<(ScheduledJob, bool)> = {
+            let mut jobs = self.jobs.lock().await;
+            let mut due = Vec::new();
+            for job in jobs.iter_mut().filter(|job| job.enabled) {
+                let Some(next_run) = job.next_run else {
+                    job.next_run = job.schedule.next_after(now, job.last_run);
+                    continue;
+                };
+                if next_run > now {
+                    continue;
+                }
+
+                // However many slots were missed, a job runs at most once to catch up
+                let missed = (now - next_run).num_seconds() > MISSED_RUN_GRACE_SECS;
+                job.next_run = job.schedule.next_after(now, Some(now));
+                if missed && !job.catch_up {
+                    log::info!("Skipping missed run of scheduled job {}", job.name);
+                    continue;
+                }
+                if !self.claim(&job.id) {
+                    log::info!("Skipping run of scheduled job {}, the previous one is still going", job.name);
+                    continue;
+                }
+                job.last_run = Some(now);
+                due.push((job.clone(), missed));
+            }
+            persist(&jobs);
+            due
+        };
+
+        // Each action runs on its own, so a long re-lookup holds up neither other jobs
+        // nor the loop picking up schedule changes
+        for (job, missed) in due {
+            let scheduler = self.clone();
+            let host = host.clone();
+            tauri::async_runtime::spawn(async move {
+                scheduler.run_job(&host, job, now, missed).await;
+            });
+        }
+    }
+
+    async fn run_job(&self, host: &Host, job: ScheduledJob, started_at: DateTime<Utc>, missed: bool) {
+        log::info!("Running scheduled job {}{}", job.name, if missed { " (missed run)" } else { "" });
+        let error = run_action(host, &job.action).await.err();
+        self.release(&job.id);
+        if let Some(e) = &error {
+            log::error!("Scheduled job {} failed: {}", job.name, e);
+        }
+
+        {
+            let mut jobs = self.jobs.lock().await;
+            if let Some(stored) = jobs.iter_mut().find(|stored| stored.id == job.id) {
+                stored.last_error = error.clone();
+            }
+            persist(&jobs);
+        }
+
+        let event = JobRunEvent {
+            job_id: job.id,
+            name: job.name,
+            started_at,
+            missed,
+            error,
+        };
+        if let Err(e) = host.emit("scheduled-job-run", &event) {
+            log::error!("Failed to emit scheduled-job-run event: {}", e);
+        }
+    }
+}
+
+// Bring one built-in job in line with its setting. Returns whether anything changed.
+fn sync_builtin(
+    jobs: &mut Vec<ScheduledJob>,
+    id: &str,
+    name: &str,
+    wanted: Option<(JobSchedule, JobAction)>,
+) -> bool {
+    let existing = jobs.iter().position(|job| job.id == id);
+
+    match (wanted, existing) {
+        (None, None) => false,
+        (None, Some(index)) => {
+            jobs.remove(index);
+            true
+        }
+        (Some((schedule, action)), Some(index)) => {
+            let job = &mut jobs[index];
+            if job.schedule == schedule && job.action == action {
+                return false;
+            }
+            if job.schedule != schedule {
+                job.next_run = schedule.next_after(Utc::now(), job.last_run);
+            }
+            job.schedule = schedule;
+            job.action = action;
+            true
+        }
+        (Some((schedule, action)), None) => {
+            jobs.push(ScheduledJob {
+                id: id.to_string(),
+                name: name.to_string(),
+                next_run: schedule.next_after(Utc::now(), None),
+                schedule,
+                action,
+                enabled: true,
+                catch_up: true,
+                last_run: None,
+                last_error: None,
+            });
+            true
+        }
+    }
+}
+
+async fn run_action(host: &Host, action: &JobAction) -> Result<(), String> {
+    match action {
+        JobAction::FolderScan { path, recursive } => {
+            folder_scan::start_folder_scan(host.clone(), expand_home(path), *recursive).await?;
+            Ok(())
+        }
+        JobAction::RescanHistory => {
+            let state = host.state();
+            let mut paths: Vec<PathBuf> = state.scan_history.lock().await.iter()
+                .map(|result| PathBuf::from(&result.file_path))
+                .filter(|path| path.is_file())
+                .collect();
+            paths.sort();
+            paths.dedup();
+
+            for path in paths {
+                state.background_scanner.add_to_queue(path, ScanPriority::ScheduledRescan).await;
+            }
+            Ok(())
+        }
+        JobAction::RecheckHashes { window_days } => {
+            relookup::recheck_clean_hashes(host, *window_days).await?;
+            Ok(())
+        }
+        JobAction::Cleanup => {
+            let state = host.state();
+            let report = cleanup::run(state, &Settings::load()?, false).await;
+            if let Err(e) = host.emit("cleanup-complete", &report) {
+                log::error!("Failed to emit cleanup-complete event: {}", e);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn persist(jobs: &[ScheduledJob]) {
+    if let Err(e) = write_jobs(&Scheduler::storage_path(), jobs) {
+        log::error!("Failed to persist scheduled jobs: {}", e);
+    }
+}
+
+fn write_jobs(storage_path: &Path, jobs: &[ScheduledJob]) -> Result<(), String> {
+    if let Some(parent) = storage_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+
+    let content = serde_json::to_string_pretty(jobs)
+        .map_err(|e| format!("Failed to serialize scheduled jobs: {}", e))?;
+    let tmp_path = storage_path.with_extension("json.tmp");
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write scheduled jobs: {}", e))?;
+    fs::rename(&tmp_path, storage_path)
+        .map_err(|e| format!("Failed to replace scheduled jobs: {}", e))
+}
+
+// Tauri commands for scheduled jobs
+#[tauri::command]
+pub async fn list_scheduled_jobs(state: tauri::State<'_, AppState>) -> Result<Vec<ScheduledJob>, String> {
+    Ok(state.scheduler.list().await)
+}
+
+#[tauri::command]
+pub async fn create_scheduled_job(
+    job: JobDefinition,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScheduledJob, SchedulerError> {
+    job.validate().map_err(|errors| SchedulerError::Invalid { errors })?;
+    Ok(state.scheduler.create(job).await?)
+}
+
+#[tauri::command]
+pub async fn update_scheduled_job(
+    id: String,
+    job: JobDefinition,
+    state: tauri::State<'_, AppState>,
+) -> Result<ScheduledJob, SchedulerError> {
+    job.validate().map_err(|errors| SchedulerError::Invalid { errors })?;
+    Ok(state.scheduler.update(&id, job).await?)
+}
+
+#[tauri::command]
+pub async fn delete_scheduled_job(id: String, state: tauri::State<'_, AppState>) -> Result<bool, String> {
+    state.scheduler.delete(&id).await
+}
+
+#[tauri::command]
+pub async fn run_scheduled_job_now(
+    id: String,
+    state: tauri::State<'_, AppState>,
+    app_handle: AppHandle,
+) -> Result<(), String> {
+    state.scheduler.run_now(&Host::App(app_handle), &id).await
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SchedulerError {
+    Invalid { errors: Vec<FieldError> },
+    Failed { message: String },
+}
+
+impl From<String> for SchedulerError {
+    fn from(message: String) -> Self {
+        SchedulerError::Failed { message }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, TimeZone, Timelike, Weekday};
+
+    #[test]
+    fn crontab_weekdays_are_shifted_to_the_crate_numbering() {
+        assert_eq!(crontab_weekdays("0").unwrap(), "1");
+        assert_eq!(crontab_weekdays("7").unwrap(), "1");
+        assert_eq!(crontab_weekdays("1-5").unwrap(), "2-6");
+        assert_eq!(crontab_weekdays("0,6").unwrap(), "1,7");
+        assert_eq!(crontab_weekdays("5-7").unwrap(), "1,6-7");
+        assert_eq!(crontab_weekdays("0-7").unwrap(), "1-7");
+        assert_eq!(crontab_weekdays("1-5/2").unwrap(), "2-6/2");
+    }
+
+    #[test]
+    fn names_and_wildcards_pass_through() {
+        assert_eq!(crontab_weekdays("*").unwrap(), "*");
+        assert_eq!(crontab_weekdays("*/2").unwrap(), "*/2");
+        assert_eq!(crontab_weekdays("MON-FRI").unwrap(), "MON-FRI");
+    }
+
+    #[test]
+    fn out_of_range_weekdays_are_rejected() {
+        assert!(crontab_weekdays("8").is_err());
+        assert!(crontab_weekdays("5-7/2").is_err());
+        assert!(parse_cron("0 3 * * 9").is_err());
+    }
+
+    #[test]
+    fn five_field_expressions_use_crontab_weekdays() {
+        // Saturday 2024-06-01, in local time like the scheduler
+        let after = Local.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+
+        let sunday = parse_cron("0 3 * * 0").unwrap().after(&after).next().unwrap();
+        assert_eq!(sunday.weekday(), Weekday::Sun);
+
+        let monday = parse_cron("30 2 * * 1").unwrap().after(&after).next().unwrap();
+        assert_eq!(monday.weekday(), Weekday::Mon);
+        assert_eq!((monday.hour(), monday.minute()), (2, 30));
+    }
+}
@@ -1,42 +1,122 @@
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
 use crate::AppState;
+use crate::host::Host;
+use crate::clamd::ClamdConfig;
+use crate::config::{DeleteConfig, MonitorConfig, RelookupConfig, ScannerConfig};
+use crate::logger::LogConfig;
+use crate::notifications::NotificationConfig;
+use crate::removable_media::RemovableMediaSettings;
+use crate::rest_api::RestApiConfig;
+use crate::siem::SiemConfig;
+use crate::webhooks::WebhookConfig;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Settings {
     pub api_key: String,
     pub welcome_completed: bool,
     pub auto_scan_downloads: bool,
     pub notify_on_scan_completion: bool,
+    pub notifications: NotificationConfig,
+    pub webhooks: Vec<WebhookConfig>,
+    pub siem: SiemConfig,
+    pub rest_api: RestApiConfig,
+    pub clamd: ClamdConfig,
     pub auto_quarantine_malicious: bool,
     pub auto_rescan_interval: Option<u64>, // Hours between automatic rescans
-    pub custom_scan_locations: Vec<String>,
     pub quarantine_settings: QuarantineSettings,
     pub scan_history_limit: u32,
-    pub file_type_filters: Vec<String>,
     pub theme: String,
     pub startup_with_system: bool,
     pub minimize_to_tray: bool,
     pub export_path: Option<String>,
-    pub background_scan_threads: u32,
+    pub scanner: ScannerConfig,
+    pub monitor: MonitorConfig,
+    pub relookup: RelookupConfig,
+    pub delete: DeleteConfig,
+    pub logging: LogConfig,
+    pub removable_media: RemovableMediaSettings,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct QuarantineSettings {
     pub auto_delete_after_days: Option<u32>,
-    pub backup_before_quarantine: bool,
+    // Decode the vault copy and compare its hash before the original is removed
+    #[serde(alias = "backup_before_quarantine")]
+    pub verify_before_quarantine: bool,
     pub quarantine_location: Option<String>,
     pub notify_on_quarantine: bool,
+    // Detections at which a Suspicious verdict is quarantined too; None leaves suspicious files alone
+    pub suspicious_threshold: Option<u32>,
+    // How long an automatic quarantine can be undone from the notification or UI
+    pub undo_window_secs: u64,
+}
+
+// Fields from the old flat settings file that now live in the scanner and monitor sections
+#[derive(Debug, Default, Deserialize)]
+struct LegacySettings {
+    custom_scan_locations: Option<Vec<String>>,
+    file_type_filters: Option<Vec<String>>,
+    background_scan_threads: Option<u32>,
+}
+
+// A validation failure for a single settings field, keyed by its dotted path
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SettingsError {
+    Invalid { errors: Vec<FieldError> },
+    Storage { message: String },
+}
+
+impl From<String> for SettingsError {
+    fn from(message: String) -> Self {
+        SettingsError::Storage { message }
+    }
+}
+
+impl std::fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingsError::Invalid { errors } => {
+                let fields: Vec<String> = errors.iter()
+                    .map(|e| format!("{}: {}", e.field, e.message))
+                    .collect();
+                write!(f, "Invalid settings ({})", fields.join("; "))
+            }
+            SettingsError::Storage { message } => write!(f, "{}", message),
+        }
+    }
 }
 
 impl Default for QuarantineSettings {
     fn default() -> Self {
         Self {
             auto_delete_after_days: Some(30),
-            backup_before_quarantine: true,
+            verify_before_quarantine: true,
             quarantine_location: None,
             notify_on_quarantine: true,
+            suspicious_threshold: Some(5),
+            undo_window_secs: 300,
         }
     }
 }
@@ -48,27 +128,52 @@ impl Default for Settings {
             welcome_completed: false,
             auto_scan_downloads: true,
             notify_on_scan_completion: true,
+            notifications: NotificationConfig::default(),
+            webhooks: Vec::new(),
+            siem: SiemConfig::default(),
+            rest_api: RestApiConfig::default(),
+            clamd: ClamdConfig::default(),
             auto_quarantine_malicious: false,
             auto_rescan_interval: Some(24), // Default to daily rescans
-            custom_scan_locations: Vec::new(),
             quarantine_settings: QuarantineSettings::default(),
             scan_history_limit: 1000,
-            file_type_filters: vec![
-                "exe".to_string(), "dll".to_string(), "bat".to_string(),
-                "cmd".to_string(), "ps1".to_string(), "vbs".to_string(),
-                "js".to_string(), "jar".to_string(), "msi".to_string(),
-                "zip".to_string(), "rar".to_string(), "scr".to_string(),
-                "pdf".to_string(), "doc".to_string(), "docx".to_string()
-            ],
             theme: "system".to_string(),
             startup_with_system: false,
             minimize_to_tray: true,
             export_path: None,
-            background_scan_threads: 2,
+            scanner: ScannerConfig::default(),
+            monitor: MonitorConfig::default(),
+            relookup: RelookupConfig::default(),
+            delete: DeleteConfig::default(),
+            logging: LogConfig::default(),
+            removable_media: RemovableMediaSettings::default(),
         }
     }
 }
 
+// The last settings read for `Settings::cached`, with the file's modification time then
+type CachedSettings = Option<(Option<SystemTime>, Arc<Settings>)>;
+static CACHED: once_cell::sync::Lazy<std::sync::Mutex<CachedSettings>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+// Directory holding settings.json
+pub fn config_dir() -> PathBuf {
+    if let Some(app_dir) = dirs::config_dir() {
+        app_dir.join("virus-scanner-app")
+    } else {
+        PathBuf::from("./config")
+    }
+}
+
+// Directory holding persisted application state such as the scan queue
+pub fn data_dir() -> PathBuf {
+    if let Some(app_dir) = dirs::data_dir() {
+        app_dir.join("virus-scanner-app")
+    } else {
+        PathBuf::from("./data")
+    }
+}
+
 impl Settings {
     #[allow(dead_code)]
     pub fn unwrap_or_default(self) -> Self {
@@ -76,56 +181,191 @@ impl Settings {
     }
 
     pub fn load() -> Result<Self, String> {
-        println!("Loading settings");
-
-        // Use a proper config directory
-        let config_dir = if let Some(app_dir) = dirs::config_dir() {
-            app_dir.join("virus-scanner-app")
-        } else {
-            PathBuf::from("./config")
-        };
+        log::debug!("Loading settings");
 
-        let settings_path = config_dir.join("settings.json");
-        println!("Settings path: {}", settings_path.display());
+        let settings_path = config_dir().join("settings.json");
+        log::debug!("Settings path: {}", settings_path.display());
 
         if settings_path.exists() {
             match fs::read_to_string(&settings_path) {
                 Ok(content) => {
-                    match serde_json::from_str(&content) {
-                        Ok(settings) => {
-                            println!("Settings loaded successfully");
+                    match Self::parse(&content) {
+                        Ok(mut settings) => {
+                            if let Err(errors) = settings.validate() {
+                                for error in &errors {
+                                    log::warn!("Invalid setting {}: {}, using default", error.field, error.message);
+                                }
+                                settings.repair(&errors);
+                            }
+                            log::info!("Settings loaded successfully");
                             return Ok(settings);
                         },
                         Err(e) => {
-                            println!("Failed to parse settings: {}, using defaults", e);
+                            log::warn!("Failed to parse settings: {}, using defaults", e);
                         }
                     }
                 },
                 Err(e) => {
-                    println!("Failed to read settings file: {}, using defaults", e);
+                    log::warn!("Failed to read settings file: {}, using defaults", e);
                 }
             }
         } else {
-            println!("Settings file does not exist, using defaults");
+            log::info!("Settings file does not exist, using defaults");
         }
 
         // Return default settings if loading fails
         let default_settings = Self::default();
-        println!("Using default settings");
+        log::info!("Using default settings");
         Ok(default_settings)
     }
 
-    pub fn save(&self) -> Result<(), String> {
-        println!("Saving settings");
+    // For code that runs on every scan or event: only re-reads the file once it has changed,
+    // including when another process such as vscan wrote it
+    pub fn cached() -> Arc<Self> {
+        let modified = fs::metadata(config_dir().join("settings.json"))
+            .and_then(|metadata| metadata.modified())
+            .ok();
+
+        let mut cached = CACHED.lock().unwrap();
+        if let Some((seen, settings)) = cached.as_ref() {
+            if *seen == modified {
+                return settings.clone();
+            }
+        }
+        let settings = Arc::new(Self::load().unwrap_or_default());
+        *cached = Some((modified, settings.clone()));
+        settings
+    }
+
+    // Parse a settings file, carrying over values from the pre-sectioned layout
+    fn parse(content: &str) -> Result<Self, serde_json::Error> {
+        let value: serde_json::Value = serde_json::from_str(content)?;
+        let mut settings: Settings = serde_json::from_value(value.clone())?;
+
+        if value.get("scanner").is_none() || value.get("monitor").is_none() {
+            let legacy: LegacySettings = serde_json::from_value(value).unwrap_or_default();
+            if let Some(locations) = legacy.custom_scan_locations {
+                settings.monitor.monitored_paths = locations.into_iter().map(PathBuf::from).collect();
+            }
+            if let Some(filters) = legacy.file_type_filters {
+                settings.monitor.file_extensions = filters;
+            }
+            if let Some(threads) = legacy.background_scan_threads {
+                settings.scanner.max_concurrent_scans = threads as usize;
+            }
+        }
+
+        Ok(settings)
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if self.scan_history_limit == 0 {
+            errors.push(FieldError::new("scan_history_limit", "History limit must be positive"));
+        }
+        if self.auto_rescan_interval == Some(0) {
+            errors.push(FieldError::new("auto_rescan_interval", "Rescan interval must be at least one hour"));
+        }
+        if !["light", "dark", "system"].contains(&self.theme.as_str()) {
+            errors.push(FieldError::new("theme", format!("Unknown theme: {}", self.theme)));
+        }
+        if let Some(export_path) = &self.export_path {
+            if !Path::new(export_path).is_dir() {
+                errors.push(FieldError::new("export_path", format!("Directory does not exist: {}", export_path)));
+            }
+        }
+        self.quarantine_settings.validate(&mut errors);
+        self.scanner.validate(&mut errors);
+        self.monitor.validate(&mut errors);
+        self.relookup.validate(&mut errors);
+        self.delete.validate(&mut errors);
+        self.logging.validate(&mut errors);
+        self.notifications.validate(&mut errors);
+        self.siem.validate(&mut errors);
+        self.rest_api.validate(&mut errors);
+        self.clamd.validate(&mut errors);
+        for webhook in &self.webhooks {
+            webhook.validate(&mut errors);
+        }
 
-        // Use a proper config directory
-        let config_dir = if let Some(app_dir) = dirs::config_dir() {
-            app_dir.join("virus-scanner-app")
+        if errors.is_empty() {
+            Ok(())
         } else {
-            PathBuf::from("./config")
-        };
+            Err(errors)
+        }
+    }
+
+    // Reset the fields that failed validation so a bad value never blocks startup
+    fn repair(&mut self, errors: &[FieldError]) {
+        let defaults = Self::default();
+
+        for error in errors {
+            match error.field.as_str() {
+                "scan_history_limit" => self.scan_history_limit = defaults.scan_history_limit,
+                "auto_rescan_interval" => self.auto_rescan_interval = defaults.auto_rescan_interval,
+                "theme" => self.theme = defaults.theme.clone(),
+                "export_path" => self.export_path = None,
+                "quarantine_settings.auto_delete_after_days" => {
+                    self.quarantine_settings.auto_delete_after_days = defaults.quarantine_settings.auto_delete_after_days;
+                }
+                "quarantine_settings.quarantine_location" => self.quarantine_settings.quarantine_location = None,
+                "quarantine_settings.suspicious_threshold" => {
+                    self.quarantine_settings.suspicious_threshold = defaults.quarantine_settings.suspicious_threshold;
+                }
+                "quarantine_settings.undo_window_secs" => {
+                    self.quarantine_settings.undo_window_secs = defaults.quarantine_settings.undo_window_secs;
+                }
+                "scanner.scan_interval_secs" => self.scanner.scan_interval_secs = defaults.scanner.scan_interval_secs,
+                "scanner.max_concurrent_scans" => self.scanner.max_concurrent_scans = defaults.scanner.max_concurrent_scans,
+                "relookup.window_days" => self.relookup.window_days = defaults.relookup.window_days,
+                "relookup.interval_hours" => self.relookup.interval_hours = defaults.relookup.interval_hours,
+                "delete.overwrite_passes" => self.delete.overwrite_passes = defaults.delete.overwrite_passes,
+                "delete.allowed_roots" => self.delete.allowed_roots.retain(|root| root.is_absolute()),
+                field if field.starts_with("notifications.") => self.notifications = defaults.notifications.clone(),
+                field if field.starts_with("webhooks.") => {
+                    // Leave out a broken webhook rather than guess at what it should send
+                    let id = field["webhooks.".len()..].split('.').next().unwrap_or_default();
+                    self.webhooks.retain(|webhook| webhook.id != id);
+                }
+                // Keep the token but stay off until the address or token is fixed
+                "rest_api.listen" => {
+                    self.rest_api.listen = defaults.rest_api.listen.clone();
+                    self.rest_api.enabled = false;
+                }
+                "rest_api.token" => self.rest_api.enabled = false,
+                "rest_api.max_upload_bytes" => self.rest_api.max_upload_bytes = defaults.rest_api.max_upload_bytes,
+                "clamd.listen" => {
+                    self.clamd.listen = defaults.clamd.listen.clone();
+                    self.clamd.enabled = false;
+                }
+                "clamd.max_stream_bytes" => self.clamd.max_stream_bytes = defaults.clamd.max_stream_bytes,
+                "clamd.scan_roots" => self.clamd.scan_roots.retain(|root| root.is_absolute()),
+                field if field.starts_with("siem.") => self.siem = defaults.siem.clone(),
+                field if field.starts_with("logging.") => self.logging = defaults.logging.clone(),
+                field if field.starts_with("scanner.retry.") => self.scanner.retry = defaults.scanner.retry.clone(),
+                field if field.starts_with("scanner.throttle.") => self.scanner.throttle = defaults.scanner.throttle.clone(),
+                "monitor.min_file_size" | "monitor.max_file_size" => {
+                    self.monitor.min_file_size = defaults.monitor.min_file_size;
+                    self.monitor.max_file_size = defaults.monitor.max_file_size;
+                }
+                "monitor.monitored_paths" => self.monitor.monitored_paths.retain(|path| path.is_dir()),
+                "monitor.ignored_patterns" => {
+                    self.monitor.ignored_patterns.retain(|pattern| glob::Pattern::new(pattern).is_ok());
+                }
+                "monitor.file_extensions" => {
+                    self.monitor.file_extensions.retain(|ext| !ext.is_empty() && !ext.contains('.') && !ext.contains(std::path::MAIN_SEPARATOR));
+                }
+                _ => {}
+            }
+        }
+    }
 
-        println!("Config directory: {}", config_dir.display());
+    pub fn save(&self) -> Result<(), String> {
+        log::debug!("Saving settings");
+
+        let config_dir = config_dir();
+        log::debug!("Config directory: {}", config_dir.display());
 
         // Create the config directory if it doesn't exist
         fs::create_dir_all(&config_dir)
@@ -141,26 +381,84 @@ impl Settings {
         fs::write(&settings_path, content)
             .map_err(|e| format!("Failed to write settings file: {}", e))?;
 
-        println!("Settings saved successfully to: {}", settings_path.display());
+        // A save within the filesystem's timestamp granularity would otherwise go unnoticed
+        *CACHED.lock().unwrap() = None;
+
+        log::info!("Settings saved successfully to: {}", settings_path.display());
+        Ok(())
+    }
+
+    // Validate and persist in one step, the path every settings update should take
+    pub fn save_validated(&self) -> Result<(), SettingsError> {
+        self.validate().map_err(|errors| SettingsError::Invalid { errors })?;
+        self.save()?;
         Ok(())
     }
 }
 
+impl QuarantineSettings {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.auto_delete_after_days == Some(0) {
+            errors.push(FieldError::new("quarantine_settings.auto_delete_after_days", "Retention must be at least one day"));
+        }
+        if self.suspicious_threshold == Some(0) {
+            errors.push(FieldError::new("quarantine_settings.suspicious_threshold", "Suspicious threshold must be at least one detection"));
+        }
+        if !(1..=86_400).contains(&self.undo_window_secs) {
+            errors.push(FieldError::new("quarantine_settings.undo_window_secs", "Undo window must be between one second and one day"));
+        }
+        if let Some(location) = &self.quarantine_location {
+            if !Path::new(location).is_dir() {
+                errors.push(FieldError::new("quarantine_settings.quarantine_location", format!("Directory does not exist: {}", location)));
+            }
+        }
+    }
+}
+
 // Tauri commands for settings
 #[tauri::command]
 pub async fn get_settings(_state: tauri::State<'_, AppState>) -> Result<Settings, String> {
-    Ok(Settings::load()?)
+    Settings::load()
+}
+
+#[tauri::command]
+pub async fn update_settings(
+    settings: Settings,
+    state: tauri::State<'_, AppState>,
+    app_handle: tauri::AppHandle,
+) -> Result<(), SettingsError> {
+    let previous = Settings::load()?;
+    settings.save_validated()?;
+    crate::audit::record_settings_change(&previous, &settings);
+    crate::logger::reconfigure(settings.logging.clone());
+    state.scheduler.sync_builtin_jobs(&settings).await;
+    if previous.rest_api.enabled != settings.rest_api.enabled
+        || previous.rest_api.listen != settings.rest_api.listen
+        || previous.rest_api.token != settings.rest_api.token
+        || previous.rest_api.max_upload_bytes != settings.rest_api.max_upload_bytes
+    {
+        state.rest_api.apply(&Host::App(app_handle.clone()), &settings.rest_api).await;
+    }
+    if previous.clamd != settings.clamd {
+        state.clamd.apply(&Host::App(app_handle.clone()), &settings.clamd).await;
+    }
+    state.background_scanner.set_config(settings.scanner.clone()).await;
+    state.file_monitor.lock().await.apply_settings(&Host::App(app_handle), settings).await?;
+    state.status_indicator.refresh();
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn update_settings(settings: Settings, _state: tauri::State<'_, AppState>) -> Result<(), String> {
-    settings.save()
+pub async fn validate_settings(settings: Settings) -> Result<Vec<FieldError>, String> {
+    Ok(settings.validate().err().unwrap_or_default())
 }
 
 #[tauri::command]
 pub async fn reset_settings() -> Result<Settings, String> {
+    let previous = Settings::load()?;
     let default_settings = Settings::default();
     default_settings.save()?;
+    crate::audit::record_settings_change(&previous, &default_settings);
     Ok(default_settings)
 }
 
@@ -171,10 +469,13 @@ pub async fn get_quarantine_settings() -> Result<QuarantineSettings, String> {
 }
 
 #[tauri::command]
-pub async fn update_quarantine_settings(quarantine: QuarantineSettings, _state: tauri::State<'_, AppState>) -> Result<(), String> {
-    let mut settings = Settings::load()?;
+pub async fn update_quarantine_settings(quarantine: QuarantineSettings, _state: tauri::State<'_, AppState>) -> Result<(), SettingsError> {
+    let previous = Settings::load()?;
+    let mut settings = previous.clone();
     settings.quarantine_settings = quarantine;
-    settings.save()
+    settings.save_validated()?;
+    crate::audit::record_settings_change(&previous, &settings);
+    Ok(())
 }
 
 // Export settings and history
@@ -228,11 +529,119 @@ pub async fn import_data(
         .map_err(|e| format!("Failed to parse import data: {}", e))?;
 
     // Update settings
-    import_data.settings.save()?;
+    let previous = Settings::load()?;
+    import_data.settings.save_validated().map_err(|e| e.to_string())?;
+    crate::audit::record_settings_change(&previous, &import_data.settings);
 
     // Update scan history
     let mut history = app_state.scan_history.lock().await;
-    *history = import_data.scan_history;
+    crate::virus_total::update_history(&mut history, |history| *history = import_data.scan_history).await;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    // Default settings as JSON with the given pointers overwritten
+    fn settings_json(overrides: &[(&str, serde_json::Value)]) -> String {
+        let mut value = serde_json::to_value(Settings::default()).unwrap();
+        for (pointer, replacement) in overrides {
+            *value.pointer_mut(pointer).unwrap_or_else(|| panic!("no field at {}", pointer)) = replacement.clone();
+        }
+        value.to_string()
+    }
+
+    #[test]
+    fn migrates_an_old_flat_settings_file() {
+        let content = json!({
+            "api_key": "key",
+            "auto_scan_downloads": false,
+            "custom_scan_locations": ["/srv/shared", "/home/user/Desktop"],
+            "file_type_filters": ["exe", "elf"],
+            "background_scan_threads": 6,
+        });
+
+        let settings = Settings::parse(&content.to_string()).unwrap();
+        assert_eq!(settings.api_key, "key");
+        assert!(!settings.auto_scan_downloads);
+        assert_eq!(settings.monitor.monitored_paths, [PathBuf::from("/srv/shared"), PathBuf::from("/home/user/Desktop")]);
+        assert_eq!(settings.monitor.file_extensions, ["exe", "elf"]);
+        assert_eq!(settings.scanner.max_concurrent_scans, 6);
+    }
+
+    #[test]
+    fn sectioned_settings_win_over_leftover_flat_fields() {
+        let mut value: serde_json::Value = serde_json::from_str(&settings_json(&[
+            ("/scanner/max_concurrent_scans", json!(2)),
+        ])).unwrap();
+        value["background_scan_threads"] = json!(8);
+
+        let settings = Settings::parse(&value.to_string()).unwrap();
+        assert_eq!(settings.scanner.max_concurrent_scans, 2);
+    }
+
+    #[test]
+    fn repair_fixes_every_field_validate_reports() {
+        let watched = TempDir::new().unwrap();
+        let existing = watched.path().to_string_lossy().to_string();
+        let content = settings_json(&[
+            ("/scan_history_limit", json!(0)),
+            ("/auto_rescan_interval", json!(0)),
+            ("/theme", json!("neon")),
+            ("/export_path", json!("/nonexistent/export")),
+            ("/quarantine_settings/auto_delete_after_days", json!(0)),
+            ("/quarantine_settings/quarantine_location", json!("/nonexistent/vault")),
+            ("/quarantine_settings/suspicious_threshold", json!(0)),
+            ("/quarantine_settings/undo_window_secs", json!(0)),
+            ("/scanner/scan_interval_secs", json!(0)),
+            ("/scanner/max_concurrent_scans", json!(0)),
+            ("/scanner/retry/max_attempts", json!(0)),
+            ("/scanner/retry/jitter", json!(2.0)),
+            ("/scanner/throttle/max_cpu_percent", json!(0.0)),
+            ("/scanner/throttle/check_interval_secs", json!(0)),
+            ("/monitor/min_file_size", json!(10)),
+            ("/monitor/max_file_size", json!(0)),
+            ("/monitor/monitored_paths", json!([existing, "/nonexistent/watched"])),
+            ("/monitor/ignored_patterns", json!(["*.tmp", "[unclosed"])),
+            ("/monitor/file_extensions", json!(["exe", ".dll", ""])),
+            ("/relookup/window_days", json!(0)),
+            ("/relookup/interval_hours", json!(0)),
+            ("/delete/overwrite_passes", json!(9)),
+            ("/delete/allowed_roots", json!(["/srv", "relative"])),
+            ("/logging/level", json!("loud")),
+            ("/logging/max_files", json!(0)),
+            ("/notifications/max_per_hour", json!(0)),
+            ("/notifications/quiet_hours", json!({ "start": "late", "end": "07:00" })),
+            ("/siem/port", json!(0)),
+            ("/siem/facility", json!(30)),
+            ("/rest_api/enabled", json!(true)),
+            ("/rest_api/listen", json!("not an address")),
+            ("/rest_api/max_upload_bytes", json!(0)),
+            ("/clamd/listen", json!("not an address")),
+            ("/clamd/max_stream_bytes", json!(0)),
+            ("/clamd/scan_roots", json!(["/srv", "relative"])),
+            ("/webhooks", json!([
+                { "id": "good", "name": "Good", "url": "https://example.com/hook" },
+                { "id": "bad", "name": "Bad", "url": "ftp://example.com", "timeout_secs": 0 },
+            ])),
+        ]);
+
+        let mut settings = Settings::parse(&content).unwrap();
+        let errors = settings.validate().unwrap_err();
+        settings.repair(&errors);
+
+        assert_eq!(settings.validate(), Ok(()));
+        // Valid entries in partly broken lists are kept
+        assert_eq!(settings.monitor.monitored_paths, [PathBuf::from(&existing)]);
+        assert_eq!(settings.monitor.ignored_patterns, ["*.tmp"]);
+        assert_eq!(settings.monitor.file_extensions, ["exe"]);
+        assert_eq!(settings.delete.allowed_roots, [PathBuf::from("/srv")]);
+        assert_eq!(settings.clamd.scan_roots, [PathBuf::from("/srv")]);
+        assert_eq!(settings.webhooks.iter().map(|webhook| webhook.id.as_str()).collect::<Vec<_>>(), ["good"]);
+        assert!(!settings.rest_api.enabled);
+    }
+}
@@ -0,0 +1,566 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::Notify;
+use tokio::time::{sleep, timeout, Duration};
+use crate::quarantine::QuarantineItem;
+use crate::settings::{self, FieldError, Settings};
+use crate::virus_total::{ScanResult, ScanStatus};
+
+const VENDOR: &str = "VirusTotal Scanner";
+const PRODUCT: &str = "virus-scanner-app";
+// Private enterprise number placeholder reserved for documentation (RFC 5612)
+const SD_ID: &str = "scan@32473";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+// Wait before retrying after the collector could not be reached
+const RETRY_SECS: u64 = 30;
+
+// Serializes access to the spool file
+static SPOOL_LOCK: once_cell::sync::Lazy<std::sync::Mutex<()>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(()));
+
+static WAKE: once_cell::sync::Lazy<Notify> = once_cell::sync::Lazy::new(Notify::new);
+
+static LAST_ERROR: once_cell::sync::Lazy<std::sync::Mutex<Option<String>>> =
+    once_cell::sync::Lazy::new(|| std::sync::Mutex::new(None));
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SiemTransport {
+    Udp,
+    Tcp,
+    Tls,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SiemFormat {
+    // Plain RFC 5424 with the fields as structured data
+    Syslog,
+    Cef,
+    Leef,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiemConfig {
+    pub enabled: bool,
+    pub host: String,
+    pub port: u16,
+    pub transport: SiemTransport,
+    pub format: SiemFormat,
+    // Syslog facility, 13 is "log audit"
+    pub facility: u8,
+    pub accept_invalid_certs: bool,
+    // Oldest spooled events are dropped beyond this
+    pub spool_limit: usize,
+}
+
+impl Default for SiemConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            host: String::new(),
+            port: 514,
+            transport: SiemTransport::Udp,
+            format: SiemFormat::Syslog,
+            facility: 13,
+            accept_invalid_certs: false,
+            spool_limit: 10_000,
+        }
+    }
+}
+
+impl SiemConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.enabled && self.host.trim().is_empty() {
+            errors.push(FieldError::new("siem.host", "A collector host is required"));
+        }
+        if self.port == 0 {
+            errors.push(FieldError::new("siem.port", "Port must be positive"));
+        }
+        if self.facility > 23 {
+            errors.push(FieldError::new("siem.facility", "Facility must be between 0 and 23"));
+        }
+        if self.spool_limit == 0 {
+            errors.push(FieldError::new("siem.spool_limit", "The spool must hold at least one event"));
+        }
+    }
+}
+
+// A security event before it is formatted for the collector
+#[derive(Debug, Clone)]
+pub struct SecurityEvent {
+    pub event_id: &'static str,
+    pub name: String,
+    // 0 (informational) to 10 (very high), as CEF and LEEF expect
+    pub severity: u8,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    // Fields by their CEF key, converted for the other formats
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl SecurityEvent {
+    pub fn from_scan(result: &ScanResult) -> Self {
+        let severity = match result.status {
+            ScanStatus::Malicious => 10,
+            ScanStatus::Suspicious => 6,
+            ScanStatus::Clean => 1,
+            _ => 3,
+        };
+
+        let mut fields = vec![
+            ("fname", result.file_name.clone()),
+            ("filePath", result.file_path.clone()),
+            ("fsize", result.file_size.to_string()),
+            ("fileHash", result.file_hash.clone()),
+            ("act", format!("{:?}", result.status).to_lowercase()),
+        ];
+        if let Some(detections) = result.detection_count {
+            fields.push(("cn1", detections.to_string()));
+        }
+        if let Some(total) = result.total_engines {
+            fields.push(("cn2", total.to_string()));
+        }
+        if let Some(permalink) = &result.permalink {
+            fields.push(("request", permalink.clone()));
+        }
+
+        Self {
+            event_id: "scan_verdict",
+            name: format!("File scanned {:?}", result.status),
+            severity,
+            timestamp: result.scan_date,
+            fields,
+        }
+    }
+
+    pub fn from_quarantine(action: &'static str, item: &QuarantineItem) -> Self {
+        let mut fields = vec![
+            ("fname", item.file_name.clone()),
+            ("filePath", item.original_path.clone()),
+            ("fsize", item.file_size.to_string()),
+            ("fileHash", item.hashes.sha256.clone()),
+            ("act", action.to_string()),
+            ("externalId", item.id.clone()),
+        ];
+        if let Some(detections) = item.detection_count {
+            fields.push(("cn1", detections.to_string()));
+        }
+
+        Self {
+            event_id: action,
+            name: format!("File {}", action.replace('_', " ")),
+            severity: 5,
+            timestamp: chrono::Utc::now(),
+            fields,
+        }
+    }
+
+    // Label for each custom CEF field, and the name used outside CEF
+    fn label(key: &str) -> Option<&'static str> {
+        match key {
+            "cn1" => Some("detections"),
+            "cn2" => Some("totalEngines"),
+            _ => None,
+        }
+    }
+
+    fn syslog_severity(&self) -> u8 {
+        match self.severity {
+            9..=10 => 2,
+            6..=8 => 4,
+            4..=5 => 5,
+            _ => 6,
+        }
+    }
+}
+
+// Format an event as an RFC 5424 message
+pub fn format_event(event: &SecurityEvent, config: &SiemConfig) -> String {
+    let host = gethostname::gethostname().to_string_lossy().to_string();
+    format_message(event, config, &host, std::process::id())
+}
+
+fn format_message(event: &SecurityEvent, config: &SiemConfig, host: &str, proc_id: u32) -> String {
+    let pri = config.facility as u32 * 8 + event.syslog_severity() as u32;
+    let timestamp = event.timestamp.to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+    let header = format!(
+        "<{}>1 {} {} {} {} {}",
+        pri, timestamp, header_field(host), PRODUCT, proc_id, event.event_id,
+    );
+
+    match config.format {
+        SiemFormat::Syslog => {
+            let params: Vec<String> = event.fields.iter()
+                .map(|(key, value)| format!("{}=\"{}\"", SecurityEvent::label(key).unwrap_or(key), escape_sd(value)))
+                .collect();
+            format!("{} [{} {}] {}", header, SD_ID, params.join(" "), event.name)
+        }
+        SiemFormat::Cef => format!("{} - {}", header, format_cef(event, host)),
+        SiemFormat::Leef => format!("{} - {}", header, format_leef(event)),
+    }
+}
+
+fn format_cef(event: &SecurityEvent, host: &str) -> String {
+    let mut extension = vec![
+        format!("rt={}", event.timestamp.timestamp_millis()),
+        format!("dvchost={}", escape_cef_value(host)),
+    ];
+    for (key, value) in &event.fields {
+        extension.push(format!("{}={}", key, escape_cef_value(value)));
+        if let Some(label) = SecurityEvent::label(key) {
+            extension.push(format!("{}Label={}", key, label));
+        }
+    }
+
+    format!(
+        "CEF:0|{}|{}|{}|{}|{}|{}|{}",
+        escape_cef_header(VENDOR),
+        escape_cef_header(PRODUCT),
+        env!("CARGO_PKG_VERSION"),
+        event.event_id,
+        escape_cef_header(&event.name),
+        event.severity,
+        extension.join(" "),
+    )
+}
+
+fn format_leef(event: &SecurityEvent) -> String {
+    let mut attributes = vec![
+        format!("devTime={}", event.timestamp.format("%b %d %Y %H:%M:%S")),
+        format!("sev={}", event.severity),
+    ];
+    for (key, value) in &event.fields {
+        attributes.push(format!("{}={}", SecurityEvent::label(key).unwrap_or(key), escape_leef_value(value)));
+    }
+
+    format!(
+        "LEEF:1.0|{}|{}|{}|{}|{}",
+        VENDOR,
+        PRODUCT,
+        env!("CARGO_PKG_VERSION"),
+        event.event_id,
+        attributes.join("\t"),
+    )
+}
+
+// Header fields are printable ASCII without spaces, or "-" when empty
+fn header_field(value: &str) -> String {
+    let cleaned: String = value.chars().filter(|c| c.is_ascii_graphic()).take(255).collect();
+    if cleaned.is_empty() { "-".to_string() } else { cleaned }
+}
+
+fn escape_sd(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]")
+}
+
+// Line breaks are only representable in the extension, so the header gets spaces instead
+fn escape_cef_header(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('|', "\\|").replace(['\n', '\r'], " ")
+}
+
+fn escape_cef_value(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('=', "\\=").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn escape_leef_value(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+fn spool_path() -> PathBuf {
+    settings::data_dir().join("siem_spool.jsonl")
+}
+
+fn read_spool() -> Vec<String> {
+    fs::read_to_string(spool_path())
+        .map(|content| {
+            content.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn write_spool(messages: &[String]) -> Result<(), String> {
+    let path = spool_path();
+    let tmp_path = path.with_extension("jsonl.tmp");
+
+    let mut content = String::new();
+    for message in messages {
+        content.push_str(&serde_json::to_string(message).unwrap_or_default());
+        content.push('\n');
+    }
+    fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write SIEM spool: {}", e))?;
+    fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to replace SIEM spool: {}", e))
+}
+
+// Queue an event for the forwarder. Events go through the spool so none are lost
+// while the collector is down or the app restarts. This appends to a file, so async
+// callers go through `scan_verdict`, which runs it on the blocking pool.
+pub fn forward(config: &SiemConfig, event: SecurityEvent) {
+    if !config.enabled {
+        return;
+    }
+
+    let message = format_event(&event, config);
+    let _guard = SPOOL_LOCK.lock().unwrap();
+    let result = fs::create_dir_all(settings::data_dir())
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(spool_path()))
+        .and_then(|mut file| writeln!(file, "{}", serde_json::to_string(&message).unwrap_or_default()));
+    if let Err(e) = result {
+        log::error!("Failed to spool SIEM event: {}", e);
+        return;
+    }
+    WAKE.notify_one();
+}
+
+pub async fn scan_verdict(result: &ScanResult) {
+    let config = Settings::cached().siem.clone();
+    if !config.enabled {
+        return;
+    }
+    let event = SecurityEvent::from_scan(result);
+    if let Err(e) = tokio::task::spawn_blocking(move || forward(&config, event)).await {
+        log::error!("Failed to spool SIEM event: {}", e);
+    }
+}
+
+// Called from the vault's own blocking file operations
+pub fn quarantine_action(action: &'static str, item: &QuarantineItem) {
+    forward(&Settings::cached().siem, SecurityEvent::from_quarantine(action, item));
+}
+
+// Drain the spool to the collector whenever events arrive, retrying while it is unreachable
+pub fn start_forwarder() {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let config = Settings::load().unwrap_or_default().siem;
+            let delay = if config.enabled {
+                match flush(&config).await {
+                    Ok(()) => None,
+                    Err(e) => {
+                        log::warn!("SIEM collector unreachable: {}", e);
+                        *LAST_ERROR.lock().unwrap() = Some(e);
+                        Some(Duration::from_secs(RETRY_SECS))
+                    }
+                }
+            } else {
+                None
+            };
+
+            match delay {
+                Some(delay) => {
+                    tokio::select! {
+                        _ = sleep(delay) => {}
+                        _ = WAKE.notified() => {}
+                    }
+                }
+                None => WAKE.notified().await,
+            }
+        }
+    });
+}
+
+async fn flush(config: &SiemConfig) -> Result<(), String> {
+    let messages = {
+        let _guard = SPOOL_LOCK.lock().unwrap();
+        let mut messages = read_spool();
+        if messages.len() > config.spool_limit {
+            let dropped = messages.len() - config.spool_limit;
+            log::warn!("SIEM spool full, dropping {} oldest events", dropped);
+            messages.drain(..dropped);
+            write_spool(&messages)?;
+        }
+        messages
+    };
+    if messages.is_empty() {
+        return Ok(());
+    }
+
+    let sent = send(config, &messages).await;
+
+    // Drop what was sent; events spooled meanwhile stay for the next round
+    let sent_count = match &sent {
+        Ok(count) | Err((count, _)) => *count,
+    };
+    let _guard = SPOOL_LOCK.lock().unwrap();
+    let remaining: Vec<String> = read_spool().into_iter().skip(sent_count).collect();
+    write_spool(&remaining)?;
+
+    match sent {
+        Ok(count) => {
+            log::debug!("Forwarded {} events to SIEM", count);
+            *LAST_ERROR.lock().unwrap() = None;
+            Ok(())
+        }
+        Err((_, e)) => Err(e),
+    }
+}
+
+// Send messages in order. Returns how many were sent, also on failure.
+async fn send(config: &SiemConfig, messages: &[String]) -> Result<usize, (usize, String)> {
+    let address = (config.host.as_str(), config.port);
+    match config.transport {
+        SiemTransport::Udp => {
+            let socket = UdpSocket::bind("0.0.0.0:0").await
+                .map_err(|e| (0, format!("Failed to open UDP socket: {}", e)))?;
+            socket.connect(address).await
+                .map_err(|e| (0, format!("Failed to resolve collector: {}", e)))?;
+            for (index, message) in messages.iter().enumerate() {
+                socket.send(message.as_bytes()).await
+                    .map_err(|e| (index, format!("Failed to send syslog datagram: {}", e)))?;
+            }
+            Ok(messages.len())
+        }
+        SiemTransport::Tcp => {
+            let mut stream = connect(config).await.map_err(|e| (0, e))?;
+            write_framed(&mut stream, messages).await
+        }
+        SiemTransport::Tls => {
+            let stream = connect(config).await.map_err(|e| (0, e))?;
+            let connector = native_tls::TlsConnector::builder()
+                .danger_accept_invalid_certs(config.accept_invalid_certs)
+                .build()
+                .map_err(|e| (0, format!("Failed to set up TLS: {}", e)))?;
+            let mut stream = timeout(CONNECT_TIMEOUT, tokio_native_tls::TlsConnector::from(connector).connect(&config.host, stream)).await
+                .map_err(|_| (0, "TLS handshake timed out".to_string()))?
+                .map_err(|e| (0, format!("TLS handshake failed: {}", e)))?;
+            write_framed(&mut stream, messages).await
+        }
+    }
+}
+
+async fn connect(config: &SiemConfig) -> Result<TcpStream, String> {
+    timeout(CONNECT_TIMEOUT, TcpStream::connect((config.host.as_str(), config.port))).await
+        .map_err(|_| "Connection to collector timed out".to_string())?
+        .map_err(|e| format!("Failed to connect to collector: {}", e))
+}
+
+// Octet-counting framing as in RFC 5425 and RFC 6587
+async fn write_framed<S: AsyncWriteExt + Unpin>(stream: &mut S, messages: &[String]) -> Result<usize, (usize, String)> {
+    for (index, message) in messages.iter().enumerate() {
+        let frame = format!("{} {}", message.len(), message);
+        stream.write_all(frame.as_bytes()).await
+            .map_err(|e| (index, format!("Failed to send syslog message: {}", e)))?;
+    }
+    // Resend everything when the final flush fails; a duplicate beats a lost event
+    stream.flush().await
+        .map_err(|e| (0, format!("Failed to send syslog messages: {}", e)))?;
+    let _ = stream.shutdown().await;
+    Ok(messages.len())
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SiemStatus {
+    pub enabled: bool,
+    pub spooled: usize,
+    pub last_error: Option<String>,
+}
+
+// Tauri commands for SIEM forwarding
+#[tauri::command]
+pub async fn get_siem_status() -> Result<SiemStatus, String> {
+    let config = Settings::load()?.siem;
+    let spooled = {
+        let _guard = SPOOL_LOCK.lock().unwrap();
+        read_spool().len()
+    };
+    Ok(SiemStatus {
+        enabled: config.enabled,
+        spooled,
+        last_error: LAST_ERROR.lock().unwrap().clone(),
+    })
+}
+
+// Send one test event straight to the collector, bypassing the spool
+#[tauri::command]
+pub async fn test_siem_connection() -> Result<String, String> {
+    let config = Settings::load()?.siem;
+    let event = SecurityEvent {
+        event_id: "test",
+        name: "Test event".to_string(),
+        severity: 0,
+        timestamp: chrono::Utc::now(),
+        fields: vec![("act", "test".to_string())],
+    };
+    let message = format_event(&event, &config);
+    send(&config, std::slice::from_ref(&message)).await.map_err(|(_, e)| e)?;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "<106>1 2024-06-01T12:34:56.789Z myhost virus-scanner-app 42 scan_verdict";
+
+    // Every character one of the formats has to escape
+    fn event(name: &str) -> SecurityEvent {
+        SecurityEvent {
+            event_id: "scan_verdict",
+            name: name.to_string(),
+            severity: 10,
+            timestamp: "2024-06-01T12:34:56.789Z".parse().unwrap(),
+            fields: vec![
+                ("fname", "a|b=c\\d]e\"f\ng\th".to_string()),
+                ("cn1", "5".to_string()),
+            ],
+        }
+    }
+
+    fn format(event: &SecurityEvent, format: SiemFormat) -> String {
+        let config = SiemConfig { format, ..SiemConfig::default() };
+        format_message(event, &config, "my host", 42)
+    }
+
+    #[test]
+    fn syslog_escapes_structured_data_values() {
+        assert_eq!(
+            format(&event("File scanned Malicious"), SiemFormat::Syslog),
+            format!(
+                "{} [scan@32473 fname=\"a|b=c\\\\d\\]e\\\"f\ng\th\" detections=\"5\"] File scanned Malicious",
+                HEADER,
+            ),
+        );
+    }
+
+    #[test]
+    fn cef_escapes_header_and_extension() {
+        assert_eq!(
+            format(&event("Bad|name\\x\ny"), SiemFormat::Cef),
+            format!(
+                "{} - CEF:0|VirusTotal Scanner|virus-scanner-app|{}|scan_verdict|Bad\\|name\\\\x y|10|\
+                 rt=1717245296789 dvchost=my host fname=a|b\\=c\\\\d]e\"f\\ng\th cn1=5 cn1Label=detections",
+                HEADER,
+                env!("CARGO_PKG_VERSION"),
+            ),
+        );
+    }
+
+    #[test]
+    fn leef_keeps_attributes_on_one_tab_separated_line() {
+        assert_eq!(
+            format(&event("File scanned Malicious"), SiemFormat::Leef),
+            format!(
+                "{} - LEEF:1.0|VirusTotal Scanner|virus-scanner-app|{}|scan_verdict|\
+                 devTime=Jun 01 2024 12:34:56\tsev=10\tfname=a|b=c\\d]e\"f g h\tdetections=5",
+                HEADER,
+                env!("CARGO_PKG_VERSION"),
+            ),
+        );
+    }
+
+    #[test]
+    fn priority_combines_facility_and_severity() {
+        let config = SiemConfig { facility: 4, ..SiemConfig::default() };
+        let mut event = event("Informational");
+        event.severity = 1;
+        assert!(format_message(&event, &config, "host", 1).starts_with("<38>1 "));
+    }
+}
@@ -0,0 +1,238 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{Mutex, Notify, RwLock};
+use tokio::time::{sleep, Duration};
+use crate::throttle::ThrottleState;
+use crate::tray::{self, TrayContent};
+use crate::settings;
+use crate::virus_total::ScanStatus;
+use crate::AppState;
+use crate::error_handler::{ErrorCategory, HealthStatus, Subsystem};
+
+// Picks up changes nobody announced, such as a flagged file removed outside the app
+const REFRESH_SECS: u64 = 5;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ProtectionStatus {
+    Protected,
+    Scanning { queued: usize },
+    Paused,
+    // API quota exhausted or a subsystem failing
+    Degraded { reasons: Vec<String> },
+    // Flagged files still present where they were found and not yet acknowledged
+    ThreatFound { files: Vec<String> },
+}
+
+impl ProtectionStatus {
+    pub fn description(&self) -> String {
+        match self {
+            ProtectionStatus::Protected => "Protected".to_string(),
+            ProtectionStatus::Scanning { queued } => format!("Scanning ({} queued)", queued),
+            ProtectionStatus::Paused => "Protection paused".to_string(),
+            ProtectionStatus::Degraded { reasons } => match reasons.first() {
+                Some(reason) => format!("Degraded: {}", reason),
+                None => "Degraded".to_string(),
+            },
+            ProtectionStatus::ThreatFound { files } if files.len() == 1 => "Threat found".to_string(),
+            ProtectionStatus::ThreatFound { files } => format!("{} threats found", files.len()),
+        }
+    }
+}
+
+// Derives the overall protection status and mirrors it to the tray and the frontend
+#[derive(Clone)]
+pub struct StatusIndicator {
+    current: Arc<RwLock<ProtectionStatus>>,
+    // What the tray currently shows
+    tray: Arc<Mutex<Option<TrayContent>>>,
+    changed: Arc<Notify>,
+    // Hash of the detection the user acknowledged, by file path. A new detection with
+    // different content at the same path is raised again.
+    acknowledged: Arc<std::sync::Mutex<BTreeMap<String, String>>>,
+}
+
+impl Default for StatusIndicator {
+    fn default() -> Self {
+        Self {
+            current: Arc::new(RwLock::new(ProtectionStatus::Protected)),
+            tray: Arc::new(Mutex::new(None)),
+            changed: Arc::new(Notify::new()),
+            acknowledged: Arc::new(std::sync::Mutex::new(load_acknowledged())),
+        }
+    }
+}
+
+fn acknowledged_path() -> PathBuf {
+    settings::data_dir().join("acknowledged_threats.json")
+}
+
+fn load_acknowledged() -> BTreeMap<String, String> {
+    match std::fs::read_to_string(acknowledged_path()) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Failed to parse acknowledged threats: {}", e);
+            BTreeMap::new()
+        }),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+fn save_acknowledged(acknowledged: &BTreeMap<String, String>) -> Result<(), String> {
+    let path = acknowledged_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let content = serde_json::to_string_pretty(acknowledged)
+        .map_err(|e| format!("Failed to serialize acknowledged threats: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write acknowledged threats: {}", e))?;
+    std::fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to replace acknowledged threats: {}", e))
+}
+
+impl StatusIndicator {
+    pub async fn current(&self) -> ProtectionStatus {
+        self.current.read().await.clone()
+    }
+
+    // Install the tray icon, then keep it and the frontend up to date
+    pub async fn start(&self, app_handle: AppHandle) -> tauri::Result<()> {
+        let state = app_handle.state::<AppState>();
+        let status = compute(&state).await;
+        let content = TrayContent::collect(&state, status.clone()).await;
+        tray::create_tray(&app_handle, &content)?;
+        *self.current.write().await = status;
+        *self.tray.lock().await = Some(content);
+
+        let indicator = self.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = indicator.changed.notified() => {}
+                    _ = sleep(Duration::from_secs(REFRESH_SECS)) => {}
+                }
+                indicator.update(&app_handle).await;
+            }
+        });
+        Ok(())
+    }
+
+    // Ask for the status to be recomputed now rather than at the next refresh
+    pub fn refresh(&self) {
+        self.changed.notify_one();
+    }
+
+    // Stop reporting the current detections for these files until they are flagged again
+    fn acknowledge(&self, detections: HashMap<String, String>) -> Result<(), String> {
+        let mut acknowledged = self.acknowledged.lock().unwrap();
+        acknowledged.extend(detections);
+        save_acknowledged(&acknowledged)?;
+        drop(acknowledged);
+        self.refresh();
+        Ok(())
+    }
+
+    async fn update(&self, app_handle: &AppHandle) {
+        let state = app_handle.state::<AppState>();
+        let status = compute(&state).await;
+
+        let mut current = self.current.write().await;
+        if *current != status {
+            log::info!("Protection status: {}", status.description());
+            *current = status.clone();
+            if let Err(e) = app_handle.emit("protection-status", &status) {
+                log::error!("Failed to emit protection-status event: {}", e);
+            }
+        }
+        drop(current);
+
+        // The menu also lists recent results and the monitoring state, so it can change on its own
+        let content = TrayContent::collect(&state, status).await;
+        let mut shown = self.tray.lock().await;
+        if shown.as_ref() == Some(&content) {
+            return;
+        }
+        match tray::update_tray(app_handle, &content) {
+            Ok(()) => *shown = Some(content),
+            Err(e) => log::error!("Failed to update tray: {}", e),
+        }
+    }
+}
+
+// The most pressing condition wins: threats, then failures, then a pause, then work in progress
+pub async fn compute(state: &AppState) -> ProtectionStatus {
+    let files: BTreeSet<String> = unresolved_threats(state).await.into_keys().collect();
+    if !files.is_empty() {
+        return ProtectionStatus::ThreatFound { files: files.into_iter().collect() };
+    }
+
+    let error_handler = state.error_handler.lock().await;
+    let mut reasons = Vec::new();
+    // New files go unscanned until the quota resets, so say so first
+    if error_handler.active_errors().iter().any(|record| record.category == ErrorCategory::QuotaExceeded) {
+        reasons.push("API quota exhausted".to_string());
+    }
+    // A failing webhook receiver does not leave this machine any less protected
+    reasons.extend(error_handler.health().into_iter()
+        .filter(|health| health.subsystem != Subsystem::Webhooks)
+        .filter(|health| health.status != HealthStatus::Healthy)
+        .map(|health| match health.last_error {
+            Some(error) => format!("{:?}: {}", health.subsystem, error),
+            None => format!("{:?} errors", health.subsystem),
+        }));
+    drop(error_handler);
+    if !reasons.is_empty() {
+        return ProtectionStatus::Degraded { reasons };
+    }
+
+    // Once set up, a stopped monitor means new downloads go unchecked
+    let monitor_paused = *state.is_setup_complete.lock().await
+        && !state.file_monitor.lock().await.is_monitoring();
+    let scanner = state.background_scanner.status().await;
+    if monitor_paused || matches!(scanner.throttle.state, ThrottleState::Paused) {
+        return ProtectionStatus::Paused;
+    }
+    if scanner.queued + scanner.active > 0 {
+        return ProtectionStatus::Scanning { queued: scanner.queued };
+    }
+
+    ProtectionStatus::Protected
+}
+
+// Files whose latest scan flagged them, that are still on disk and have not been
+// acknowledged, with the hash of the flagged content. A later clean scan resolves a detection.
+async fn unresolved_threats(state: &AppState) -> HashMap<String, String> {
+    let mut latest = HashMap::new();
+    for result in state.scan_history.lock().await.iter() {
+        latest.insert(result.file_path.clone(), (result.status.clone(), result.file_hash.clone()));
+    }
+
+    let acknowledged = state.status_indicator.acknowledged.lock().unwrap().clone();
+    latest.into_iter()
+        .filter(|(_, (status, _))| matches!(status, ScanStatus::Malicious | ScanStatus::Suspicious))
+        .filter(|(path, (_, hash))| acknowledged.get(path) != Some(hash))
+        .filter(|(path, _)| Path::new(path).is_file())
+        .map(|(path, (_, hash))| (path, hash))
+        .collect()
+}
+
+// Tauri commands for the protection status
+#[tauri::command]
+pub async fn get_protection_status(state: tauri::State<'_, AppState>) -> Result<ProtectionStatus, String> {
+    Ok(state.status_indicator.current().await)
+}
+
+// Acknowledge the given flagged files, or every unresolved detection when none are passed
+#[tauri::command]
+pub async fn acknowledge_threats(files: Option<Vec<String>>, state: tauri::State<'_, AppState>) -> Result<(), String> {
+    let mut detections = unresolved_threats(&state).await;
+    if let Some(files) = files {
+        detections.retain(|path, _| files.contains(path));
+    }
+    state.status_indicator.acknowledge(detections)
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use crate::settings::FieldError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThrottleConfig {
+    pub enabled: bool,
+    // One-minute load average divided by the number of CPUs
+    pub max_load_per_cpu: f64,
+    pub max_cpu_percent: f64,
+    // Share of wall time the busiest disk spent doing I/O
+    pub max_disk_busy_percent: f64,
+    pub pause_on_battery: bool,
+    pub check_interval_secs: u64,
+    #[serde(skip, default = "default_proc_root")]
+    pub proc_root: PathBuf,
+    #[serde(skip, default = "default_sysfs_root")]
+    pub sysfs_root: PathBuf,
+}
+
+fn default_proc_root() -> PathBuf {
+    PathBuf::from("/proc")
+}
+
+fn default_sysfs_root() -> PathBuf {
+    PathBuf::from("/sys")
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_load_per_cpu: 0.8,
+            max_cpu_percent: 75.0,
+            max_disk_busy_percent: 80.0,
+            pause_on_battery: true,
+            check_interval_secs: 5,
+            proc_root: default_proc_root(),
+            sysfs_root: default_sysfs_root(),
+        }
+    }
+}
+
+impl ThrottleConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        if self.max_load_per_cpu <= 0.0 {
+            errors.push(FieldError::new("scanner.throttle.max_load_per_cpu", "Load threshold must be positive"));
+        }
+        if !(0.0..=100.0).contains(&self.max_cpu_percent) || self.max_cpu_percent == 0.0 {
+            errors.push(FieldError::new("scanner.throttle.max_cpu_percent", "CPU threshold must be between 1 and 100 percent"));
+        }
+        if !(0.0..=100.0).contains(&self.max_disk_busy_percent) || self.max_disk_busy_percent == 0.0 {
+            errors.push(FieldError::new("scanner.throttle.max_disk_busy_percent", "Disk threshold must be between 1 and 100 percent"));
+        }
+        if self.check_interval_secs == 0 {
+            errors.push(FieldError::new("scanner.throttle.check_interval_secs", "Check interval must be positive"));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub enum ThrottleReason {
+    HighLoad,
+    HighCpu,
+    HighDiskIo,
+    OnBattery,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ThrottleState {
+    Running,
+    Paused,
+    Throttled { reasons: Vec<ThrottleReason> },
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceSample {
+    pub load_per_cpu: Option<f64>,
+    pub cpu_percent: Option<f64>,
+    pub disk_busy_percent: Option<f64>,
+    pub on_battery: bool,
+}
+
+impl ResourceSample {
+    pub fn throttle_reasons(&self, config: &ThrottleConfig) -> Vec<ThrottleReason> {
+        let mut reasons = Vec::new();
+        if self.load_per_cpu.is_some_and(|load| load > config.max_load_per_cpu) {
+            reasons.push(ThrottleReason::HighLoad);
+        }
+        if self.cpu_percent.is_some_and(|cpu| cpu > config.max_cpu_percent) {
+            reasons.push(ThrottleReason::HighCpu);
+        }
+        if self.disk_busy_percent.is_some_and(|busy| busy > config.max_disk_busy_percent) {
+            reasons.push(ThrottleReason::HighDiskIo);
+        }
+        if config.pause_on_battery && self.on_battery {
+            reasons.push(ThrottleReason::OnBattery);
+        }
+        reasons
+    }
+}
+
+// Samples /proc and sysfs. CPU and disk figures are rates, so the first sample
+// only primes the counters and reports None for them.
+#[derive(Default)]
+pub struct ResourceMonitor {
+    last_cpu: Option<(u64, u64)>,
+    last_disk: Option<(Instant, HashMap<String, u64>)>,
+}
+
+impl ResourceMonitor {
+    pub fn sample(&mut self, config: &ThrottleConfig) -> ResourceSample {
+        let cpus = num_cpus::get().max(1) as f64;
+
+        let cpu_percent = read_cpu_times(&config.proc_root).and_then(|(idle, total)| {
+            let percent = self.last_cpu.and_then(|(last_idle, last_total)| {
+                let total_delta = total.checked_sub(last_total)?;
+                let idle_delta = idle.checked_sub(last_idle)?;
+                (total_delta > 0).then(|| 100.0 * (total_delta - idle_delta.min(total_delta)) as f64 / total_delta as f64)
+            });
+            self.last_cpu = Some((idle, total));
+            percent
+        });
+
+        let disk_busy_percent = read_disk_io_ticks(&config.proc_root, &config.sysfs_root).and_then(|ticks| {
+            let now = Instant::now();
+            let percent = self.last_disk.as_ref().and_then(|(last_time, last_ticks)| {
+                let elapsed_ms = now.duration_since(*last_time).as_millis() as f64;
+                if elapsed_ms <= 0.0 {
+                    return None;
+                }
+                ticks.iter()
+                    .filter_map(|(device, busy)| {
+                        let delta = busy.checked_sub(*last_ticks.get(device)?)?;
+                        Some((100.0 * delta as f64 / elapsed_ms).min(100.0))
+                    })
+                    .reduce(f64::max)
+            });
+            self.last_disk = Some((now, ticks));
+            percent
+        });
+
+        ResourceSample {
+            load_per_cpu: read_load_average(&config.proc_root).map(|load| load / cpus),
+            cpu_percent,
+            disk_busy_percent,
+            on_battery: on_battery(&config.sysfs_root),
+        }
+    }
+}
+
+// One-minute load average from <proc>/loadavg
+pub fn read_load_average(proc_root: &Path) -> Option<f64> {
+    fs::read_to_string(proc_root.join("loadavg")).ok()?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+// Idle and total jiffies from the aggregate cpu line of <proc>/stat
+fn read_cpu_times(proc_root: &Path) -> Option<(u64, u64)> {
+    let content = fs::read_to_string(proc_root.join("stat")).ok()?;
+    let line = content.lines().find(|line| line.starts_with("cpu "))?;
+    let values: Vec<u64> = line.split_whitespace()
+        .skip(1)
+        .take(8)
+        .filter_map(|value| value.parse().ok())
+        .collect();
+    if values.len() < 5 {
+        return None;
+    }
+
+    // idle + iowait
+    let idle = values[3] + values[4];
+    Some((idle, values.iter().sum()))
+}
+
+// Milliseconds spent doing I/O per whole disk from <proc>/diskstats. Partitions are
+// skipped by checking for the device under <sys>/block.
+fn read_disk_io_ticks(proc_root: &Path, sysfs_root: &Path) -> Option<HashMap<String, u64>> {
+    let content = fs::read_to_string(proc_root.join("diskstats")).ok()?;
+    let ticks = content.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let name = *fields.get(2)?;
+            if name.starts_with("loop") || name.starts_with("ram") || !sysfs_root.join("block").join(name).exists() {
+                return None;
+            }
+            Some((name.to_string(), fields.get(12)?.parse().ok()?))
+        })
+        .collect();
+    Some(ticks)
+}
+
+// On battery when no mains supply is online and a battery is discharging
+pub fn on_battery(sysfs_root: &Path) -> bool {
+    let Ok(supplies) = fs::read_dir(sysfs_root.join("class/power_supply")) else {
+        return false;
+    };
+
+    let read = |dir: &Path, name: &str| fs::read_to_string(dir.join(name))
+        .map(|value| value.trim().to_string())
+        .unwrap_or_default();
+
+    let mut discharging = false;
+    for supply in supplies.flatten() {
+        let dir = supply.path();
+        match read(&dir, "type").as_str() {
+            "Mains" | "USB" if read(&dir, "online") == "1" => return false,
+            "Battery" if read(&dir, "status") == "Discharging" => discharging = true,
+            _ => {}
+        }
+    }
+    discharging
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    struct Fixture {
+        proc_root: TempDir,
+        sysfs_root: TempDir,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self {
+                proc_root: TempDir::new().unwrap(),
+                sysfs_root: TempDir::new().unwrap(),
+            }
+        }
+
+        fn config(&self) -> ThrottleConfig {
+            ThrottleConfig {
+                proc_root: self.proc_root.path().to_path_buf(),
+                sysfs_root: self.sysfs_root.path().to_path_buf(),
+                ..ThrottleConfig::default()
+            }
+        }
+
+        fn write_proc(&self, name: &str, content: &str) {
+            fs::write(self.proc_root.path().join(name), content).unwrap();
+        }
+
+        fn power_supply(&self, name: &str, attributes: &[(&str, &str)]) {
+            let dir = self.sysfs_root.path().join("class/power_supply").join(name);
+            fs::create_dir_all(&dir).unwrap();
+            for (attribute, value) in attributes {
+                fs::write(dir.join(attribute), format!("{}\n", value)).unwrap();
+            }
+        }
+
+        fn block_device(&self, name: &str) {
+            fs::create_dir_all(self.sysfs_root.path().join("block").join(name)).unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_one_minute_load_average() {
+        let fixture = Fixture::new();
+        fixture.write_proc("loadavg", "1.50 0.75 0.25 2/345 6789\n");
+
+        assert_eq!(read_load_average(fixture.proc_root.path()), Some(1.5));
+    }
+
+    #[test]
+    fn missing_or_malformed_loadavg_reports_nothing() {
+        let fixture = Fixture::new();
+        assert_eq!(read_load_average(fixture.proc_root.path()), None);
+
+        fixture.write_proc("loadavg", "not-a-number\n");
+        assert_eq!(read_load_average(fixture.proc_root.path()), None);
+    }
+
+    #[test]
+    fn discharging_battery_without_mains_is_on_battery() {
+        let fixture = Fixture::new();
+        fixture.power_supply("AC", &[("type", "Mains"), ("online", "0")]);
+        fixture.power_supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+
+        assert!(on_battery(fixture.sysfs_root.path()));
+    }
+
+    #[test]
+    fn online_mains_is_not_on_battery() {
+        let fixture = Fixture::new();
+        fixture.power_supply("AC", &[("type", "Mains"), ("online", "1")]);
+        fixture.power_supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+
+        assert!(!on_battery(fixture.sysfs_root.path()));
+    }
+
+    #[test]
+    fn charging_battery_or_no_supplies_is_not_on_battery() {
+        let fixture = Fixture::new();
+        assert!(!on_battery(fixture.sysfs_root.path()));
+
+        fixture.power_supply("BAT0", &[("type", "Battery"), ("status", "Charging")]);
+        assert!(!on_battery(fixture.sysfs_root.path()));
+    }
+
+    #[test]
+    fn disk_ticks_skip_partitions_and_virtual_devices() {
+        let fixture = Fixture::new();
+        fixture.block_device("sda");
+        fixture.block_device("loop0");
+        fixture.write_proc("diskstats", concat!(
+            "   8       0 sda 100 0 200 0 50 0 60 0 0 1234 0\n",
+            "   8       1 sda1 90 0 180 0 40 0 50 0 0 999 0\n",
+            "   7       0 loop0 1 0 2 0 0 0 0 0 0 555 0\n",
+        ));
+
+        let ticks = read_disk_io_ticks(fixture.proc_root.path(), fixture.sysfs_root.path()).unwrap();
+        assert_eq!(ticks, HashMap::from([("sda".to_string(), 1234)]));
+    }
+
+    #[test]
+    fn cpu_usage_is_measured_between_samples() {
+        let fixture = Fixture::new();
+        let config = fixture.config();
+        let mut monitor = ResourceMonitor::default();
+
+        fixture.write_proc("stat", "cpu  100 0 100 700 100 0 0 0 0 0\ncpu0 100 0 100 700 100 0 0 0 0 0\n");
+        assert_eq!(monitor.sample(&config).cpu_percent, None);
+
+        // 200 jiffies later, 50 of them idle or waiting on I/O
+        fixture.write_proc("stat", "cpu  200 0 150 740 110 0 0 0 0 0\n");
+        assert_eq!(monitor.sample(&config).cpu_percent, Some(75.0));
+    }
+
+    #[test]
+    fn sample_divides_load_by_cpu_count_and_reads_battery() {
+        let fixture = Fixture::new();
+        fixture.write_proc("loadavg", "4.00 3.00 2.00 1/100 42\n");
+        fixture.power_supply("BAT0", &[("type", "Battery"), ("status", "Discharging")]);
+
+        let sample = ResourceMonitor::default().sample(&fixture.config());
+        assert_eq!(sample.load_per_cpu, Some(4.0 / num_cpus::get().max(1) as f64));
+        assert!(sample.on_battery);
+        assert_eq!(sample.disk_busy_percent, None);
+    }
+
+    #[test]
+    fn reasons_follow_thresholds() {
+        let config = ThrottleConfig::default();
+        let sample = ResourceSample {
+            load_per_cpu: Some(config.max_load_per_cpu + 0.1),
+            cpu_percent: Some(config.max_cpu_percent),
+            disk_busy_percent: Some(config.max_disk_busy_percent + 1.0),
+            on_battery: true,
+        };
+        assert_eq!(
+            sample.throttle_reasons(&config),
+            vec![ThrottleReason::HighLoad, ThrottleReason::HighDiskIo, ThrottleReason::OnBattery]
+        );
+
+        let config = ThrottleConfig { pause_on_battery: false, ..config };
+        assert!(!sample.throttle_reasons(&config).contains(&ThrottleReason::OnBattery));
+    }
+}
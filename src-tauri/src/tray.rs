@@ -1,69 +1,233 @@
-use tauri::{AppHandle, Manager, Runtime, Emitter};
-use tauri::menu::{Menu, MenuBuilder, MenuItemBuilder};
-use tauri::tray::{TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState};
-
-pub fn create_tray<R: Runtime>() -> TrayIconBuilder<R> {
-    let menu_builder = MenuBuilder::new();
-    let menu = menu_builder
-        .item(&MenuItemBuilder::new("Show").build().unwrap())
-        .item(&MenuItemBuilder::new("Scan File").build().unwrap())
-        .item(&MenuItemBuilder::new("Settings").build().unwrap())
-        .item(&MenuItemBuilder::new("History").build().unwrap())
+use tauri::{AppHandle, Manager, Emitter};
+use tauri::image::Image;
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, MenuItemBuilder, SubmenuBuilder};
+use tauri::tray::{TrayIcon, TrayIconBuilder, TrayIconEvent, MouseButton, MouseButtonState};
+use crate::file_monitor;
+use crate::host::Host;
+use crate::status_indicator::ProtectionStatus;
+use crate::virus_total::{ScanResult, ScanStatus};
+use crate::AppState;
+
+pub const TRAY_ID: &str = "main";
+// Scan results listed in the menu
+pub const RECENT_RESULTS: usize = 5;
+const RESULT_ID_PREFIX: &str = "result:";
+
+// Everything the tray shows, compared to skip rebuilding a menu that would not change
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrayContent {
+    pub status: ProtectionStatus,
+    pub monitoring: bool,
+    pub recent: Vec<RecentResult>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecentResult {
+    pub file_name: String,
+    pub status: ScanStatus,
+    // Hash and scan time identify the entry even after newer scans shift the history
+    pub file_hash: String,
+    pub scan_date: chrono::DateTime<chrono::Utc>,
+}
+
+impl RecentResult {
+    fn menu_id(&self) -> String {
+        format!("{}{}:{}", RESULT_ID_PREFIX, self.file_hash, self.scan_date.timestamp_millis())
+    }
+}
+
+impl TrayContent {
+    pub async fn collect(state: &AppState, status: ProtectionStatus) -> Self {
+        let recent = state.scan_history.lock().await.iter()
+            .rev()
+            .take(RECENT_RESULTS)
+            .map(|result: &ScanResult| RecentResult {
+                file_name: result.file_name.clone(),
+                status: result.status.clone(),
+                file_hash: result.file_hash.clone(),
+                scan_date: result.scan_date,
+            })
+            .collect();
+
+        Self {
+            status,
+            monitoring: state.file_monitor.lock().await.is_monitoring(),
+            recent,
+        }
+    }
+}
+
+// Install the tray icon showing the given content
+pub fn create_tray(app: &AppHandle, content: &TrayContent) -> tauri::Result<TrayIcon> {
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(status_icon(&content.status)?)
+        .tooltip(content.status.description())
+        .menu(&build_menu(app, content)?)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| handle_tray_event(tray.app_handle(), event))
+        .build(app)
+}
+
+// Bring the icon, tooltip and menu in line with new content
+pub fn update_tray(app: &AppHandle, content: &TrayContent) -> tauri::Result<()> {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return Ok(());
+    };
+    tray.set_icon(Some(status_icon(&content.status)?))?;
+    tray.set_tooltip(Some(content.status.description()))?;
+    tray.set_menu(Some(build_menu(app, content)?))?;
+    Ok(())
+}
+
+fn status_icon(status: &ProtectionStatus) -> tauri::Result<Image<'static>> {
+    let bytes: &'static [u8] = match status {
+        ProtectionStatus::Protected => include_bytes!("../icons/normal.png"),
+        ProtectionStatus::Scanning { .. } => include_bytes!("../icons/scanning.png"),
+        ProtectionStatus::Paused | ProtectionStatus::Degraded { .. } => include_bytes!("../icons/warning.png"),
+        ProtectionStatus::ThreatFound { .. } => include_bytes!("../icons/error.png"),
+    };
+    Image::from_bytes(bytes)
+}
+
+fn build_menu(app: &AppHandle, content: &TrayContent) -> tauri::Result<Menu<tauri::Wry>> {
+    // The first line only reports the status
+    let status_item = MenuItemBuilder::with_id("status", content.status.description())
+        .enabled(false)
+        .build(app)?;
+
+    let mut recent = SubmenuBuilder::new(app, "Recent Results");
+    if content.recent.is_empty() {
+        recent = recent.item(&MenuItemBuilder::with_id("no_results", "No scans yet").enabled(false).build(app)?);
+    }
+    for result in &content.recent {
+        let label = format!("{} — {}", result.file_name, status_label(&result.status));
+        recent = recent.item(&MenuItemBuilder::with_id(result.menu_id(), label).build(app)?);
+    }
+
+    let monitoring_label = if content.monitoring { "Pause Monitoring" } else { "Resume Monitoring" };
+
+    MenuBuilder::new(app)
+        .item(&status_item)
+        .separator()
+        .item(&recent.build()?)
         .separator()
-        .item(&MenuItemBuilder::new("Quit").build().unwrap())
+        .item(&MenuItemBuilder::with_id("toggle_monitoring", monitoring_label).build(app)?)
+        .item(&MenuItemBuilder::with_id("scan_downloads", "Scan Downloads Now").build(app)?)
+        .item(&MenuItemBuilder::with_id("open_quarantine", "Open Quarantine").build(app)?)
+        .separator()
+        .item(&MenuItemBuilder::with_id("show", "Show").build(app)?)
+        .item(&MenuItemBuilder::with_id("scan_file", "Scan File").build(app)?)
+        .item(&MenuItemBuilder::with_id("settings", "Settings").build(app)?)
+        .item(&MenuItemBuilder::with_id("history", "History").build(app)?)
+        .separator()
+        .item(&MenuItemBuilder::with_id("quit", "Quit").build(app)?)
         .build()
-        .unwrap();
+}
 
-    TrayIconBuilder::new().menu(&menu)
+fn status_label(status: &ScanStatus) -> &'static str {
+    match status {
+        ScanStatus::Pending => "Pending",
+        ScanStatus::InProgress => "Scanning",
+        ScanStatus::Completed => "Completed",
+        ScanStatus::Failed => "Failed",
+        ScanStatus::Clean => "Clean",
+        ScanStatus::Suspicious => "Suspicious",
+        ScanStatus::Malicious => "Malicious",
+    }
 }
 
-pub fn handle_tray_event<R: Runtime>(app: &AppHandle<R>, event: TrayIconEvent) {
-    match event {
-        TrayIconEvent::Click { 
-            button: MouseButton::Left, 
-            button_state: MouseButtonState::Up, 
-            .. 
-        } => {
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    match event.id().as_ref() {
+        "quit" => {
+            app.exit(0);
+        }
+        "show" => {
             show_main_window(app);
-        },
-        TrayIconEvent::MenuItemClick { id, .. } => {
-            match id.as_str() {
-                "Quit" => {
-                    app.exit(0);
-                }
-                "Show" => {
-                    show_main_window(app);
-                }
-                "Scan File" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        window.emit("show-file-dialog", ()).unwrap();
-                    }
-                }
-                "Settings" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        window.emit("show-settings", ()).unwrap();
-                    }
-                    show_main_window(app);
+        }
+        "scan_file" => {
+            emit_to_main(app, "show-file-dialog", ());
+        }
+        "settings" => {
+            emit_to_main(app, "show-settings", ());
+            show_main_window(app);
+        }
+        "history" => {
+            emit_to_main(app, "show-history", ());
+            show_main_window(app);
+        }
+        "open_quarantine" => {
+            emit_to_main(app, "show-quarantine", ());
+            show_main_window(app);
+        }
+        "toggle_monitoring" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let monitoring = app.state::<AppState>().file_monitor.lock().await.is_monitoring();
+                if let Err(e) = file_monitor::set_monitoring(&Host::App(app.clone()), !monitoring).await {
+                    log::error!("Failed to toggle monitoring: {}", e);
                 }
-                "History" => {
-                    if let Some(window) = app.get_webview_window("main") {
-                        window.emit("show-history", ()).unwrap();
-                    }
-                    show_main_window(app);
+            });
+        }
+        "scan_downloads" => {
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                let download_path = app.state::<AppState>().file_monitor.lock().await
+                    .get_download_path()
+                    .to_path_buf();
+                if let Err(e) = crate::folder_scan::start_folder_scan(Host::App(app), download_path, false).await {
+                    log::error!("Failed to start downloads scan: {}", e);
                 }
-                _ => {}
+            });
+        }
+        id => {
+            let entry = id.strip_prefix(RESULT_ID_PREFIX)
+                .and_then(|entry| entry.rsplit_once(':'))
+                .and_then(|(hash, millis)| Some((hash.to_string(), millis.parse::<i64>().ok()?)));
+            if let Some((file_hash, scan_millis)) = entry {
+                show_recent_result(app, file_hash, scan_millis);
             }
-        },
-        _ => {}
+        }
+    }
+}
+
+// Open the history on one of the results listed in the menu
+fn show_recent_result(app: &AppHandle, file_hash: String, scan_millis: i64) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = app.state::<AppState>().scan_history.lock().await.iter()
+            .rev()
+            .find(|result| result.file_hash == file_hash && result.scan_date.timestamp_millis() == scan_millis)
+            .cloned();
+        if let Some(result) = result {
+            emit_to_main(&app, "show-scan-result", result);
+            show_main_window(&app);
+        }
+    });
+}
+
+pub fn handle_tray_event(app: &AppHandle, event: TrayIconEvent) {
+    if let TrayIconEvent::Click {
+        button: MouseButton::Left,
+        button_state: MouseButtonState::Up,
+        ..
+    } = event
+    {
+        show_main_window(app);
+    }
+}
+
+fn emit_to_main<S: serde::Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Some(window) = app.get_webview_window("main") {
+        if let Err(e) = window.emit(event, payload) {
+            log::error!("Failed to emit {} event: {}", event, e);
+        }
     }
 }
 
-fn show_main_window<R: Runtime>(app: &AppHandle<R>) {
+fn show_main_window(app: &AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
-        window.show().unwrap();
-        window.set_focus().unwrap();
-        
-        #[cfg(target_os = "macos")]
-        window.eval("if (window.__TAURI_METADATA__) { window.__TAURI_METADATA__.__tauriUseMacOS = true; }").unwrap();
+        window.show().unwrap_or_default();
+        window.set_focus().unwrap_or_default();
     }
 }
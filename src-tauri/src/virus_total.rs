@@ -1,6 +1,6 @@
 use reqwest::{Client, multipart};
 use serde::{Deserialize, Serialize};
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::fs::File;
 use std::io::Read;
 use std::collections::HashMap;
@@ -9,6 +9,8 @@ use std::time::{Duration, Instant};
 use tauri::{AppHandle, Emitter};
 use sha2::{Sha256, Digest};
 use tokio::time::sleep;
+use crate::host::Host;
+use crate::{ErrorCategory, Subsystem};
 
 // Constants
 const VT_API_URL: &str = "https://www.virustotal.com/api/v3";
@@ -16,7 +18,9 @@ const CACHE_EXPIRATION: Duration = Duration::from_secs(86400); // 24 hours
 const API_RATE_LIMIT: Duration = Duration::from_secs(15); // 15 seconds between API calls for free tier
 
 // Cache for storing scan results to avoid rescanning
-static SCAN_CACHE: once_cell::sync::Lazy<Arc<Mutex<HashMap<String, (ScanResult, Instant)>>>> =
+type ScanCache = HashMap<String, (ScanResult, Instant)>;
+
+static SCAN_CACHE: once_cell::sync::Lazy<Arc<Mutex<ScanCache>>> =
     once_cell::sync::Lazy::new(|| Arc::new(Mutex::new(HashMap::new())));
 
 // Last API call timestamp for rate limiting
@@ -65,6 +69,52 @@ pub enum ScanStatus {
     Malicious,
 }
 
+// A failed lookup, upload or scan, with the category it is reported under
+#[derive(Debug, Clone)]
+pub struct ScanError {
+    pub category: ErrorCategory,
+    pub message: String,
+}
+
+impl ScanError {
+    pub fn new(category: ErrorCategory, message: impl Into<String>) -> Self {
+        Self { category, message: message.into() }
+    }
+
+    fn request(context: &str, e: reqwest::Error) -> Self {
+        let category = match e.status() {
+            Some(status) => ErrorCategory::from_status(status.as_u16()),
+            None if e.is_timeout() || e.is_connect() || e.is_request() => ErrorCategory::NetworkError,
+            None => ErrorCategory::ApiError,
+        };
+        Self::new(category, format!("{}: {}", context, e))
+    }
+
+    fn status(context: &str, status: reqwest::StatusCode) -> Self {
+        Self::new(ErrorCategory::from_status(status.as_u16()), format!("{}: {}", context, status))
+    }
+
+    fn io(context: &str, e: std::io::Error) -> Self {
+        Self::new(ErrorCategory::from_io(e.kind()), format!("{}: {}", context, e))
+    }
+
+    fn api(message: impl Into<String>) -> Self {
+        Self::new(ErrorCategory::ApiError, message)
+    }
+}
+
+impl std::fmt::Display for ScanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<ScanError> for String {
+    fn from(e: ScanError) -> Self {
+        e.message
+    }
+}
+
 pub struct VirusTotal {
     client: Client,
     api_key: String,
@@ -93,9 +143,9 @@ impl VirusTotal {
     }
 
     pub async fn test_api_key(&self) -> Result<bool, String> {
-        self.rate_limit().await?;
+        self.rate_limit().await;
 
-        let response = self.client.get(&format!("{}/users/current", VT_API_URL))
+        let response = self.client.get(format!("{}/users/current", VT_API_URL))
             .header("x-apikey", &self.api_key)
             .send()
             .await
@@ -104,51 +154,91 @@ impl VirusTotal {
         Ok(response.status().is_success())
     }
 
-    pub async fn scan_file<P: AsRef<Path>>(&self, file_path: P) -> Result<ScanResult, String> {
-        let path = PathBuf::from(file_path.as_ref());
-        println!("Scanning file: {}", path.display());
+    pub async fn scan_file<P: AsRef<Path>>(&self, file_path: P) -> Result<ScanResult, ScanError> {
+        crate::scanner::ScanPipeline::new(self.clone()).scan(file_path.as_ref()).await
+    }
 
-        // Validate file
-        if !path.exists() {
-            return Err("File does not exist".to_string());
-        }
+    // Fetch the existing report for a hash, None when VirusTotal has never analyzed the file
+    pub async fn lookup_hash(&self, path: &Path, file_hash: &str) -> Result<Option<ScanResult>, ScanError> {
+        let Some(attributes) = self.file_report(file_hash).await? else {
+            return Ok(None);
+        };
+
+        let result = build_scan_result(
+            path,
+            file_hash,
+            &attributes["last_analysis_stats"],
+            &attributes["last_analysis_results"],
+        )?;
+        cache_result(&result);
+
+        Ok(Some(result))
+    }
 
-        // Get file metadata
-        let file_name = path.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown file")
-            .to_string();
+    // Like lookup_hash, for a file that is not on this machine; names it as VirusTotal does
+    pub async fn lookup_report(&self, file_hash: &str) -> Result<Option<ScanResult>, ScanError> {
+        let Some(attributes) = self.file_report(file_hash).await? else {
+            return Ok(None);
+        };
+
+        let file_name = attributes["meaningful_name"].as_str().unwrap_or(file_hash).to_string();
+        Ok(Some(result_from_report(
+            file_name.clone(),
+            file_name,
+            attributes["size"].as_u64().unwrap_or(0),
+            // The report may have been found by MD5 or SHA-1
+            attributes["sha256"].as_str().unwrap_or(file_hash),
+            &attributes["last_analysis_stats"],
+            &attributes["last_analysis_results"],
+        )))
+    }
 
-        let file_size = std::fs::metadata(&path)
-            .map(|m| m.len())
-            .map_err(|e| format!("Failed to get file metadata: {}", e))?;
+    // Attributes of the file report, None when there is no report with engine results
+    async fn file_report(&self, file_hash: &str) -> Result<Option<serde_json::Value>, ScanError> {
+        log::debug!("Looking up hash {}", file_hash);
 
-        // Calculate file hash
-        println!("Calculating file hash");
-        let file_hash = calculate_file_hash(&path).await?;
+        self.rate_limit().await;
 
-        // Check cache
-        {
-            let cache = SCAN_CACHE.lock().unwrap();
-            if let Some((cached_result, timestamp)) = cache.get(&file_hash) {
-                if timestamp.elapsed() < CACHE_EXPIRATION {
-                    println!("Using cached results");
-                    return Ok(cached_result.clone());
-                }
-            }
+        let response = self.client.get(format!("{}/files/{}", VT_API_URL, file_hash))
+            .header("x-apikey", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| ScanError::request("Hash lookup failed", e))?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(ScanError::status("Hash lookup failed", response.status()));
         }
 
-        // Upload and scan file
-        println!("Uploading file to VirusTotal");
+        let report = response.json::<serde_json::Value>()
+            .await
+            .map_err(|e| ScanError::request("Failed to parse file report", e))?;
+
+        let attributes = &report["data"]["attributes"];
+        let has_results = attributes["last_analysis_results"].as_object()
+            .map(|results| !results.is_empty())
+            .unwrap_or(false);
+        if !has_results {
+            return Ok(None);
+        }
+
+        Ok(Some(attributes.clone()))
+    }
+
+    // Upload a file and wait for VirusTotal to finish analyzing it
+    pub async fn upload_and_analyze(&self, path: &Path, file_hash: &str) -> Result<ScanResult, ScanError> {
+        log::info!("Uploading file to VirusTotal");
 
-        self.rate_limit().await?;
+        self.rate_limit().await;
 
         // Read file into memory for multipart form
-        let mut file = std::fs::File::open(&path)
-            .map_err(|e| format!("Failed to open file: {}", e))?;
+        let mut file = std::fs::File::open(path)
+            .map_err(|e| ScanError::io("Failed to open file", e))?;
         let mut buffer = Vec::new();
         file.read_to_end(&mut buffer)
-            .map_err(|e| format!("Failed to read file: {}", e))?;
+            .map_err(|e| ScanError::io("Failed to read file", e))?;
 
         let file_name_for_upload = path.file_name().unwrap_or_default().to_string_lossy().to_string();
         
@@ -160,115 +250,75 @@ impl VirusTotal {
         let form = multipart::Form::new()
             .part("file", part);
 
-        let response = self.client.post(&format!("{}/files", VT_API_URL))
+        let response = self.client.post(format!("{}/files", VT_API_URL))
             .header("x-apikey", &self.api_key)
             .multipart(form)
             .send()
             .await
-            .map_err(|e| format!("Failed to upload file: {}", e))?;
+            .map_err(|e| ScanError::request("Failed to upload file", e))?;
 
         if !response.status().is_success() {
-            return Err(format!("Upload failed: {}", response.status()));
+            return Err(ScanError::status("Upload failed", response.status()));
         }
 
         let upload_result = response.json::<serde_json::Value>()
             .await
-            .map_err(|e| format!("Failed to parse upload response: {}", e))?;
+            .map_err(|e| ScanError::request("Failed to parse upload response", e))?;
 
         let analysis_id = upload_result["data"]["id"].as_str()
-            .ok_or("Missing analysis ID")?;
+            .ok_or_else(|| ScanError::api("Missing analysis ID"))?;
 
         // Poll for analysis completion with retry mechanism
-        println!("Analyzing file");
+        log::info!("Analyzing file");
 
         let mut attempts = 0;
         let max_attempts = 30;
 
         while attempts < max_attempts {
-            self.rate_limit().await?;
+            self.rate_limit().await;
 
-            let response = self.client.get(&format!("{}/analyses/{}", VT_API_URL, analysis_id))
+            let response = self.client.get(format!("{}/analyses/{}", VT_API_URL, analysis_id))
                 .header("x-apikey", &self.api_key)
                 .send()
                 .await
-                .map_err(|e| format!("Analysis failed: {}", e))?;
+                .map_err(|e| ScanError::request("Analysis failed", e))?;
 
             if !response.status().is_success() {
-                return Err(format!("Analysis failed: {}", response.status()));
+                return Err(ScanError::status("Analysis failed", response.status()));
             }
 
             let analysis_result = response.json::<serde_json::Value>()
                 .await
-                .map_err(|e| format!("Failed to parse analysis: {}", e))?;
+                .map_err(|e| ScanError::request("Failed to parse analysis", e))?;
 
             let status = analysis_result["data"]["attributes"]["status"].as_str()
-                .ok_or("Missing status")?;
+                .ok_or_else(|| ScanError::api("Missing status"))?;
 
             if status == "completed" {
-                let stats = &analysis_result["data"]["attributes"]["stats"];
-                let results = &analysis_result["data"]["attributes"]["results"];
-
-                let malicious = stats["malicious"].as_u64().unwrap_or(0);
-                let suspicious = stats["suspicious"].as_u64().unwrap_or(0);
-                let total = stats["total"].as_u64().unwrap_or(0);
-
-                let mut vendor_results = HashMap::new();
-                if let Some(obj) = results.as_object() {
-                    for (engine, result) in obj {
-                        vendor_results.insert(engine.clone(), ScanEntry {
-                            detected: result["category"].as_str().unwrap_or("") == "malicious",
-                            version: result["engine_version"].as_str().map(String::from),
-                            result: result["result"].as_str().map(String::from),
-                            engine_name: engine.clone(),
-                            engine_version: result["engine_version"].as_str().map(String::from),
-                            engine_update: result["engine_update"].as_str().map(String::from),
-                        });
-                    }
-                }
-
-                let status = if malicious > 0 {
-                    ScanStatus::Malicious
-                } else if suspicious > 0 {
-                    ScanStatus::Suspicious
-                } else {
-                    ScanStatus::Clean
-                };
-
-                let result = ScanResult {
-                    file_path: path.to_string_lossy().to_string(),
-                    file_name,
-                    file_size,
-                    file_hash: file_hash.clone(),
-                    scan_date: chrono::Utc::now(),
-                    status,
-                    detection_count: Some((malicious + suspicious) as u32),
-                    total_engines: Some(total as u32),
-                    permalink: Some(format!("https://www.virustotal.com/gui/file/{}/detection", file_hash)),
-                    vendor_results: Some(vendor_results),
-                };
-
-                // Cache the result
-                {
-                    let mut cache = SCAN_CACHE.lock().unwrap();
-                    cache.insert(file_hash, (result.clone(), Instant::now()));
-                }
-
-                println!("Scan completed successfully");
+                let result = build_scan_result(
+                    path,
+                    file_hash,
+                    &analysis_result["data"]["attributes"]["stats"],
+                    &analysis_result["data"]["attributes"]["results"],
+                )?;
+                cache_result(&result);
+
+                log::info!("Scan completed successfully");
                 return Ok(result);
             }
 
             let progress = 60 + ((attempts as f32 / max_attempts as f32) * 30.0) as u8;
-            println!("Analyzing file: {}%", progress);
+            log::debug!("Analyzing file: {}%", progress);
 
             attempts += 1;
             sleep(Duration::from_secs(2)).await;
         }
 
-        Err("Analysis timed out".to_string())
+        Err(ScanError::api("Analysis timed out"))
     }
 
     // Rate limit API calls
-    async fn rate_limit(&self) -> Result<(), String> {
+    async fn rate_limit(&self) {
         let wait_needed;
         
         {
@@ -287,18 +337,23 @@ impl VirusTotal {
         }
         
         if let Some(wait_time) = wait_needed {
-            println!("Rate limiting: waiting for {} seconds", wait_time.as_secs());
+            log::debug!("Rate limiting: waiting for {} seconds", wait_time.as_secs());
             sleep(wait_time).await;
         }
         
         let mut last_call = LAST_API_CALL.lock().unwrap();
         *last_call = Some(Instant::now());
-        
-        Ok(())
     }
 }
 
-async fn calculate_file_hash(path: &Path) -> Result<String, String> {
+pub(crate) async fn calculate_file_hash(path: &Path) -> Result<String, String> {
+    // Large files would otherwise hold up the async worker for the whole read
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || hash_file_blocking(&path)).await
+        .map_err(|e| format!("Failed to hash file: {}", e))?
+}
+
+fn hash_file_blocking(path: &Path) -> Result<String, String> {
     let mut file = File::open(path)
         .map_err(|e| format!("Failed to open file: {}", e))?;
 
@@ -319,6 +374,259 @@ async fn calculate_file_hash(path: &Path) -> Result<String, String> {
     Ok(format!("{:x}", hasher.finalize()))
 }
 
+// Build a scan result from the stats and per-engine results of an analysis or file report
+fn build_scan_result(
+    path: &Path,
+    file_hash: &str,
+    stats: &serde_json::Value,
+    results: &serde_json::Value,
+) -> Result<ScanResult, ScanError> {
+    let file_name = path.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown file")
+        .to_string();
+
+    let file_size = std::fs::metadata(path)
+        .map(|m| m.len())
+        .map_err(|e| ScanError::io("Failed to get file metadata", e))?;
+
+    Ok(result_from_report(path.to_string_lossy().to_string(), file_name, file_size, file_hash, stats, results))
+}
+
+fn result_from_report(
+    file_path: String,
+    file_name: String,
+    file_size: u64,
+    file_hash: &str,
+    stats: &serde_json::Value,
+    results: &serde_json::Value,
+) -> ScanResult {
+    let malicious = stats["malicious"].as_u64().unwrap_or(0);
+    let suspicious = stats["suspicious"].as_u64().unwrap_or(0);
+
+    let mut vendor_results = HashMap::new();
+    if let Some(obj) = results.as_object() {
+        for (engine, result) in obj {
+            vendor_results.insert(engine.clone(), ScanEntry {
+                detected: result["category"].as_str().unwrap_or("") == "malicious",
+                version: result["engine_version"].as_str().map(String::from),
+                result: result["result"].as_str().map(String::from),
+                engine_name: engine.clone(),
+                engine_version: result["engine_version"].as_str().map(String::from),
+                engine_update: result["engine_update"].as_str().map(String::from),
+            });
+        }
+    }
+
+    // The stats object has no total, so count the engines that reported
+    let total = if vendor_results.is_empty() {
+        stats.as_object()
+            .map(|counts| counts.values().filter_map(|count| count.as_u64()).sum())
+            .unwrap_or(0)
+    } else {
+        vendor_results.len() as u64
+    };
+
+    let status = if malicious > 0 {
+        ScanStatus::Malicious
+    } else if suspicious > 0 {
+        ScanStatus::Suspicious
+    } else {
+        ScanStatus::Clean
+    };
+
+    ScanResult {
+        file_path,
+        file_name,
+        file_size,
+        file_hash: file_hash.to_string(),
+        scan_date: chrono::Utc::now(),
+        status,
+        detection_count: Some((malicious + suspicious) as u32),
+        total_engines: Some(total as u32),
+        permalink: Some(format!("https://www.virustotal.com/gui/file/{}/detection", file_hash)),
+        vendor_results: Some(vendor_results),
+    }
+}
+
+fn cache_result(result: &ScanResult) {
+    let mut cache = SCAN_CACHE.lock().unwrap();
+    cache.insert(result.file_hash.clone(), (result.clone(), Instant::now()));
+}
+
+// Look up a cached scan result by file hash, ignoring expired entries
+pub(crate) fn cached_result(file_hash: &str) -> Option<ScanResult> {
+    let cache = SCAN_CACHE.lock().unwrap();
+    cache.get(file_hash)
+        .filter(|(_, timestamp)| timestamp.elapsed() < CACHE_EXPIRATION)
+        .map(|(result, _)| result.clone())
+}
+
+// Drop cache entries past their expiry and return how many there were
+pub(crate) fn evict_expired_cache(dry_run: bool) -> usize {
+    let mut cache = SCAN_CACHE.lock().unwrap();
+    let expired = cache.values()
+        .filter(|(_, timestamp)| timestamp.elapsed() >= CACHE_EXPIRATION)
+        .count();
+    if !dry_run {
+        cache.retain(|_, (_, timestamp)| timestamp.elapsed() < CACHE_EXPIRATION);
+    }
+    expired
+}
+
+// Scan a file with the configured API key and record the result in the scan history
+pub async fn scan_file_internal(file_path: &str, state: &crate::AppState) -> Result<ScanResult, ScanError> {
+    let result = scan_transient(file_path, state).await?;
+    record_result(state, result.clone()).await;
+
+    Ok(result)
+}
+
+// Scan a file that only exists for the request, such as an API upload. The result is not
+// recorded, since the file is gone by the time anyone would look at it.
+pub(crate) async fn scan_transient(file_path: &str, state: &crate::AppState) -> Result<ScanResult, ScanError> {
+    let api_key = state.api_key.lock().await.clone()
+        .ok_or_else(|| ScanError::new(ErrorCategory::ConfigurationError, "API key not initialized"))?;
+
+    let pipeline = crate::scanner::ScanPipeline::new(VirusTotal::new(api_key));
+    let outcome = pipeline.scan(Path::new(file_path)).await;
+    report_api_outcome(state, &outcome).await;
+    outcome
+}
+
+// Keep the API's health current for every scan. Files that could not be read say nothing
+// about the API.
+pub(crate) async fn report_api_outcome<T>(state: &crate::AppState, outcome: &Result<T, ScanError>) {
+    match outcome {
+        Ok(_) => state.error_handler.lock().await.report_success(Subsystem::Api, "scan"),
+        Err(e) if e.category != ErrorCategory::FileSystemError => {
+            state.error_handler.lock().await.report(Subsystem::Api, e.category, "scan", &e.message);
+        }
+        Err(_) => {}
+    }
+}
+
+// Scan a file and apply what follows a verdict
+pub(crate) async fn scan_and_respond(host: &Host, file_path: &str) -> Result<ScanResult, String> {
+    let state = host.state();
+    let result = scan_file_internal(file_path, state).await?;
+    respond(host, &result).await;
+    Ok(result)
+}
+
+// The auto-quarantine policy, notification rules and webhooks, for every way a file gets scanned
+pub(crate) async fn respond(host: &Host, result: &ScanResult) {
+    crate::quarantine::enforce_policy(host, result).await;
+    crate::notifications::scan_completed(host, result);
+    crate::webhooks::detection(host, result);
+}
+
+fn history_path() -> std::path::PathBuf {
+    crate::settings::data_dir().join("scan_history.json")
+}
+
+// Scan history persisted by a previous run, oldest first
+pub fn load_history() -> Vec<ScanResult> {
+    read_history().unwrap_or_else(|e| {
+        log::warn!("{}", e);
+        Vec::new()
+    })
+}
+
+// A missing file is an empty history, one that cannot be parsed is an error
+fn read_history() -> Result<Vec<ScanResult>, String> {
+    match std::fs::read_to_string(history_path()) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse scan history: {}", e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read scan history: {}", e)),
+    }
+}
+
+// The app, the daemon and vscan each keep their own copy of the history, so every
+// change re-reads the file under this lock instead of overwriting it
+fn lock_history() -> Result<std::fs::File, String> {
+    let path = history_path().with_extension("lock");
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open scan history lock: {}", e))?;
+    file.lock()
+        .map_err(|e| format!("Failed to lock scan history: {}", e))?;
+    Ok(file)
+}
+
+// Apply a change to the latest persisted history and refresh the in-memory copy with it.
+// The file work runs off the async runtime. A history file that cannot be parsed is left
+// alone, and only the in-memory copy changes.
+pub(crate) async fn update_history<F, R>(history: &mut Vec<ScanResult>, change: F) -> R
+where
+    F: FnOnce(&mut Vec<ScanResult>) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let persisted = tokio::task::spawn_blocking(move || {
+        let _lock = lock_history().map_err(|e| log::error!("{}", e)).ok();
+        match read_history() {
+            Ok(mut latest) => {
+                let output = change(&mut latest);
+                if let Err(e) = save_history(&latest) {
+                    log::error!("Failed to persist scan history: {}", e);
+                }
+                Ok((latest, output))
+            }
+            Err(e) => Err((e, change)),
+        }
+    }).await.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+
+    match persisted {
+        Ok((latest, output)) => {
+            *history = latest;
+            output
+        }
+        Err((e, change)) => {
+            log::error!("{}, refusing to overwrite it", e);
+            change(history)
+        }
+    }
+}
+
+fn save_history(history: &[ScanResult]) -> Result<(), String> {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create data directory: {}", e))?;
+    }
+    let content = serde_json::to_string(history)
+        .map_err(|e| format!("Failed to serialize scan history: {}", e))?;
+    let tmp_path = path.with_extension("json.tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write scan history: {}", e))?;
+    std::fs::rename(&tmp_path, &path)
+        .map_err(|e| format!("Failed to replace scan history: {}", e))
+}
+
+// Append a result to the scan history and persist it
+pub(crate) async fn record_result(state: &crate::AppState, result: ScanResult) {
+    crate::audit::record("scan_verdict", &result.file_path, true, serde_json::json!({
+        "sha256": result.file_hash,
+        "status": result.status,
+        "detection_count": result.detection_count,
+        "total_engines": result.total_engines,
+    }));
+
+    crate::siem::scan_verdict(&result).await;
+
+    let mut history = state.scan_history.lock().await;
+    update_history(&mut history, |history| history.push(result)).await;
+    state.status_indicator.refresh();
+}
+
 // Helper function to emit progress updates
 fn emit_progress(app_handle: &AppHandle, step: u8, message: &str, progress: u8) -> Result<(), String> {
     app_handle.emit("scan-progress", (step, message, progress))
@@ -332,39 +640,31 @@ pub async fn scan_file(
     state: tauri::State<'_, crate::AppState>,
     app_handle: AppHandle,
 ) -> Result<ScanResponse, String> {
-    let api_key = state.api_key.lock().await.clone();
-    
-    match api_key {
-        Some(key) => {
-            emit_progress(&app_handle, 1, "Starting scan", 10).ok();
-            
-            let vt = VirusTotal::new(key);
-            match vt.scan_file(&file_path).await {
-                Ok(result) => {
-                    // Add to scan history
-                    let mut history = state.scan_history.lock().await;
-                    history.push(result.clone());
-                    
-                    emit_progress(&app_handle, 3, "Scan completed", 100).ok();
-                    
-                    Ok(ScanResponse {
-                        success: true,
-                        message: "Scan completed successfully".to_string(),
-                        result: Some(result),
-                    })
-                },
-                Err(e) => {
-                    emit_progress(&app_handle, 3, "Scan failed", 100).ok();
-                    
-                    Ok(ScanResponse {
-                        success: false,
-                        message: format!("Scan failed: {}", e),
-                        result: None,
-                    })
-                }
-            }
+    if state.api_key.lock().await.is_none() {
+        return Err("API key not initialized".to_string());
+    }
+
+    emit_progress(&app_handle, 1, "Starting scan", 10).ok();
+
+    match scan_and_respond(&Host::App(app_handle.clone()), &file_path).await {
+        Ok(result) => {
+            emit_progress(&app_handle, 3, "Scan completed", 100).ok();
+
+            Ok(ScanResponse {
+                success: true,
+                message: "Scan completed successfully".to_string(),
+                result: Some(result),
+            })
         },
-        None => Err("API key not initialized".to_string()),
+        Err(e) => {
+            emit_progress(&app_handle, 3, "Scan failed", 100).ok();
+
+            Ok(ScanResponse {
+                success: false,
+                message: format!("Scan failed: {}", e),
+                result: None,
+            })
+        }
     }
 }
 
@@ -392,7 +692,7 @@ pub async fn get_scan_history(state: tauri::State<'_, crate::AppState>) -> Resul
 #[tauri::command]
 pub async fn clear_scan_history(state: tauri::State<'_, crate::AppState>) -> Result<(), String> {
     let mut history = state.scan_history.lock().await;
-    history.clear();
+    update_history(&mut history, |history| history.clear()).await;
     Ok(())
 }
 
@@ -428,13 +728,17 @@ pub async fn scan_file_vt(
     }
 }
 
-// Delete file command
+// Delete a scanned or quarantined file, to the trash unless another mode is requested
 #[tauri::command]
-pub async fn delete_file(file_path: String) -> Result<bool, String> {
-    use std::fs;
-
-    match fs::remove_file(&file_path) {
-        Ok(_) => Ok(true),
-        Err(e) => Err(format!("Failed to delete file: {}", e)),
-    }
+pub async fn delete_file(
+    file_path: String,
+    mode: Option<crate::config::DeleteMode>,
+    state: tauri::State<'_, crate::AppState>,
+) -> Result<bool, String> {
+    let mode = match mode {
+        Some(mode) => mode,
+        None => crate::settings::Settings::load()?.delete.default_mode,
+    };
+    crate::safe_delete::safe_delete(&state, &file_path, mode).await?;
+    Ok(true)
 }
@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+use std::path::Path;
+use hmac::{Hmac, Mac};
+use reqwest::header::{HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tauri::AppHandle;
+use crate::host::Host;
+use crate::quarantine::{self, Quarantine};
+use crate::settings::{FieldError, QuarantineSettings, Settings};
+use crate::virus_total::{ScanResult, ScanStatus};
+use crate::{ErrorCategory, RetryPolicy, Subsystem};
+
+const SIGNATURE_HEADER: &str = "X-Scanner-Signature";
+const TIMESTAMP_HEADER: &str = "X-Scanner-Timestamp";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // Signs each request with HMAC-SHA256 when set
+    #[serde(default)]
+    pub secret: Option<String>,
+    // Verdicts delivered to this webhook
+    #[serde(default = "default_verdicts")]
+    pub verdicts: Vec<ScanStatus>,
+    #[serde(default = "default_timeout")]
+    pub timeout_secs: u64,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_verdicts() -> Vec<ScanStatus> {
+    vec![ScanStatus::Malicious, ScanStatus::Suspicious]
+}
+
+fn default_timeout() -> u64 {
+    10
+}
+
+impl WebhookConfig {
+    pub fn validate(&self, errors: &mut Vec<FieldError>) {
+        let field = format!("webhooks.{}", self.id);
+        match reqwest::Url::parse(&self.url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => errors.push(FieldError::new(&field, format!("Webhook URL must be http or https: {}", self.url))),
+        }
+        for (name, value) in &self.headers {
+            if HeaderName::from_bytes(name.as_bytes()).is_err() || HeaderValue::from_str(value).is_err() {
+                errors.push(FieldError::new(&field, format!("Invalid header: {}", name)));
+            }
+        }
+        if self.timeout_secs == 0 {
+            errors.push(FieldError::new(&field, "Timeout must be positive"));
+        }
+        for (retry_field, message) in self.retry.problems() {
+            errors.push(FieldError::new(&format!("{}.retry.{}", field, retry_field), message));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EventHashes {
+    pub sha256: String,
+    pub sha1: Option<String>,
+    pub md5: Option<String>,
+}
+
+// Body POSTed to each webhook
+#[derive(Debug, Clone, Serialize)]
+pub struct DetectionEvent {
+    pub event: String,
+    pub id: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub host: String,
+    pub file_path: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub hashes: EventHashes,
+    pub verdict: ScanStatus,
+    pub detections: Option<u32>,
+    pub total_engines: Option<u32>,
+    pub permalink: Option<String>,
+}
+
+impl DetectionEvent {
+    pub fn from_result(event: &str, result: &ScanResult, quarantine: &QuarantineSettings) -> Self {
+        Self {
+            event: event.to_string(),
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: chrono::Utc::now(),
+            host: gethostname::gethostname().to_string_lossy().to_string(),
+            file_path: result.file_path.clone(),
+            file_name: result.file_name.clone(),
+            file_size: result.file_size,
+            hashes: event_hashes(result, quarantine),
+            verdict: result.status.clone(),
+            detections: result.detection_count,
+            total_engines: result.total_engines,
+            permalink: result.permalink.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeliveryReport {
+    pub webhook_id: String,
+    pub success: bool,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+struct DeliveryError {
+    message: String,
+    status: Option<u16>,
+    category: ErrorCategory,
+    // Client errors other than rate limiting will fail the same way again
+    retryable: bool,
+}
+
+impl std::fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+// The file may already be in quarantine, which recorded every hash when it moved it
+fn event_hashes(result: &ScanResult, quarantine: &QuarantineSettings) -> EventHashes {
+    let hashes = quarantine::hash_file(Path::new(&result.file_path)).ok()
+        .filter(|hashes| hashes.sha256 == result.file_hash)
+        .or_else(|| {
+            Quarantine::open(quarantine).ok()?
+                .list().ok()?
+                .into_iter()
+                .find(|item| item.hashes.sha256 == result.file_hash)
+                .map(|item| item.hashes)
+        });
+
+    match hashes {
+        Some(hashes) => EventHashes {
+            sha256: hashes.sha256,
+            sha1: Some(hashes.sha1),
+            md5: Some(hashes.md5),
+        },
+        None => EventHashes {
+            sha256: result.file_hash.clone(),
+            sha1: None,
+            md5: None,
+        },
+    }
+}
+
+// Deliver a scan result to every enabled webhook whose verdict filter matches
+pub fn detection(host: &Host, result: &ScanResult) {
+    let settings = Settings::cached();
+    let webhooks: Vec<WebhookConfig> = settings.webhooks.iter()
+        .filter(|webhook| webhook.enabled && webhook.verdicts.contains(&result.status))
+        .cloned()
+        .collect();
+    if webhooks.is_empty() {
+        return;
+    }
+    let quarantine = settings.quarantine_settings.clone();
+
+    let host = host.clone();
+    let result = result.clone();
+    tauri::async_runtime::spawn(async move {
+        // Hashing may read the whole file, so keep it off the async workers
+        let event = match tokio::task::spawn_blocking(move || DetectionEvent::from_result("detection", &result, &quarantine)).await {
+            Ok(event) => event,
+            Err(e) => {
+                log::error!("Failed to build webhook event: {}", e);
+                return;
+            }
+        };
+
+        let deliveries = webhooks.iter().map(|webhook| deliver(&host, webhook, &event));
+        futures::future::join_all(deliveries).await;
+    });
+}
+
+// POST an event, retrying with backoff per the webhook's policy
+pub async fn deliver(host: &Host, webhook: &WebhookConfig, event: &DetectionEvent) -> DeliveryReport {
+    let state = host.state();
+    let body = match serde_json::to_vec(event) {
+        Ok(body) => body,
+        Err(e) => return report(webhook, Err(format!("Failed to serialize webhook event: {}", e)), None),
+    };
+
+    let operation = format!("webhook:{}", webhook.name);
+    let outcome = retry_with_backoff!(
+        state.error_handler,
+        Subsystem::Webhooks,
+        &operation,
+        webhook.retry,
+        post(webhook, &body),
+        |e: &DeliveryError| e.category,
+        |e: &DeliveryError| e.retryable
+    );
+
+    match outcome {
+        Ok(status) => {
+            log::info!("Delivered {} event {} to webhook {}", event.event, event.id, webhook.name);
+            report(webhook, Ok(()), Some(status))
+        }
+        Err(e) => report(webhook, Err(e.message), e.status),
+    }
+}
+
+fn report(webhook: &WebhookConfig, outcome: Result<(), String>, status: Option<u16>) -> DeliveryReport {
+    DeliveryReport {
+        webhook_id: webhook.id.clone(),
+        success: outcome.is_ok(),
+        status,
+        error: outcome.err(),
+    }
+}
+
+async fn post(webhook: &WebhookConfig, body: &[u8]) -> Result<u16, DeliveryError> {
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(webhook.timeout_secs))
+        .build()
+        .map_err(|e| DeliveryError {
+            message: format!("Failed to create HTTP client: {}", e),
+            status: None,
+            category: ErrorCategory::ConfigurationError,
+            retryable: false,
+        })?;
+
+    let mut request = client.post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json");
+    for (name, value) in &webhook.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    if let Some(secret) = &webhook.secret {
+        let timestamp = chrono::Utc::now().timestamp().to_string();
+        request = request
+            .header(TIMESTAMP_HEADER, &timestamp)
+            .header(SIGNATURE_HEADER, format!("sha256={}", sign(secret, &timestamp, body)));
+    }
+
+    let response = request.body(body.to_vec()).send().await
+        .map_err(|e| DeliveryError {
+            message: format!("Webhook request failed: {}", e),
+            status: None,
+            // The request builder only fails on a header or URL from the webhook's settings
+            category: if e.is_builder() { ErrorCategory::ConfigurationError } else { ErrorCategory::NetworkError },
+            retryable: true,
+        })?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(status.as_u16());
+    }
+    Err(DeliveryError {
+        message: format!("Webhook returned HTTP {}", status),
+        status: Some(status.as_u16()),
+        category: ErrorCategory::from_status(status.as_u16()),
+        retryable: status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+    })
+}
+
+// HMAC-SHA256 over "<timestamp>.<body>", so a captured request cannot be replayed later
+// under a new timestamp
+fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// Tauri command to send a sample detection to one webhook right away
+#[tauri::command]
+pub async fn test_webhook(id: String, app_handle: AppHandle) -> Result<DeliveryReport, String> {
+    let webhook = Settings::load()?.webhooks.into_iter()
+        .find(|webhook| webhook.id == id)
+        .ok_or_else(|| format!("No webhook with id {}", id))?;
+
+    // The EICAR test file, which every engine reports without it being harmful
+    let event = DetectionEvent {
+        event: "test".to_string(),
+        id: uuid::Uuid::new_v4().to_string(),
+        timestamp: chrono::Utc::now(),
+        host: gethostname::gethostname().to_string_lossy().to_string(),
+        file_path: "/tmp/eicar.com".to_string(),
+        file_name: "eicar.com".to_string(),
+        file_size: 68,
+        hashes: EventHashes {
+            sha256: "275a021bbfb6489e54d471899f7db9d1663fc695ec2fe2a2c4538aabf651fd0f".to_string(),
+            sha1: Some("3395856ce81f2b7382dee72602f798b642f14140".to_string()),
+            md5: Some("44d88612fea8a8f36de82e1278abb02f".to_string()),
+        },
+        verdict: ScanStatus::Malicious,
+        detections: Some(60),
+        total_engines: Some(70),
+        permalink: Some("https://www.virustotal.com/gui/file/275a021bbfb6489e54d471899f7db9d1663fc695ec2fe2a2c4538aabf651fd0f".to_string()),
+    };
+
+    Ok(deliver(&Host::App(app_handle), &webhook, &event).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use crate::host::Headless;
+    use crate::AppState;
+
+    struct Received {
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    // Answers each request with the next status, recording what it was sent
+    async fn endpoint(statuses: Vec<u16>) -> (String, Arc<Mutex<Vec<Received>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let log = received.clone();
+        tokio::spawn(async move {
+            for status in statuses {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = BufReader::new(stream);
+
+                let mut request_line = String::new();
+                stream.read_line(&mut request_line).await.unwrap();
+                let mut headers = HashMap::new();
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).await.unwrap();
+                    let Some((name, value)) = line.trim_end().split_once(':') else {
+                        break;
+                    };
+                    headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+                }
+                let length = headers.get("content-length").map_or(0, |length| length.parse().unwrap());
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).await.unwrap();
+                log.lock().unwrap().push(Received { headers, body });
+
+                let response = format!("HTTP/1.1 {} Test\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status);
+                stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+
+        (url, received)
+    }
+
+    fn webhook(url: String) -> WebhookConfig {
+        WebhookConfig {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            url,
+            enabled: true,
+            headers: HashMap::from([("X-Team".to_string(), "security".to_string())]),
+            secret: Some("s3cret".to_string()),
+            verdicts: default_verdicts(),
+            timeout_secs: 5,
+            retry: RetryPolicy {
+                max_attempts: 3,
+                base_delay_ms: 1,
+                max_delay_ms: 1,
+                jitter: 0.0,
+            },
+        }
+    }
+
+    fn event() -> DetectionEvent {
+        DetectionEvent {
+            event: "detection".to_string(),
+            id: "event-1".to_string(),
+            timestamp: chrono::Utc::now(),
+            host: "test-host".to_string(),
+            file_path: "/tmp/sample.exe".to_string(),
+            file_name: "sample.exe".to_string(),
+            file_size: 42,
+            hashes: EventHashes {
+                sha256: "ab".repeat(32),
+                sha1: None,
+                md5: None,
+            },
+            verdict: ScanStatus::Malicious,
+            detections: Some(12),
+            total_engines: Some(70),
+            permalink: None,
+        }
+    }
+
+    fn host() -> Host {
+        Host::Headless(Arc::new(Headless::new(AppState::default())))
+    }
+
+    #[tokio::test]
+    async fn posts_signed_json_with_custom_headers() {
+        let (url, received) = endpoint(vec![200]).await;
+        let report = deliver(&host(), &webhook(url), &event()).await;
+
+        assert!(report.success);
+        assert_eq!(report.status, Some(200));
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        let request = &received[0];
+
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["event"], "detection");
+        assert_eq!(body["id"], "event-1");
+        assert_eq!(body["verdict"], "Malicious");
+        assert_eq!(body["hashes"]["sha256"], "ab".repeat(32));
+        assert_eq!(request.headers["content-type"], "application/json");
+        assert_eq!(request.headers["x-team"], "security");
+
+        let timestamp = &request.headers[&TIMESTAMP_HEADER.to_ascii_lowercase()];
+        assert_eq!(
+            request.headers[&SIGNATURE_HEADER.to_ascii_lowercase()],
+            format!("sha256={}", sign("s3cret", timestamp, &request.body)),
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_and_rate_limits() {
+        let (url, received) = endpoint(vec![503, 429, 200]).await;
+        let report = deliver(&host(), &webhook(url), &event()).await;
+
+        assert!(report.success);
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let (url, received) = endpoint(vec![400, 200]).await;
+        let report = deliver(&host(), &webhook(url), &event()).await;
+
+        assert!(!report.success);
+        assert_eq!(report.status, Some(400));
+        assert_eq!(received.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_the_attempt_cap() {
+        let (url, received) = endpoint(vec![500, 500, 500, 200]).await;
+        let report = deliver(&host(), &webhook(url), &event()).await;
+
+        assert!(!report.success);
+        assert_eq!(report.status, Some(500));
+        assert_eq!(received.lock().unwrap().len(), 3);
+    }
+}